@@ -0,0 +1,19 @@
+//! cargo bench入口，使用库中的性能测试
+//! 环境变量MERKLE_BENCH_MAX可以调整构建树的最大输入长度，例如1G
+use merkle::bench::{run, BenchConfig};
+use merkle::config::parse_size;
+use merkle::output::{Lang, Report};
+
+fn main() {
+    let mut config = BenchConfig::default();
+    if let Some(max) = std::env::var("MERKLE_BENCH_MAX")
+        .ok()
+        .and_then(|v| parse_size(&v))
+    {
+        config.max_size = max;
+    }
+    let lang = Lang::from_env();
+    run(&config, |r| {
+        print!("{}", Report::Bench(vec![r.clone()]).to_text(lang))
+    });
+}
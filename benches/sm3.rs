@@ -0,0 +1,253 @@
+//! SM3的性能对比：与改写压缩函数之前的实现比较不同输入长度下的吞吐量
+//! 运行cargo bench --bench sm3
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// 改写之前的SM3实现，只用于对比
+mod baseline {
+    pub fn sm3(data: &[u8]) -> [u8; 32] {
+        let mut obj = SM3::new(data);
+        obj.hash()
+    }
+
+    struct SM3 {
+        digest: [u32; 8], // 哈希值（初始值、迭代压缩中间值）
+        length: u64,      // 原始长度（比特）
+        message: Vec<u8>, // 原始或填充后的消息
+    }
+
+    // 初始值
+    const IV: [u32; 8] = [
+        0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d,
+        0xb0fb0e4e,
+    ];
+
+    // 常量
+    const T0_15: u32 = 0x79cc4519;
+    const T16_63: u32 = 0x7a879d8a;
+
+    fn get_tt(j: u32) -> u32 {
+        if j < 16 {
+            T0_15
+        } else if j < 64 {
+            T16_63
+        } else {
+            panic!("SM3 get_tt: j out of range 0-63")
+        }
+    }
+
+    // 布尔函数
+    fn ff0_15(x: u32, y: u32, z: u32) -> u32 {
+        x ^ y ^ z
+    }
+
+    fn ff16_63(x: u32, y: u32, z: u32) -> u32 {
+        (x & y) | (x & z) | (y & z)
+    }
+
+    fn ff(x: u32, y: u32, z: u32, j: u32) -> u32 {
+        if j < 16 {
+            ff0_15(x, y, z)
+        } else if j < 64 {
+            ff16_63(x, y, z)
+        } else {
+            panic!("SM3 ff: j out of range j = {}", j);
+        }
+    }
+
+    fn gg0_15(x: u32, y: u32, z: u32) -> u32 {
+        x ^ y ^ z
+    }
+
+    fn gg16_63(x: u32, y: u32, z: u32) -> u32 {
+        (x & y) | (!x & z)
+    }
+
+    fn gg(x: u32, y: u32, z: u32, j: u32) -> u32 {
+        if j < 16 {
+            gg0_15(x, y, z)
+        } else if j < 64 {
+            gg16_63(x, y, z)
+        } else {
+            panic!("SM3 gg: j out of range j = {}", j);
+        }
+    }
+
+    // 置换函数
+    fn p0(x: u32) -> u32 {
+        x ^ x.rotate_left(9) ^ x.rotate_left(17)
+    }
+
+    fn p1(x: u32) -> u32 {
+        x ^ x.rotate_left(15) ^ x.rotate_left(23)
+    }
+
+    // 把4个u8整数转化为u32整数(大端)
+    fn u8_to_u32(buffer: &[u8], i: usize) -> u32 {
+        u32::from(buffer[i]) << 24
+            | u32::from(buffer[i + 1]) << 16
+            | u32::from(buffer[i + 2]) << 8
+            | u32::from(buffer[i + 3])
+    }
+
+    // 把u32整数转化为4个8u整数（大端）并放入指定位置
+    fn u32_to_u8(buffer: &mut [u8], i: usize, num: u32) {
+        for j in (0..4).rev() {
+            buffer[i * 4 + 3 - j] = (num >> (j * 8)) as u8;
+        }
+    }
+
+    impl SM3 {
+        fn new(data: &[u8]) -> SM3 {
+            SM3 {
+                digest: IV,
+                length: (data.len() << 3) as u64,
+                message: data.to_vec(),
+            }
+        }
+
+        // 设消息m的长度为l比特。首先将比特“1”添加到消息的末尾，再添加k个“0”，k是
+        // 满足l + 1 + k == 448mod512的最小的非负整数。然后再添加一个64位比特串，
+        // 该比特串是长度l的二进制表示。填充后的消息m′的比特长度为512的倍数。
+        fn pad(&mut self) {
+            self.message.push(0x80);
+            let blocksize = 64;
+            // 填充至l + 1 + k == 448mod512
+            let mut fill = self.message.len() % blocksize;
+            if fill > 56 {
+                fill = 120 - fill;
+            } else {
+                fill = 56 - fill;
+            }
+            for _ in 0..fill {
+                self.message.push(0x00);
+            }
+
+            // 添加一个64位比特串，该比特串是长度l的二进制表示
+            // 以大端将原始长度存入message
+            for i in (0..8).rev() {
+                self.message.push((self.length >> (i * 8) & 0xff) as u8)
+            }
+
+            // 按上面的计算方式填充后长度一定是64字节的整数倍
+            debug_assert!(self.message.len().is_multiple_of(64));
+        }
+
+        fn expand(&mut self, w: &mut [u32; 68], w1: &mut [u32; 64], buffer: &[u8; 64]) {
+            // 以大端形式把字节转换为字放入w中
+            for (i, word) in w.iter_mut().take(16).enumerate() {
+                *word = u8_to_u32(buffer, i * 4);
+            }
+            for i in 16..68 {
+                w[i] = p1(w[i - 16] ^ w[i - 9] ^ w[i - 3].rotate_left(15))
+                    ^ w[i - 13].rotate_left(7)
+                    ^ w[i - 6];
+            }
+            for (i, word) in w1.iter_mut().enumerate() {
+                *word = w[i] ^ w[i + 4];
+            }
+        }
+
+        fn cf(&mut self, buffer: &[u8; 64]) {
+            // 消息拓展
+            let mut w: [u32; 68] = [0; 68];
+            let mut w1: [u32; 64] = [0; 64];
+            self.expand(&mut w, &mut w1, buffer);
+            // ABCDEFGH <- V
+            // 将V复制到r中，使用ABCDEFGH作为索引
+            let mut r = self.digest;
+            let (a, b, c, d, e, f, g, h) = (0, 1, 2, 3, 4, 5, 6, 7);
+            let mut ss1: u32;
+            let mut ss2: u32;
+            let mut tt1: u32;
+            let mut tt2: u32;
+
+            for i in 0..64 {
+                ss1 = (r[a]
+                    .rotate_left(12)
+                    .wrapping_add(r[e])
+                    .wrapping_add(get_tt(i).rotate_left(i)))
+                .rotate_left(7);
+                ss2 = ss1 ^ (r[a].rotate_left(12));
+                tt1 = ff(r[a], r[b], r[c], i)
+                    .wrapping_add(r[d])
+                    .wrapping_add(ss2)
+                    .wrapping_add(w1[i as usize]);
+                tt2 = gg(r[e], r[f], r[g], i)
+                    .wrapping_add(r[h])
+                    .wrapping_add(ss1)
+                    .wrapping_add(w[i as usize]);
+                r[d] = r[c];
+                r[c] = r[b].rotate_left(9);
+                r[b] = r[a];
+                r[a] = tt1;
+                r[h] = r[g];
+                r[g] = r[f].rotate_left(19);
+                r[f] = r[e];
+                r[e] = p0(tt2);
+            }
+            for (d, v) in self.digest.iter_mut().zip(r.iter()) {
+                *d ^= v;
+            }
+        }
+
+        fn hash(&mut self) -> [u8; 32] {
+            let mut output: [u8; 32] = [0; 32];
+            let mut buffer: [u8; 64] = [0; 64];
+            // 填充
+            self.pad();
+
+            // 将填充后的消息m′按512比特进行分组：m′ = B(0)B(1)...B(n−1)
+            // 每次将512比特数据从message存入buffer后使用压缩函数迭代
+            for j in 0..(self.message.len() / 64) {
+                for i in (j * 64)..(j * 64 + 64) {
+                    buffer[i - j * 64] = self.message[i];
+                }
+                self.cf(&buffer);
+            }
+
+            // 以字节形式（大端）将散列值输出到output中
+            for (i, num) in self.digest.iter().enumerate() {
+                u32_to_u8(&mut output, i, *num);
+            }
+            output
+        }
+    }
+}
+
+// 在time内反复计算哈希，返回吞吐量(MB/s)
+fn throughput(hash: fn(&[u8]) -> [u8; 32], data: &[u8], time: Duration) -> f64 {
+    let start = Instant::now();
+    let mut bytes = 0;
+    while start.elapsed() < time {
+        black_box(hash(black_box(data)));
+        bytes += data.len();
+    }
+    bytes as f64 / start.elapsed().as_secs_f64() / 1e6
+}
+
+// 两种实现交替多次测量，各取最好的一次，减少其他进程造成的波动
+fn compare(old: fn(&[u8]) -> [u8; 32], new: fn(&[u8]) -> [u8; 32], data: &[u8]) -> (f64, f64) {
+    let time = Duration::from_millis(50);
+    let mut best = (0f64, 0f64);
+    for _ in 0..20 {
+        best.0 = best.0.max(throughput(old, data, time));
+        best.1 = best.1.max(throughput(new, data, time));
+    }
+    best
+}
+
+fn main() {
+    for size in [64, 1 << 10, 16 << 20] {
+        let data: Vec<u8> = (0..size).map(|i| (i * 31 + 7) as u8).collect();
+        assert_eq!(baseline::sm3(&data), merkle::sm3::sm3(&data));
+        let (old, new) = compare(baseline::sm3, merkle::sm3::sm3, &data);
+        println!(
+            "{:>9}  旧实现 {:7.1} MB/s  新实现 {:7.1} MB/s  {:.2}x",
+            size,
+            old,
+            new,
+            new / old
+        );
+    }
+}
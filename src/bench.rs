@@ -0,0 +1,184 @@
+//! 性能测试：SM3吞吐量、构建Merkle树、生成proof、比较两棵树和验证proof
+// 每项测试重复执行直到超过给定的时间，结果换算为MB/s和ops/s
+// cargo bench和命令行的bench子命令都使用这里的实现
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::{chunking::Blocking, proof::Proof, sm3::sm3, tree::MerkleTree};
+
+const KB: usize = 1024;
+const MB: usize = 1024 * KB;
+const GB: usize = 1024 * MB;
+
+// SM3测试的输入长度
+const SM3_SIZES: &[usize] = &[64, KB, 64 * KB, MB];
+// 构建树测试的输入长度，超过max_size的会被跳过
+const BUILD_SIZES: &[usize] = &[KB, 32 * KB, MB, 32 * MB, GB];
+// 生成proof、比较和验证使用的数据长度
+const PROOF_SIZE: usize = MB;
+
+pub struct BenchConfig {
+    pub max_size: usize,        // 构建树的最大输入长度
+    pub blocksizes: Vec<usize>, // 构建树使用的数据块大小
+    pub min_time: Duration,     // 每项测试至少运行的时间
+}
+
+impl Default for BenchConfig {
+    fn default() -> BenchConfig {
+        BenchConfig {
+            max_size: 32 * MB,
+            blocksizes: vec![KB, 4 * KB, 64 * KB],
+            min_time: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchResult {
+    pub name: String,
+    pub bytes: usize, // 每次操作处理的字节数，0表示不统计吞吐量
+    pub ops: u64,     // 执行次数
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn ops_per_sec(&self) -> f64 {
+        self.ops as f64 / self.elapsed.as_secs_f64()
+    }
+
+    pub fn mb_per_sec(&self) -> Option<f64> {
+        if self.bytes == 0 {
+            return None;
+        }
+        Some(self.ops_per_sec() * self.bytes as f64 / MB as f64)
+    }
+}
+
+// 把字节数显示为1KB、32MB这样的形式
+pub fn size_name(n: usize) -> String {
+    match n {
+        n if n >= GB && n.is_multiple_of(GB) => format!("{}GB", n / GB),
+        n if n >= MB && n.is_multiple_of(MB) => format!("{}MB", n / MB),
+        n if n >= KB && n.is_multiple_of(KB) => format!("{}KB", n / KB),
+        n => format!("{}B", n),
+    }
+}
+
+// 用线性同余生成测试数据，保证每次运行的数据一致
+fn test_data(len: usize) -> Vec<u8> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..len)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 56) as u8
+        })
+        .collect()
+}
+
+// 重复执行f直到超过min_time，至少执行一次
+fn measure<F: FnMut()>(name: String, bytes: usize, min_time: Duration, mut f: F) -> BenchResult {
+    let start = Instant::now();
+    let mut ops = 0;
+    loop {
+        f();
+        ops += 1;
+        let elapsed = start.elapsed();
+        if elapsed >= min_time {
+            return BenchResult {
+                name,
+                bytes,
+                ops,
+                elapsed,
+            };
+        }
+    }
+}
+
+// 执行全部测试，每完成一项就调用一次report
+pub fn run<R: FnMut(&BenchResult)>(config: &BenchConfig, mut report: R) -> Vec<BenchResult> {
+    let mut results = Vec::new();
+    let mut record = |result: BenchResult| {
+        report(&result);
+        results.push(result);
+    };
+    let largest = BUILD_SIZES
+        .iter()
+        .copied()
+        .filter(|n| *n <= config.max_size)
+        .chain([PROOF_SIZE, *SM3_SIZES.last().unwrap()])
+        .max()
+        .unwrap();
+    let data = test_data(largest);
+
+    for &n in SM3_SIZES {
+        let input = &data[..n];
+        record(measure(
+            format!("sm3/{}", size_name(n)),
+            n,
+            config.min_time,
+            || {
+                black_box(sm3(black_box(input)));
+            },
+        ));
+    }
+
+    for &n in BUILD_SIZES.iter().filter(|n| **n <= config.max_size) {
+        for &bs in &config.blocksizes {
+            let blocking = Blocking::Fixed(bs);
+            let input = &data[..n];
+            record(measure(
+                format!("build/{}/bs={}", size_name(n), bs),
+                n,
+                config.min_time,
+                || {
+                    black_box(MerkleTree::from_bytes(black_box(input), &blocking).unwrap());
+                },
+            ));
+        }
+    }
+
+    // 后面的测试使用1MB数据、1KB数据块构建的树
+    let input = &data[..PROOF_SIZE];
+    let tree = MerkleTree::from_bytes(input, &Blocking::Fixed(KB)).unwrap();
+    let leaves = tree.leaves;
+    let mut i = 0;
+    record(measure(
+        format!("gen_proof/{}leaves", leaves),
+        0,
+        config.min_time,
+        || {
+            black_box(tree.gen_proof(i % leaves).unwrap());
+            i += 1;
+        },
+    ));
+
+    // 修改其中几个数据块后比较
+    let mut modified = input.to_vec();
+    for k in 0..8 {
+        modified[k * PROOF_SIZE / 8] ^= 0xff;
+    }
+    let other = MerkleTree::from_bytes(&modified, &Blocking::Fixed(KB)).unwrap();
+    record(measure(
+        format!("compare/{}leaves", leaves),
+        0,
+        config.min_time,
+        || {
+            black_box(tree.compare(&other).unwrap());
+        },
+    ));
+
+    let root = tree.root_hash().unwrap();
+    let proof = Proof::new(&tree, input[..KB].to_vec(), 0, KB).unwrap();
+    record(measure(
+        format!("verify/{}leaves", leaves),
+        0,
+        config.min_time,
+        || {
+            black_box(proof.verify(&root).is_ok());
+        },
+    ));
+
+    results
+}
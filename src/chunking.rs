@@ -0,0 +1,186 @@
+//! 数据分块：固定大小分块与基于内容的FastCDC分块
+// 固定大小分块时，在文件中插入或删除一个字节会使之后的所有数据块都发生偏移，
+// 而内容定义分块(CDC)由数据内容本身决定切分点，修改只会影响附近的少数数据块
+use alloc::vec::Vec;
+
+use crate::error::{MerkleError, Reason, Result};
+
+// 一个数据块在原始数据中的位置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chunk {
+    pub offset: usize, // 起始偏移(字节)
+    pub length: usize, // 长度(字节)
+}
+
+impl Chunk {
+    // 从原始数据中取出这个数据块
+    pub fn slice<'a>(&self, data: &'a [u8]) -> &'a [u8] {
+        &data[self.offset..self.offset + self.length]
+    }
+}
+
+// 用splitmix64在编译期生成gear哈希表
+const fn gen_gear() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x5348_4133_4d45_524b; // "SHA3MERK"
+    let mut i = 0;
+    while i < 256 {
+        seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+const GEAR: [u64; 256] = gen_gear();
+
+// 取fp的高bits位作为判断切分点的掩码
+fn mask(bits: u32) -> u64 {
+    if bits == 0 {
+        0
+    } else {
+        u64::MAX << (64 - bits.min(63))
+    }
+}
+
+// FastCDC分块器，min_size <= 数据块长度 <= max_size，平均长度约为avg_size
+// 字段只能通过new设置，保证三个大小始终有效
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastCdc {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+}
+
+impl FastCdc {
+    // 三个大小需满足 0 < min_size <= avg_size <= max_size
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<FastCdc> {
+        if !(0 < min_size && min_size <= avg_size && avg_size <= max_size) {
+            return Err(MerkleError::InvalidArgument(Reason::FastCdc {
+                min: min_size,
+                avg: avg_size,
+                max: max_size,
+            }));
+        }
+        Ok(FastCdc {
+            min_size,
+            avg_size,
+            max_size,
+        })
+    }
+
+    // 以avg_size为平均长度的默认参数(min = avg / 4, max = avg * 8)
+    pub fn with_avg(avg_size: usize) -> Result<FastCdc> {
+        let max_size = avg_size
+            .checked_mul(8)
+            .ok_or(MerkleError::InvalidArgument(Reason::CdcAvg(avg_size)))?;
+        FastCdc::new((avg_size / 4).max(1), avg_size, max_size)
+    }
+
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    // 返回data开头第一个数据块的长度
+    // 归一化分块：长度小于avg_size时使用更严格的掩码，超过后使用更宽松的掩码，
+    // 使数据块长度集中在avg_size附近
+    pub fn cut(&self, data: &[u8]) -> usize {
+        if data.len() <= self.min_size {
+            return data.len();
+        }
+        let end = data.len().min(self.max_size);
+        let normal = end.min(self.avg_size);
+        let bits = usize::BITS - self.avg_size.leading_zeros() - 1; // log2(avg_size)
+        let mask_s = mask(bits + 1);
+        let mask_l = mask(bits.saturating_sub(1));
+
+        let mut fp: u64 = 0;
+        let mut i = self.min_size;
+        while i < normal {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & mask_s == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        while i < end {
+            fp = (fp << 1).wrapping_add(GEAR[data[i] as usize]);
+            if fp & mask_l == 0 {
+                return i + 1;
+            }
+            i += 1;
+        }
+        end
+    }
+
+    // 把数据切分为若干数据块，空数据得到一个长度为0的数据块
+    pub fn chunks(&self, data: &[u8]) -> Vec<Chunk> {
+        let mut result = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let length = self.cut(&data[offset..]);
+            result.push(Chunk { offset, length });
+            offset += length;
+        }
+        if result.is_empty() {
+            result.push(Chunk {
+                offset: 0,
+                length: 0,
+            });
+        }
+        result
+    }
+}
+
+// 构建Merkle树时使用的分块方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Blocking {
+    Fixed(usize),     // 固定大小分块，与config::data_to_blocks一致
+    FastCdc(FastCdc), // 基于内容的分块
+}
+
+impl Blocking {
+    // 记录在树中的数据块大小，内容定义分块没有固定大小，记为0
+    pub fn blocksize(&self) -> usize {
+        match self {
+            Blocking::Fixed(blocksize) => *blocksize,
+            Blocking::FastCdc(_) => 0,
+        }
+    }
+
+    // 计算所有数据块的位置，固定分块的大小为0时返回错误
+    pub fn split(&self, data: &[u8]) -> Result<Vec<Chunk>> {
+        match self {
+            Blocking::Fixed(0) => Err(MerkleError::InvalidArgument(Reason::ZeroBlocksize)),
+            Blocking::Fixed(blocksize) => Ok(fixed_chunks(data.len(), *blocksize)),
+            Blocking::FastCdc(cdc) => Ok(cdc.chunks(data)),
+        }
+    }
+}
+
+// 长度为len的数据按blocksize固定分块的位置，blocksize必须大于0
+// 与data_to_blocks相同，末尾总会有一个长度小于blocksize的数据块(可能为空)
+pub(crate) fn fixed_chunks(len: usize, blocksize: usize) -> Vec<Chunk> {
+    let mut result: Vec<Chunk> = (0..len / blocksize)
+        .map(|i| Chunk {
+            offset: i * blocksize,
+            length: blocksize,
+        })
+        .collect();
+    result.push(Chunk {
+        offset: len / blocksize * blocksize,
+        length: len % blocksize,
+    });
+    result
+}
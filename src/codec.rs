@@ -0,0 +1,89 @@
+//! 二进制编码的辅助函数(变长整数、带长度前缀的字节串)
+use alloc::vec::Vec;
+
+use crate::{
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+};
+
+// 以LEB128变长格式写入无符号整数
+pub(crate) fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+// 写入长度前缀和字节串
+pub(crate) fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    put_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+pub(crate) fn invalid(reason: Reason) -> MerkleError {
+    MerkleError::InvalidData(reason)
+}
+
+// 从字节串中按顺序读取数据
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, pos: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.pos < len {
+            return Err(invalid(Reason::Truncated));
+        }
+        let result = &self.data[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(result)
+    }
+
+    // 带长度前缀的摘要
+    pub(crate) fn digest(&mut self) -> Result<Digest> {
+        Digest::from_slice(self.bytes()?).ok_or_else(|| invalid(Reason::DigestLength))
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.u8()?;
+            if shift >= 64 {
+                return Err(invalid(Reason::VarintOverflow));
+            }
+            result |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+            shift += 7;
+        }
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize> {
+        let v = self.varint()?;
+        if v > usize::MAX as u64 {
+            return Err(invalid(Reason::NumberOutOfRange));
+        }
+        Ok(v as usize)
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.usize()?;
+        self.take(len)
+    }
+}
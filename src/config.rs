@@ -1,11 +1,11 @@
-use std::fs;
+use std::fmt::Display;
 
-use crate::hash::{hash_to_str, HashSM3};
-use crate::proof::Proof;
-use crate::tree::MerkleTree;
+use crate::digest::Digest;
+use crate::error::{MerkleError, Reason, Result};
+use crate::output::{tr, Lang, Msg};
 
 // 把文件数据切分为大小为'blocksize'字节的数据块组
-pub fn data_to_blocks(data: &Vec<u8>, blocksize: usize) -> Vec<Vec<u8>> {
+pub fn data_to_blocks(data: &[u8], blocksize: usize) -> Vec<Vec<u8>> {
     let mut data_blocks = Vec::new();
     let mut counter: usize = 0; // counter记录已经往临时数组里放了多少数据
     let mut temp: Vec<u8> = Vec::with_capacity(blocksize); // 容积为blocksize的临时数组
@@ -27,93 +27,381 @@ pub fn data_to_blocks(data: &Vec<u8>, blocksize: usize) -> Vec<Vec<u8>> {
     data_blocks
 }
 
+// 进程退出码
+pub const EXIT_OK: i32 = 0; // 成功
+pub const EXIT_MISMATCH: i32 = 1; // 比较发现不同、验证失败或检查不通过
+pub const EXIT_USAGE: i32 = 2; // 命令行参数有误
+pub const EXIT_ERROR: i32 = 3; // 读写文件等运行错误
+
+const DEFAULT_BLOCKSIZE: usize = 1024;
+
+// 子命令名称、用法和说明
+const COMMANDS: &[(&str, Msg, Msg)] = &[
+    ("root", Msg::RootUsage, Msg::RootAbout),
+    ("build", Msg::BuildUsage, Msg::BuildAbout),
+    ("compare", Msg::CompareUsage, Msg::CompareAbout),
+    ("prove", Msg::ProveUsage, Msg::ProveAbout),
+    ("verify", Msg::VerifyUsage, Msg::VerifyAbout),
+    ("diff", Msg::DiffUsage, Msg::DiffAbout),
+    ("bench", Msg::BenchUsage, Msg::BenchAbout),
+    ("manifest", Msg::ManifestUsage, Msg::ManifestAbout),
+    ("check", Msg::CheckUsage, Msg::CheckAbout),
+    ("show", Msg::ShowUsage, Msg::ShowAbout),
+];
+
+pub fn usage(lang: Lang) -> String {
+    let mut out = tr(lang, Msg::Usage, &[]);
+    out.push('\n');
+    for (name, _, about) in COMMANDS {
+        out.push_str(&format!("  {:<10}{}\n", name, tr(lang, *about, &[])));
+    }
+    out.push_str(&format!(
+        "  {:<10}{}\n\n",
+        "help",
+        tr(lang, Msg::HelpAbout, &[])
+    ));
+    out.push_str(&tr(lang, Msg::CommonHelp, &[]));
+    out
+}
+
+pub fn command_usage(name: &str, lang: Lang) -> Option<String> {
+    COMMANDS
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, usage, about)| {
+            tr(
+                lang,
+                Msg::CommandUsage,
+                &[
+                    &tr(lang, *usage, &[]),
+                    &tr(lang, *about, &[]),
+                    &tr(lang, Msg::CommonHelp, &[]),
+                ],
+            )
+        })
+}
+
+// 输出信息使用的语言：参数中最后一个有效的--lang，否则根据环境变量
+// 在完整解析参数之前确定，参数有误时也能输出对应语言的错误信息
+pub fn lang_from_args(args: &[String]) -> Lang {
+    let mut lang = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = match arg.strip_prefix("--lang") {
+            Some("") => iter.next().map(String::as_str),
+            Some(v) => v.strip_prefix('='),
+            None => None,
+        };
+        if let Some(l) = value.and_then(Lang::parse) {
+            lang = Some(l);
+        }
+    }
+    lang.unwrap_or_else(Lang::from_env)
+}
+
+// 解析字节数，支持K、M、G后缀(1024进制)
+pub fn parse_size(s: &str) -> Option<usize> {
+    let (num, shift) = match s.to_ascii_uppercase().trim_end_matches('B') {
+        n if n.ends_with('K') => (n[..n.len() - 1].to_string(), 10),
+        n if n.ends_with('M') => (n[..n.len() - 1].to_string(), 20),
+        n if n.ends_with('G') => (n[..n.len() - 1].to_string(), 30),
+        n => (n.to_string(), 0),
+    };
+    num.parse::<usize>().ok()?.checked_mul(1 << shift)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Root {
+        file: String,
+    },
+    Build {
+        file: String,
+    },
+    Compare {
+        file1: String,
+        file2: String,
+    },
+    Prove {
+        file: String,
+        index: usize,
+        out: Option<String>, // 写出proof文件的路径
+    },
+    Verify {
+        file1: String,
+        file2: String,
+        index: usize,
+    },
+    VerifyProof {
+        root: Digest,  // 可信的根哈希
+        proof: String, // proof文件
+        block: String, // 数据块文件或十六进制数据
+    },
+    Diff {
+        old: String,
+        new: String,
+        out: String,
+        cdc: bool,
+    },
+    Bench {
+        max_size: usize,          // 构建树测试的最大输入长度
+        millis: u64,              // 每项测试运行的毫秒数
+        blocksize: Option<usize>, // 只测试给定的数据块大小
+    },
+    Manifest {
+        dir: String,
+        out: String,
+        leaves: bool, // 是否记录叶子哈希
+    },
+    Check {
+        dir: String,
+        manifest: String,
+    },
+    Show {
+        file: String,
+        other: Option<String>, // 用于标出不同节点的第二个文件
+        depth: Option<usize>,  // 从根节点起展开的层数
+        dot: bool,
+    },
+    Help(Option<String>),
+}
+
 pub struct Config {
-    pub operator: String,
-    pub file1: String,
-    pub file2: String,
+    pub command: Command,
     pub blocksize: usize,
-    pub index: usize,
+    pub hash: Option<Digest>, // 期望的根哈希
+    pub format: Format,
+    pub lang: Lang, // 文本输出使用的语言
 }
 
-impl Config {
-    fn new(args: &Vec<String>) -> Config {
-        if args.len() == 5 {
-            // ./merkle compare file1 file2 blocksize
-            Config {
-                operator: args[1].clone(),
-                file1: args[2].clone(),
-                file2: args[3].clone(),
-                blocksize: args[4].clone().parse().unwrap(),
-                index: 0,
-            }
-        } else if args.len() == 6 {
-            // ./merkle proof file1(验证proof) file2(生成proof) blocksize index
-            Config {
-                operator: args[1].clone(),
-                file1: args[2].clone(),
-                file2: args[3].clone(),
-                blocksize: args[4].clone().parse().unwrap(),
-                index: args[5].clone().parse().unwrap(),
+// 需要取值的选项
+const VALUE_FLAGS: &[&str] = &[
+    "block-size",
+    "index",
+    "hash",
+    "format",
+    "lang",
+    "out",
+    "max-size",
+    "time",
+    "root",
+    "proof",
+    "block",
+    "depth",
+];
+// 开关选项
+const SWITCH_FLAGS: &[&str] = &["cdc", "dot", "help", "no-leaves"];
+
+// 把参数分为位置参数和选项
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<(String, String)>,
+    lang: Lang, // 错误信息使用的语言
+}
+
+// 按语言生成的命令行参数错误
+fn arg_error(lang: Lang, msg: Msg, args: &[&dyn Display]) -> MerkleError {
+    MerkleError::InvalidArgument(Reason::Message(tr(lang, msg, args)))
+}
+
+impl Args {
+    fn parse(args: &[String], lang: Lang) -> Result<Args> {
+        let mut result = Args {
+            positional: vec![],
+            flags: vec![],
+            lang,
+        };
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let name = match arg.as_str() {
+                "-h" => "help",
+                "-o" => "out",
+                a => match a.strip_prefix("--") {
+                    Some(name) => name,
+                    None => {
+                        result.positional.push(arg.clone());
+                        continue;
+                    }
+                },
+            };
+            // 支持 --name=value 的写法
+            let (name, inline) = match name.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => (name, None),
+            };
+            if VALUE_FLAGS.contains(&name) {
+                let value = match inline {
+                    Some(v) => v,
+                    None => iter
+                        .next()
+                        .cloned()
+                        .ok_or_else(|| arg_error(lang, Msg::MissingValue, &[&name]))?,
+                };
+                result.flags.push((name.to_string(), value));
+            } else if SWITCH_FLAGS.contains(&name) && inline.is_none() {
+                result.flags.push((name.to_string(), String::new()));
+            } else {
+                return Err(arg_error(lang, Msg::UnknownOption, &[arg]));
             }
-        } else {
-            eprintln!(
-                "./merkle [compare file1 file2 blocksize | proof file1(验证proof) file2(生成proof) blocksize index]"
-            );
-            std::process::exit(1);
+        }
+        Ok(result)
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    fn number<N: std::str::FromStr>(&self, name: &str) -> Result<Option<N>> {
+        match self.get(name) {
+            Some(v) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| arg_error(self.lang, Msg::InvalidValue, &[&name, &v])),
+            None => Ok(None),
         }
     }
 }
 
-pub fn run(args: Vec<String>) {
-    let config = Config::new(&args);
-
-    // true代表接下来进行两个文件的比较，false表示利用文件2验证文件1中的某个数据块是否存在于文件2中
-    let flag = config.operator.eq(&String::from("compare"));
-
-    // 读取文件
-    let f1 = data_to_blocks(&fs::read(config.file1).unwrap(), config.blocksize);
-    let f2 = data_to_blocks(&fs::read(config.file2).unwrap(), config.blocksize);
-    // 构建Merkle树
-    let tree1 = MerkleTree::new(&f1, config.blocksize);
-    let tree2 = MerkleTree::new(&f2, config.blocksize);
-
-    if flag {
-        // compare
-        let diffent = tree1.compare(&tree2);
-
-        println!(
-            "树1根哈希: {}\n树2根哈希: {}",
-            hash_to_str(&tree1.root_hash()),
-            hash_to_str(&tree2.root_hash())
-        );
-
-        println!(
-            "对比得到不同的数据块为(blocksize: {}) \n{:?}",
-            tree1.blocksize, diffent
-        )
-    } else {
-        // proof
-        if config.index >= tree2.leaves {
-            eprintln!(
-                "生成proof失败， 下标({})越界， 树2只有{}个数据块",
-                config.index, tree2.leaves
-            );
-            std::process::exit(1);
+impl Config {
+    pub fn new(args: &[String]) -> Result<Config> {
+        let args = args.get(1..).unwrap_or(&[]);
+        let msg_lang = lang_from_args(args);
+        let err = |msg: Msg, args: &[&dyn Display]| arg_error(msg_lang, msg, args);
+        let parsed = Args::parse(args, msg_lang)?;
+        let mut pos = parsed.positional.iter().cloned();
+        let name = pos.next();
+
+        let format = match parsed.get("format") {
+            None | Some("text") => Format::Text,
+            Some("json") => Format::Json,
+            Some(f) => return Err(err(Msg::UnknownFormat, &[&f])),
+        };
+        let blocksize = parsed.number("block-size")?.unwrap_or(DEFAULT_BLOCKSIZE);
+        if blocksize == 0 {
+            return Err(err(Msg::ZeroBlocksize, &[]));
         }
-        println!("利用树2生成下标为{}处的Proof： ", config.index);
-
-        // 生成proof
-        let proof = Proof::new(
-            &tree2,
-            f1.get(config.index).unwrap().clone(),
-            config.index,
-            config.blocksize,
-        );
-        proof.show();
-        println!(
-            "目标树的根杂凑值为{}\n验证proof结果 {}",
-            hash_to_str(&tree2.root_hash()),
-            tree2.validate(&proof)
-        )
+        let lang = match parsed.get("lang") {
+            Some(l) => Lang::parse(l).ok_or_else(|| err(Msg::UnknownLang, &[&l]))?,
+            None => Lang::from_env(),
+        };
+        let hash = match parsed.get("hash") {
+            Some(h) => Some(
+                h.parse::<Digest>()
+                    .map_err(|_| err(Msg::InvalidHash, &[&h]))?,
+            ),
+            None => None,
+        };
+
+        let command = match name.as_deref() {
+            None => Command::Help(None),
+            Some("help") => Command::Help(pos.next()),
+            Some(n) if parsed.has("help") => Command::Help(Some(n.to_string())),
+            Some(n) => {
+                let mut arg = |what: Msg| {
+                    pos.next()
+                        .ok_or_else(|| err(Msg::MissingArg, &[&n, &tr(msg_lang, what, &[])]))
+                };
+                let index = parsed.number("index")?;
+                let need_index = || index.ok_or_else(|| err(Msg::NeedOption, &[&n, &"index"]));
+                let command = match n {
+                    "root" => Command::Root {
+                        file: arg(Msg::ArgFile)?,
+                    },
+                    "build" => Command::Build {
+                        file: arg(Msg::ArgFile)?,
+                    },
+                    "compare" => Command::Compare {
+                        file1: arg(Msg::ArgFile1)?,
+                        file2: arg(Msg::ArgFile2)?,
+                    },
+                    "prove" => Command::Prove {
+                        file: arg(Msg::ArgFile)?,
+                        index: need_index()?,
+                        out: parsed.get("out").map(String::from),
+                    },
+                    "verify" if parsed.has("proof") => Command::VerifyProof {
+                        root: parsed
+                            .get("root")
+                            .and_then(|r| r.parse().ok())
+                            .ok_or_else(|| err(Msg::NeedHexRoot, &[]))?,
+                        proof: parsed.get("proof").unwrap().to_string(),
+                        block: parsed
+                            .get("block")
+                            .map(String::from)
+                            .ok_or_else(|| err(Msg::NeedOption, &[&n, &"block"]))?,
+                    },
+                    "verify" => Command::Verify {
+                        file1: arg(Msg::ArgFile1)?,
+                        file2: arg(Msg::ArgFile2)?,
+                        index: need_index()?,
+                    },
+                    "diff" => Command::Diff {
+                        old: arg(Msg::ArgOldFile)?,
+                        new: arg(Msg::ArgNewFile)?,
+                        out: parsed
+                            .get("out")
+                            .map(String::from)
+                            .ok_or_else(|| err(Msg::NeedOption, &[&n, &"out"]))?,
+                        cdc: parsed.has("cdc"),
+                    },
+                    "bench" => Command::Bench {
+                        max_size: match parsed.get("max-size") {
+                            Some(v) => parse_size(v)
+                                .ok_or_else(|| err(Msg::InvalidValue, &[&"max-size", &v]))?,
+                            None => 32 << 20,
+                        },
+                        millis: parsed.number("time")?.unwrap_or(500),
+                        blocksize: parsed.get("block-size").map(|_| blocksize),
+                    },
+                    "manifest" => Command::Manifest {
+                        dir: arg(Msg::ArgDir)?,
+                        out: arg(Msg::ArgManifest)?,
+                        leaves: !parsed.has("no-leaves"),
+                    },
+                    "check" => Command::Check {
+                        dir: arg(Msg::ArgDir)?,
+                        manifest: arg(Msg::ArgManifest)?,
+                    },
+                    "show" => Command::Show {
+                        file: arg(Msg::ArgFile)?,
+                        other: pos.next(),
+                        depth: parsed.number("depth")?,
+                        dot: parsed.has("dot"),
+                    },
+                    _ => return Err(err(Msg::UnknownCommand, &[&n])),
+                };
+                if let Some(extra) = pos.next() {
+                    return Err(err(Msg::ExtraArg, &[&extra]));
+                }
+                // 只有root和build会检查期望的根哈希
+                let checks_hash = matches!(command, Command::Root { .. } | Command::Build { .. });
+                if hash.is_some() && !checks_hash {
+                    return Err(err(Msg::UnknownOption, &[&"--hash"]));
+                }
+                command
+            }
+        };
+
+        Ok(Config {
+            command,
+            blocksize,
+            hash,
+            format,
+            lang,
+        })
     }
 }
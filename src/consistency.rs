@@ -0,0 +1,189 @@
+//! 只追加的Merkle树的历史版本：旧版本的根哈希、针对旧版本的proof和两个版本之间的一致性proof
+// 叶子数量为n的树的左子树正好包含前k个叶子(k为小于n的最大的2的幂)，右子树包含其余叶子，
+// 与RFC 6962中MTH的定义相同，因此可以直接使用其中的PATH和PROOF算法，只是合并哈希使用Digest::combine。
+// 树的前m个叶子构成的树就是叶子数量为m时的历史版本，其中的完整子树可以直接取当前树中的节点
+use alloc::vec::Vec;
+
+use crate::{
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    proof::{fold_path, LeafProof},
+    tree::{path_positions, MerkleTree},
+};
+
+// 小于n的最大的2的幂，n至少为2
+fn split(n: usize) -> usize {
+    1 << (usize::BITS - 1 - (n - 1).leading_zeros())
+}
+
+impl MerkleTree {
+    // 前size个叶子构成的树的根哈希
+    pub fn prefix_root(&self, size: usize) -> Result<Digest> {
+        self.check_size(size)?;
+        Ok(self.range_hash(0, size))
+    }
+
+    // 第index个叶子在前size个叶子构成的树中的proof
+    pub fn prefix_proof(&self, index: usize, size: usize) -> Result<LeafProof> {
+        self.check_size(size)?;
+        let pos_chain = path_positions(index, size).ok_or(MerkleError::IndexOutOfRange {
+            index,
+            leaves: size,
+        })?;
+        let mut chain = Vec::new();
+        self.path(index, 0, size, &mut chain);
+        let leaf = self.nodes[0][index];
+        Ok(LeafProof {
+            roothash: fold_path(leaf, &chain, &pos_chain),
+            chain,
+            pos_chain,
+            leaf,
+            index,
+            leaves: size,
+            blocksize: self.blocksize,
+        })
+    }
+
+    // 叶子数量从old_size增长到size的一致性proof
+    pub fn consistency_proof(&self, old_size: usize, size: usize) -> Result<ConsistencyProof> {
+        self.check_size(size)?;
+        if old_size == 0 || old_size > size {
+            return Err(MerkleError::InvalidArgument(Reason::ConsistencySize {
+                old: old_size,
+                size,
+            }));
+        }
+        let mut chain = Vec::new();
+        self.subproof(old_size, 0, size, true, &mut chain);
+        Ok(ConsistencyProof {
+            old_size,
+            size,
+            chain,
+        })
+    }
+
+    fn check_size(&self, size: usize) -> Result<()> {
+        if size == 0 || size > self.leaves {
+            return Err(MerkleError::InvalidArgument(Reason::NoVersion {
+                leaves: self.leaves,
+                size,
+            }));
+        }
+        Ok(())
+    }
+
+    // 叶子[start, end)构成的树的根哈希，对齐的完整子树直接取已有节点
+    fn range_hash(&self, start: usize, end: usize) -> Digest {
+        let n = end - start;
+        if n.is_power_of_two() && start.is_multiple_of(n) {
+            let level = n.trailing_zeros() as usize;
+            return self.nodes[level][start >> level];
+        }
+        let k = split(n);
+        Digest::combine(
+            &self.range_hash(start, start + k),
+            &self.range_hash(start + k, end),
+        )
+    }
+
+    // RFC 6962的PATH(m, D[start:end])，认证路径从下至上
+    fn path(&self, m: usize, start: usize, end: usize, out: &mut Vec<Digest>) {
+        let n = end - start;
+        if n <= 1 {
+            return;
+        }
+        let k = split(n);
+        if m < k {
+            self.path(m, start, start + k, out);
+            out.push(self.range_hash(start + k, end));
+        } else {
+            self.path(m - k, start + k, end, out);
+            out.push(self.range_hash(start, start + k));
+        }
+    }
+
+    // RFC 6962的SUBPROOF(m, D[start:end], b)
+    fn subproof(&self, m: usize, start: usize, end: usize, whole: bool, out: &mut Vec<Digest>) {
+        let n = end - start;
+        if m == n {
+            if !whole {
+                out.push(self.range_hash(start, end));
+            }
+            return;
+        }
+        let k = split(n);
+        if m <= k {
+            self.subproof(m, start, start + k, whole, out);
+            out.push(self.range_hash(start + k, end));
+        } else {
+            self.subproof(m - k, start + k, end, false, out);
+            out.push(self.range_hash(start, start + k));
+        }
+    }
+}
+
+// 证明叶子数量为old_size的树是叶子数量为size的树的前缀
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistencyProof {
+    pub old_size: usize,
+    pub size: usize,
+    pub chain: Vec<Digest>,
+}
+
+impl ConsistencyProof {
+    // 用可信的新旧两个根哈希验证，算法同RFC 9162 2.1.4.2
+    pub fn verify(&self, old_root: &Digest, root: &Digest) -> Result<()> {
+        let fail = || Err(MerkleError::VerifyFailed(Reason::Consistency));
+        if self.old_size == 0 || self.old_size > self.size {
+            return fail();
+        }
+        if self.old_size == self.size {
+            if !self.chain.is_empty() {
+                return fail();
+            }
+            return if old_root == root { Ok(()) } else { fail() };
+        }
+
+        // 旧树是完整的二叉树时，旧的根哈希本身就是新树中的节点，proof中省略了它
+        let mut chain = self.chain.iter();
+        let first = if self.old_size.is_power_of_two() {
+            *old_root
+        } else {
+            match chain.next() {
+                Some(h) => *h,
+                None => return fail(),
+            }
+        };
+        let mut old_node = self.old_size - 1;
+        let mut new_node = self.size - 1;
+        while old_node & 1 == 1 {
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+        let (mut fr, mut sr) = (first, first);
+        for c in chain {
+            if new_node == 0 {
+                return fail();
+            }
+            if old_node & 1 == 1 || old_node == new_node {
+                fr = Digest::combine(c, &fr);
+                sr = Digest::combine(c, &sr);
+                while old_node & 1 == 0 && old_node != 0 {
+                    old_node >>= 1;
+                    new_node >>= 1;
+                }
+            } else {
+                sr = Digest::combine(&sr, c);
+            }
+            old_node >>= 1;
+            new_node >>= 1;
+        }
+        if new_node != 0 {
+            return fail();
+        }
+        if fr != *old_root || sr != *root {
+            return fail();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+//! 与数据内容无关的常数时间比较
+// 普通的==在遇到第一个不同的字节时就返回，比较耗时会泄露两个值有多少前缀相同。
+// 根哈希、proof和网络上收到的哈希都使用这里的比较，总是读完全部字节后再给出结果。
+// 长度本身不是秘密，长度不同时直接返回false。
+use core::hint::black_box;
+
+// 比较两个字节序列，在两边都取完之前不会提前返回
+pub fn ct_eq_iter<A, B>(a: A, b: B) -> bool
+where
+    A: IntoIterator<Item = u8>,
+    B: IntoIterator<Item = u8>,
+{
+    let mut a = a.into_iter();
+    let mut b = b.into_iter();
+    let mut diff = 0u8;
+    let mut same_len = true;
+    loop {
+        match (a.next(), b.next()) {
+            (Some(x), Some(y)) => diff |= x ^ y,
+            (None, None) => break,
+            // 长度不同，继续取完较长的一边
+            _ => same_len = false,
+        }
+    }
+    // black_box防止编译器把循环优化为提前退出的比较
+    same_len & (black_box(diff) == 0)
+}
+
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && ct_eq_iter(a.iter().copied(), b.iter().copied())
+}
@@ -0,0 +1,255 @@
+//! 利用两棵Merkle树的比较结果生成文件差异补丁(类似rsync)，并用补丁从旧文件重建新文件
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    chunking::{Blocking, Chunk},
+    codec::{invalid, put_bytes, put_varint, Reader},
+    digest::Digest,
+    error::{DataKind, MerkleError, Reason, Result},
+    tree::MerkleTree,
+};
+
+const MAGIC: &[u8; 4] = b"MPAT";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchOp {
+    Copy { offset: usize, length: usize }, // 从旧文件复制一段数据
+    Literal(Vec<u8>),                      // 旧文件中没有的新数据
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub blocksize: usize,          // 新文件的数据块大小，0表示内容定义分块
+    pub chunk_lengths: Vec<usize>, // 内容定义分块时新文件每个数据块的长度
+    pub new_len: usize,            // 新文件长度
+    pub new_root: Digest,          // 新文件的根哈希
+    pub ops: Vec<PatchOp>,
+}
+
+impl Patch {
+    // 追加一个操作，相邻的复制区间或新数据会被合并
+    fn push(&mut self, op: PatchOp) {
+        match (self.ops.last_mut(), op) {
+            (
+                Some(PatchOp::Copy { offset, length }),
+                PatchOp::Copy {
+                    offset: o,
+                    length: l,
+                },
+            ) if *offset + *length == o => *length += l,
+            (Some(PatchOp::Literal(data)), PatchOp::Literal(more)) => data.extend(more),
+            (_, op) => self.ops.push(op),
+        }
+    }
+
+    // 补丁中新数据的总字节数
+    pub fn literal_len(&self) -> usize {
+        self.ops
+            .iter()
+            .map(|op| match op {
+                PatchOp::Literal(data) => data.len(),
+                PatchOp::Copy { .. } => 0,
+            })
+            .sum()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        put_varint(&mut out, self.blocksize as u64);
+        put_varint(&mut out, self.chunk_lengths.len() as u64);
+        for len in &self.chunk_lengths {
+            put_varint(&mut out, *len as u64);
+        }
+        put_varint(&mut out, self.new_len as u64);
+        put_bytes(&mut out, self.new_root.as_ref());
+        put_varint(&mut out, self.ops.len() as u64);
+        for op in &self.ops {
+            match op {
+                PatchOp::Copy { offset, length } => {
+                    out.push(0);
+                    put_varint(&mut out, *offset as u64);
+                    put_varint(&mut out, *length as u64);
+                }
+                PatchOp::Literal(data) => {
+                    out.push(1);
+                    put_bytes(&mut out, data);
+                }
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Patch> {
+        let mut r = Reader::new(data);
+        if r.take(4)? != MAGIC || r.u8()? != VERSION {
+            return Err(invalid(Reason::NotFormat(DataKind::Patch)));
+        }
+        let blocksize = r.usize()?;
+        let count = r.usize()?;
+        let mut chunk_lengths = Vec::new();
+        for _ in 0..count {
+            chunk_lengths.push(r.usize()?);
+        }
+        let new_len = r.usize()?;
+        let new_root = r.digest()?;
+        let count = r.usize()?;
+        let mut ops = Vec::new();
+        for _ in 0..count {
+            let op = match r.u8()? {
+                0 => PatchOp::Copy {
+                    offset: r.usize()?,
+                    length: r.usize()?,
+                },
+                1 => PatchOp::Literal(r.bytes()?.to_vec()),
+                op => return Err(invalid(Reason::PatchOp(op))),
+            };
+            ops.push(op);
+        }
+        if !r.is_empty() {
+            return Err(invalid(Reason::TrailingData(DataKind::Patch)));
+        }
+        Ok(Patch {
+            blocksize,
+            chunk_lengths,
+            new_len,
+            new_root,
+            ops,
+        })
+    }
+}
+
+// 生成从old到new的补丁
+// 两棵树结构相同时先用compare找出不同的数据块，其余数据块直接从旧文件相同位置复制；
+// 不同的数据块再按哈希在旧文件中查找，找不到时作为新数据写入补丁
+pub fn make_patch(
+    old: &[u8],
+    old_tree: &MerkleTree,
+    new: &[u8],
+    new_tree: &MerkleTree,
+) -> Result<Patch> {
+    let old_spans = old_tree.spans(old.len());
+    let new_spans = new_tree.spans(new.len());
+    let old_leaves = old_tree.nodes.first().map(|l| l.as_slice()).unwrap_or(&[]);
+    let new_leaves = new_tree.nodes.first().map(|l| l.as_slice()).unwrap_or(&[]);
+    if old_spans.len() != old_leaves.len() || new_spans.len() != new_leaves.len() {
+        return Err(invalid(Reason::BlockCount));
+    }
+
+    let changed: Option<HashSet<usize>> = if old_tree.struct_eq(new_tree) && new_tree.blocksize > 0
+    {
+        Some(old_tree.compare(new_tree)?.into_iter().collect())
+    } else {
+        None
+    };
+
+    // 旧文件中每个哈希值第一次出现的位置
+    let mut known: HashMap<&Digest, Chunk> = HashMap::new();
+    for (leaf, span) in old_leaves.iter().zip(old_spans.iter()) {
+        known.entry(leaf).or_insert(*span);
+    }
+
+    let mut patch = Patch {
+        blocksize: new_tree.blocksize,
+        chunk_lengths: if new_tree.blocksize == 0 {
+            new_spans.iter().map(|c| c.length).collect()
+        } else {
+            vec![]
+        },
+        new_len: new.len(),
+        new_root: new_tree.root_hash()?,
+        ops: vec![],
+    };
+    for (i, (leaf, span)) in new_leaves.iter().zip(new_spans.iter()).enumerate() {
+        if span.length == 0 {
+            continue;
+        }
+        let unchanged = match &changed {
+            Some(changed) => !changed.contains(&i),
+            None => false,
+        };
+        // 两棵树中记录的数据块位置可能与传入的数据不符，越界时返回错误而不是panic
+        let source = if unchanged {
+            Some(
+                *old_spans
+                    .get(i)
+                    .ok_or_else(|| invalid(Reason::BlockCount))?,
+            )
+        } else {
+            known.get(leaf).copied()
+        };
+        match source {
+            Some(c) => patch.push(PatchOp::Copy {
+                offset: c.offset,
+                length: c.length,
+            }),
+            None => {
+                let data = new
+                    .get(span.offset..span.offset + span.length)
+                    .ok_or_else(|| invalid(Reason::BlockRange))?;
+                patch.push(PatchOp::Literal(data.to_vec()))
+            }
+        }
+    }
+    Ok(patch)
+}
+
+// 在旧文件上应用补丁得到新文件，并检查新文件的根哈希与补丁中记录的是否一致
+pub fn apply_patch(old: &[u8], patch: &Patch) -> Result<Vec<u8>> {
+    // new_len来自补丁，预分配不超过旧文件加上补丁中新数据的长度
+    let mut result = Vec::with_capacity(
+        patch
+            .new_len
+            .min(old.len().saturating_add(patch.literal_len())),
+    );
+    for op in &patch.ops {
+        match op {
+            PatchOp::Copy { offset, length } => {
+                let end = offset
+                    .checked_add(*length)
+                    .filter(|end| *end <= old.len())
+                    .ok_or_else(|| invalid(Reason::CopyRange))?;
+                result.extend_from_slice(&old[*offset..end]);
+            }
+            PatchOp::Literal(data) => result.extend_from_slice(data),
+        }
+        if result.len() > patch.new_len {
+            return Err(invalid(Reason::PatchLength));
+        }
+    }
+    if result.len() != patch.new_len {
+        return Err(invalid(Reason::PatchLength));
+    }
+
+    let tree = if patch.blocksize > 0 {
+        MerkleTree::from_bytes(&result, &Blocking::Fixed(patch.blocksize))?
+    } else {
+        let mismatch = || invalid(Reason::ChunkLengths);
+        let total = patch
+            .chunk_lengths
+            .iter()
+            .try_fold(0usize, |sum, len| sum.checked_add(*len))
+            .ok_or_else(mismatch)?;
+        if total != result.len() {
+            return Err(mismatch());
+        }
+        let mut offset = 0usize;
+        let mut blocks = Vec::with_capacity(patch.chunk_lengths.len());
+        for len in &patch.chunk_lengths {
+            let end = offset.checked_add(*len).ok_or_else(mismatch)?;
+            blocks.push(result.get(offset..end).ok_or_else(mismatch)?);
+            offset = end;
+        }
+        MerkleTree::new(&blocks, 0)
+    };
+    let computed = tree.root_hash()?;
+    if computed != patch.new_root {
+        return Err(MerkleError::VerifyFailed(Reason::RootMismatch {
+            computed,
+            expected: patch.new_root,
+        }));
+    }
+    Ok(result)
+}
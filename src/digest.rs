@@ -0,0 +1,149 @@
+//! 固定长度的SM3摘要类型，作为Merkle树节点、根哈希和proof链中的哈希值
+// 摘要直接存放在[u8; 32]中，复制时不需要分配内存；==使用常数时间比较
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use crate::{
+    ct::ct_eq,
+    sm3::{sm3, Sm3},
+};
+
+// 摘要的字节数
+pub const DIGEST_LEN: usize = 32;
+
+#[derive(Clone, Copy, Default)]
+pub struct Digest(pub [u8; DIGEST_LEN]);
+
+impl Digest {
+    // 计算数据的SM3摘要
+    pub fn of(data: &[u8]) -> Digest {
+        Digest(sm3(data))
+    }
+
+    // 两个子节点拼接后的摘要，即父节点的哈希值
+    pub fn combine(left: &Digest, right: &Digest) -> Digest {
+        let mut hasher = Sm3::new();
+        hasher.update(&left.0);
+        hasher.update(&right.0);
+        Digest(hasher.finalize())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; DIGEST_LEN] {
+        &self.0
+    }
+
+    // 从任意长度的字节串转换，长度不是32时返回None
+    pub fn from_slice(bytes: &[u8]) -> Option<Digest> {
+        <[u8; DIGEST_LEN]>::try_from(bytes).ok().map(Digest)
+    }
+
+    #[cfg(feature = "alloc")]
+    pub fn to_vec(&self) -> alloc::vec::Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+// 常数时间比较，根哈希和proof的验证都依赖这里，不在第一个不同的字节处提前返回
+impl PartialEq for Digest {
+    fn eq(&self, other: &Digest) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Digest {}
+
+impl Hash for Digest {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialOrd for Digest {
+    fn partial_cmp(&self, other: &Digest) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Digest {
+    fn cmp(&self, other: &Digest) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl fmt::Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Digest({})", self)
+    }
+}
+
+// 十六进制字符串格式有误或长度不是64个字符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseDigestError;
+
+impl fmt::Display for ParseDigestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "摘要应为{}个十六进制字符", DIGEST_LEN * 2)
+    }
+}
+
+impl FromStr for Digest {
+    type Err = ParseDigestError;
+
+    fn from_str(s: &str) -> Result<Digest, ParseDigestError> {
+        let s = s.as_bytes();
+        if s.len() != DIGEST_LEN * 2 {
+            return Err(ParseDigestError);
+        }
+        let digit = |c: u8| (c as char).to_digit(16).ok_or(ParseDigestError);
+        let mut result = [0u8; DIGEST_LEN];
+        for (byte, pair) in result.iter_mut().zip(s.chunks_exact(2)) {
+            *byte = (digit(pair[0])? << 4 | digit(pair[1])?) as u8;
+        }
+        Ok(Digest(result))
+    }
+}
+
+impl From<[u8; DIGEST_LEN]> for Digest {
+    fn from(bytes: [u8; DIGEST_LEN]) -> Digest {
+        Digest(bytes)
+    }
+}
+
+impl From<Digest> for [u8; DIGEST_LEN] {
+    fn from(digest: Digest) -> [u8; DIGEST_LEN] {
+        digest.0
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Digest> for alloc::vec::Vec<u8> {
+    fn from(digest: Digest) -> alloc::vec::Vec<u8> {
+        digest.to_vec()
+    }
+}
+
+impl TryFrom<&[u8]> for Digest {
+    type Error = ParseDigestError;
+
+    fn try_from(bytes: &[u8]) -> Result<Digest, ParseDigestError> {
+        Digest::from_slice(bytes).ok_or(ParseDigestError)
+    }
+}
+
+impl AsRef<[u8]> for Digest {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
@@ -0,0 +1,295 @@
+//! 目录树哈希：把整个目录(文件名、类型、权限和层次结构)绑定到一个根哈希上
+// 每个文件用现有的固定分块方式构建MerkleTree，目录节点的哈希由按名称排序后的
+// (名称, 类型, 权限, 子节点根哈希) 列表计算得到：
+//   dir_hash = sm3("DIR\0" || entry_0 || entry_1 || ...)
+//   entry    = 名称长度(4字节大端) || 名称 || 类型(1字节) || 权限(4字节大端) || 子节点根哈希
+// 空目录的哈希为sm3("DIR\0")。
+// 符号链接不会被跟随，其根哈希为sm3("LNK\0" || 链接目标)。
+// 其他类型的文件(设备、管道、套接字等)不受支持，遇到时返回错误。
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    config::data_to_blocks,
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    hash::HashSM3,
+    proof::{check_root, fold_path, Proof},
+    tree::MerkleTree,
+};
+
+const DIR_TAG: &[u8] = b"DIR\0";
+const LINK_TAG: &[u8] = b"LNK\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+}
+
+impl EntryKind {
+    fn code(&self) -> u8 {
+        match self {
+            EntryKind::File => 0,
+            EntryKind::Dir => 1,
+            EntryKind::Symlink => 2,
+        }
+    }
+}
+
+// 目录中一项参与哈希计算的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub name: String,
+    pub kind: EntryKind,
+    pub mode: u32,    // 权限位，非unix平台为0
+    pub root: Digest, // 文件的Merkle根、子目录哈希或符号链接哈希
+}
+
+impl EntryInfo {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(self.name.len() as u32).to_be_bytes());
+        out.extend_from_slice(self.name.as_bytes());
+        out.push(self.kind.code());
+        out.extend_from_slice(&self.mode.to_be_bytes());
+        out.extend_from_slice(self.root.as_bytes());
+    }
+}
+
+// 计算目录节点的哈希，entries需已按名称排序
+pub fn dir_hash(entries: &[EntryInfo]) -> Digest {
+    let mut data = DIR_TAG.to_vec();
+    for e in entries {
+        e.encode(&mut data);
+    }
+    data.sm3()
+}
+
+// 计算符号链接的哈希
+pub fn link_hash(target: &[u8]) -> Digest {
+    let mut data = LINK_TAG.to_vec();
+    data.extend_from_slice(target);
+    data.sm3()
+}
+
+pub enum Node {
+    File(MerkleTree),
+    Dir(DirTree),
+    Symlink(Vec<u8>), // 链接目标
+}
+
+pub struct DirEntry {
+    pub info: EntryInfo,
+    pub node: Node,
+}
+
+pub struct DirTree {
+    pub entries: Vec<DirEntry>, // 按名称排序
+    pub blocksize: usize,
+    pub root: Digest,
+}
+
+#[cfg(unix)]
+fn mode_of(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode_of(_meta: &fs::Metadata) -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn link_target(path: &Path) -> Result<Vec<u8>> {
+    use std::os::unix::ffi::OsStrExt;
+    Ok(fs::read_link(path)?.as_os_str().as_bytes().to_vec())
+}
+
+#[cfg(not(unix))]
+fn link_target(path: &Path) -> Result<Vec<u8>> {
+    Ok(fs::read_link(path)?
+        .to_string_lossy()
+        .into_owned()
+        .into_bytes())
+}
+
+impl DirTree {
+    // 遍历目录构建目录树
+    pub fn build(path: &Path, blocksize: usize) -> Result<DirTree> {
+        let mut entries = Vec::new();
+        for item in fs::read_dir(path)? {
+            let item = item?;
+            let name = item.file_name().into_string().map_err(|name| {
+                MerkleError::InvalidData(Reason::FileName(format!("{:?}", name)))
+            })?;
+            let meta = fs::symlink_metadata(item.path())?;
+            let mode = mode_of(&meta);
+            let file_type = meta.file_type();
+
+            let (kind, root, node) = if file_type.is_symlink() {
+                let target = link_target(&item.path())?;
+                (
+                    EntryKind::Symlink,
+                    link_hash(&target),
+                    Node::Symlink(target),
+                )
+            } else if file_type.is_dir() {
+                let sub = DirTree::build(&item.path(), blocksize)?;
+                (EntryKind::Dir, sub.root, Node::Dir(sub))
+            } else if file_type.is_file() {
+                let blocks = data_to_blocks(&fs::read(item.path())?, blocksize);
+                let tree = MerkleTree::new(&blocks, blocksize);
+                (EntryKind::File, tree.root_hash()?, Node::File(tree))
+            } else {
+                return Err(MerkleError::InvalidArgument(Reason::FileType(
+                    item.path().display().to_string(),
+                )));
+            };
+            entries.push(DirEntry {
+                info: EntryInfo {
+                    name,
+                    kind,
+                    mode,
+                    root,
+                },
+                node,
+            });
+        }
+        entries.sort_by(|a, b| a.info.name.as_bytes().cmp(b.info.name.as_bytes()));
+
+        let infos: Vec<EntryInfo> = entries.iter().map(|e| e.info.clone()).collect();
+        Ok(DirTree {
+            root: dir_hash(&infos),
+            entries,
+            blocksize,
+        })
+    }
+
+    pub fn root_hash(&self) -> Digest {
+        self.root
+    }
+
+    // 按以'/'分隔的相对路径查找目录项
+    pub fn get(&self, path: &str) -> Option<&DirEntry> {
+        let mut dir = self;
+        let mut parts = path.split('/').filter(|p| !p.is_empty()).peekable();
+        while let Some(name) = parts.next() {
+            let entry = dir.entries.iter().find(|e| e.info.name == name)?;
+            if parts.peek().is_none() {
+                return Some(entry);
+            }
+            match &entry.node {
+                Node::Dir(sub) => dir = sub,
+                _ => return None,
+            }
+        }
+        None
+    }
+
+    // 按路径顺序列出所有普通文件及其Merkle树
+    pub fn files(&self) -> Vec<(String, &MerkleTree)> {
+        let mut result = Vec::new();
+        self.collect_files("", &mut result);
+        result
+    }
+
+    fn collect_files<'a>(&'a self, prefix: &str, result: &mut Vec<(String, &'a MerkleTree)>) {
+        for e in &self.entries {
+            let path = format!("{}{}", prefix, e.info.name);
+            match &e.node {
+                Node::File(tree) => result.push((path, tree)),
+                Node::Dir(sub) => sub.collect_files(&format!("{}/", path), result),
+                Node::Symlink(_) => {}
+            }
+        }
+    }
+
+    // 为path处文件的第index个数据块生成到目录根哈希的proof，block为该数据块的内容
+    pub fn prove(&self, path: &str, index: usize, block: Vec<u8>) -> Option<DirProof> {
+        let mut steps = Vec::new();
+        let mut dir = self;
+        let mut parts = path.split('/').filter(|p| !p.is_empty()).peekable();
+        while let Some(name) = parts.next() {
+            let position = dir.entries.iter().position(|e| e.info.name == name)?;
+            steps.push(DirStep {
+                entries: dir.entries.iter().map(|e| e.info.clone()).collect(),
+                position,
+            });
+            match (&dir.entries[position].node, parts.peek()) {
+                (Node::Dir(sub), Some(_)) => dir = sub,
+                (Node::File(tree), None) if index < tree.leaves => {
+                    // 目录链从文件所在目录开始，逐级向上
+                    steps.reverse();
+                    return Some(DirProof {
+                        file_proof: Proof::new(tree, block, index, tree.blocksize).ok()?,
+                        steps,
+                    });
+                }
+                _ => return None,
+            }
+        }
+        None
+    }
+}
+
+// 目录链中的一级：某个目录的全部目录项，以及下一级(文件或子目录)在其中的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirStep {
+    pub entries: Vec<EntryInfo>,
+    pub position: usize,
+}
+
+// 从某个文件的一个数据块一直到目录根哈希的proof
+pub struct DirProof {
+    pub file_proof: Proof<Vec<u8>>,
+    pub steps: Vec<DirStep>, // 从文件所在目录到根目录
+}
+
+impl DirProof {
+    // 数据块在目录树中的路径
+    pub fn path(&self) -> String {
+        let names: Vec<&str> = self
+            .steps
+            .iter()
+            .rev()
+            .map(|s| s.entries[s.position].name.as_str())
+            .collect();
+        names.join("/")
+    }
+
+    // 验证proof能否得到给定的目录根哈希，文件的根哈希由数据块和proof链重新计算
+    // 失败时返回MerkleError::VerifyFailed说明原因
+    pub fn verify(&self, root: &Digest) -> Result<()> {
+        let proof = &self.file_proof;
+        proof.check_path()?;
+        if self.steps.is_empty() {
+            return Err(MerkleError::VerifyFailed(Reason::DirChainEmpty));
+        }
+        let mut hash = fold_path(proof.data.sm3(), &proof.chain, &proof.pos_chain);
+        for (i, step) in self.steps.iter().enumerate() {
+            let entry = step
+                .entries
+                .get(step.position)
+                .ok_or(MerkleError::VerifyFailed(Reason::DirPosition {
+                    step: i,
+                    position: step.position,
+                }))?;
+            // 最内层必须是普通文件，其余各级必须是目录
+            let kind = if i == 0 {
+                EntryKind::File
+            } else {
+                EntryKind::Dir
+            };
+            if entry.kind != kind || entry.root != hash {
+                return Err(MerkleError::VerifyFailed(Reason::DirEntry {
+                    step: i,
+                    name: entry.name.clone(),
+                }));
+            }
+            hash = dir_hash(&step.entries);
+        }
+        check_root(&hash, root)
+    }
+}
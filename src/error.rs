@@ -0,0 +1,373 @@
+//! 库中统一使用的错误类型
+// 错误附带的原因是结构化的，库中的Display给出中文说明，
+// 命令行按所选语言通过output中的消息表生成说明
+use alloc::string::String;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+use crate::digest::Digest;
+use crate::sm2::Sm2Error;
+
+#[derive(Debug)]
+pub enum MerkleError {
+    EmptyTree,      // 树中没有任何节点
+    StructMismatch, // 两棵树结构不同
+    IndexOutOfRange {
+        index: usize,
+        leaves: usize,
+    }, // 数据块下标越界
+    InvalidArgument(Reason), // 命令行参数等输入有误
+    InvalidData(Reason), // 补丁、清单、proof等编码数据有误
+    VerifyFailed(Reason), // 哈希或proof验证失败
+    Protocol(Reason), // 同步协议中对方返回错误或消息不符合预期
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+pub type Result<T> = core::result::Result<T, MerkleError>;
+
+// 编码数据的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    Proof,
+    Patch,
+    Manifest,
+    SignedRoot,
+}
+
+// 出错的具体原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    // 编码数据有误
+    Truncated,
+    DigestLength,
+    VarintOverflow,
+    NumberOutOfRange,
+    NotFormat(DataKind),
+    TrailingData(DataKind),
+    ProofIndex,  // proof中的数据块下标越界
+    ProofLength, // proof链长度与数据块下标不符
+    PatchOp(u8),
+    BlockCount,   // 数据块数量与树不符
+    BlockRange,   // 数据块超出文件范围
+    CopyRange,    // 补丁中的复制区间超出旧文件范围
+    PatchLength,  // 重建后的文件长度与补丁不符
+    ChunkLengths, // 补丁中的数据块长度与文件长度不符
+    ManifestLine(usize),
+    HashFormat(String),
+    FileName(String), // 不是有效UTF-8的文件名
+    FrameLength,
+    ReplyCount, // 服务端返回的节点或数据块数量有误
+    CorruptEntry {
+        offset: usize,
+        len: usize,
+        max: usize,
+    },
+    SthMismatch {
+        signed: usize,
+        leaves: usize,
+    },
+    KeyFile(String),
+    Json(usize), // JSON在该字节处有误
+    MissingLeaves(String),
+    Sm2(Sm2Error),
+    // 验证失败
+    LeafHash(Digest),
+    NodeRange {
+        level: usize,
+        index: usize,
+        leaves: usize,
+    },
+    PathShape {
+        level: usize,
+        index: usize,
+        leaves: usize,
+    },
+    RootMismatch {
+        computed: Digest,
+        expected: Digest,
+    },
+    Children {
+        level: usize,
+        parent: usize,
+    },
+    BlockProof(usize),
+    Signature(Digest),
+    DirChainEmpty,
+    DirPosition {
+        step: usize,
+        position: usize,
+    },
+    DirEntry {
+        step: usize,
+        name: String,
+    },
+    StoreLeaves(String),
+    StoreBlock {
+        name: String,
+        index: usize,
+    },
+    FileSize {
+        name: String,
+        size: u64,
+        expected: u64,
+    },
+    Tag(usize),
+    Consistency,
+    // 协议错误
+    ServerError(String),
+    UnexpectedMessage,
+    Closed,
+    BadRequest,
+    RequestTooLong,
+    UnknownEndpoint(String),
+    MethodNotAllowed {
+        path: String,
+        method: String,
+    },
+    // 参数有误
+    Message(String), // 调用方已按语言生成的说明，如命令行参数错误
+    ZeroBlocksize,
+    FastCdc {
+        min: usize,
+        avg: usize,
+        max: usize,
+    },
+    CdcAvg(usize), // FastCDC的平均数据块大小过大
+    NoSuchHash(Digest),
+    NoSuchNode {
+        level: usize,
+        index: usize,
+    },
+    ShardCount {
+        leaves: usize,
+        level: usize,
+        expected: usize,
+        actual: usize,
+    },
+    MergeBlocksize {
+        segment: usize,
+        blocksize: usize,
+        expected: usize,
+    },
+    MergeAlign {
+        segment: usize,
+        start: usize,
+        leaves: usize,
+    },
+    ConsistencySize {
+        old: usize,
+        size: usize,
+    },
+    NoVersion {
+        leaves: usize,
+        size: usize,
+    },
+    EntryTooLong {
+        len: usize,
+        max: usize,
+    },
+    ZeroInterval,
+    MissingParam(String),
+    ParamValue {
+        name: String,
+        value: String,
+    },
+    EmptyName,
+    NoSuchFile(String),
+    FileType(String),
+}
+
+impl fmt::Display for DataKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DataKind::Proof => "proof文件",
+            DataKind::Patch => "补丁文件",
+            DataKind::Manifest => "清单文件",
+            DataKind::SignedRoot => "签名的根哈希",
+        })
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::Truncated => write!(f, "数据被截断"),
+            Reason::DigestLength => write!(f, "哈希长度有误"),
+            Reason::VarintOverflow => write!(f, "变长整数溢出"),
+            Reason::NumberOutOfRange => write!(f, "数值超出范围"),
+            Reason::NotFormat(kind) => write!(f, "不是有效的{}", kind),
+            Reason::TrailingData(kind) => write!(f, "{}末尾有多余数据", kind),
+            Reason::ProofIndex => write!(f, "proof中数据块下标越界"),
+            Reason::ProofLength => write!(f, "proof链长度与数据块下标不符"),
+            Reason::PatchOp(op) => write!(f, "未知的补丁操作{}", op),
+            Reason::BlockCount => write!(f, "文件的数据块数量与树不符"),
+            Reason::BlockRange => write!(f, "数据块超出文件范围"),
+            Reason::CopyRange => write!(f, "复制区间超出旧文件范围"),
+            Reason::PatchLength => write!(f, "重建后的文件长度与补丁不符"),
+            Reason::ChunkLengths => write!(f, "数据块长度与文件长度不符"),
+            Reason::ManifestLine(line) => write!(f, "清单第{}行有误", line),
+            Reason::HashFormat(s) => write!(f, "哈希格式有误: {}", s),
+            Reason::FileName(name) => write!(f, "文件名不是有效的UTF-8: {}", name),
+            Reason::FrameLength => write!(f, "消息长度有误"),
+            Reason::ReplyCount => write!(f, "服务端返回的数量有误"),
+            Reason::CorruptEntry { offset, len, max } => write!(
+                f,
+                "entries文件偏移{}处的条目长度{}超过上限{}",
+                offset, len, max
+            ),
+            Reason::SthMismatch { signed, leaves } => write!(
+                f,
+                "已发布的树头({}个条目)与日志中的{}个条目不一致",
+                signed, leaves
+            ),
+            Reason::KeyFile(path) => write!(f, "私钥文件{}有误", path),
+            Reason::Json(pos) => write!(f, "JSON第{}个字节处格式有误", pos),
+            Reason::MissingLeaves(path) => write!(f, "存储的清单中{}没有记录叶子哈希", path),
+            Reason::Sm2(e) => write!(f, "{}", e),
+            Reason::LeafHash(leaf) => write!(f, "数据块的哈希与proof中的叶子哈希{}不一致", leaf),
+            Reason::NodeRange {
+                level,
+                index,
+                leaves,
+            } => write!(
+                f,
+                "第{}层下标{}的节点超出范围(叶子数量{})",
+                level, index, leaves
+            ),
+            Reason::PathShape {
+                level,
+                index,
+                leaves,
+            } => write!(
+                f,
+                "proof链与节点位置(第{}层下标{})和叶子数量{}不符",
+                level, index, leaves
+            ),
+            Reason::RootMismatch { computed, expected } => write!(
+                f,
+                "计算出的根哈希{}与期望的根哈希{}不一致",
+                computed, expected
+            ),
+            Reason::Children { level, parent } => {
+                write!(f, "第{}层第{}个节点的子节点与其哈希不符", level, parent)
+            }
+            Reason::BlockProof(index) => write!(f, "数据块{}的proof无效", index),
+            Reason::Signature(root) => write!(f, "根哈希{}的签名无效", root),
+            Reason::DirChainEmpty => write!(f, "目录链为空"),
+            Reason::DirPosition { step, position } => {
+                write!(f, "目录链第{}级的位置{}超出目录项范围", step, position)
+            }
+            Reason::DirEntry { step, name } => {
+                write!(f, "目录链第{}级的目录项{}与计算结果不符", step, name)
+            }
+            Reason::StoreLeaves(name) => write!(f, "{}的叶子哈希与根哈希不一致", name),
+            Reason::StoreBlock { name, index } => {
+                write!(f, "{}的第{}个数据块缺失或损坏", name, index)
+            }
+            Reason::FileSize {
+                name,
+                size,
+                expected,
+            } => write!(f, "{}的大小为{}，清单中为{}", name, size, expected),
+            Reason::Tag(index) => write!(f, "数据块{}的认证标签不符", index),
+            Reason::Consistency => write!(f, "一致性proof与根哈希不符"),
+            Reason::ServerError(msg) => write!(f, "服务端错误: {}", msg),
+            Reason::UnexpectedMessage => write!(f, "收到意外的消息类型"),
+            Reason::Closed => write!(f, "服务端关闭了连接"),
+            Reason::BadRequest => write!(f, "HTTP请求格式有误"),
+            Reason::RequestTooLong => write!(f, "HTTP请求过长"),
+            Reason::UnknownEndpoint(path) => write!(f, "未知的接口{}", path),
+            Reason::MethodNotAllowed { path, method } => write!(f, "{}不支持{}", path, method),
+            Reason::Message(msg) => write!(f, "{}", msg),
+            Reason::ZeroBlocksize => write!(f, "数据块大小必须大于0"),
+            Reason::FastCdc { min, avg, max } => write!(
+                f,
+                "FastCDC的数据块大小需满足 0 < 最小值({}) <= 平均值({}) <= 最大值({})",
+                min, avg, max
+            ),
+            Reason::CdcAvg(avg) => write!(f, "FastCDC的平均数据块大小{}过大", avg),
+            Reason::NoSuchHash(hash) => write!(f, "没有哈希为{}的数据块", hash),
+            Reason::NoSuchNode { level, index } => {
+                write!(f, "第{}层没有下标为{}的节点", level, index)
+            }
+            Reason::ShardCount {
+                leaves,
+                level,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}个数据块按每片2^{}个应分为{}片，实际有{}个子树根",
+                leaves, level, expected, actual
+            ),
+            Reason::MergeBlocksize {
+                segment,
+                blocksize,
+                expected,
+            } => write!(
+                f,
+                "第{}段的数据块大小{}与第1段的{}不同",
+                segment, blocksize, expected
+            ),
+            Reason::MergeAlign {
+                segment,
+                start,
+                leaves,
+            } => write!(
+                f,
+                "第{}段(起始下标{}，{}个数据块)与合并后的树不对齐，无法直接合并",
+                segment, start, leaves
+            ),
+            Reason::ConsistencySize { old, size } => {
+                write!(f, "无法证明{}个叶子的树与{}个叶子的树一致", old, size)
+            }
+            Reason::NoVersion { leaves, size } => {
+                write!(f, "树有{}个叶子，没有叶子数量为{}的版本", leaves, size)
+            }
+            Reason::EntryTooLong { len, max } => write!(f, "条目长度{}超过上限{}", len, max),
+            Reason::ZeroInterval => write!(f, "签名树头的间隔必须大于0"),
+            Reason::MissingParam(name) => write!(f, "缺少参数{}", name),
+            Reason::ParamValue { name, value } => write!(f, "参数{}的值无效: {}", name, value),
+            Reason::EmptyName => write!(f, "文件名不能为空"),
+            Reason::NoSuchFile(name) => write!(f, "存储中没有文件{}", name),
+            Reason::FileType(path) => write!(f, "不支持的文件类型: {}", path),
+        }
+    }
+}
+
+impl fmt::Display for MerkleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MerkleError::EmptyTree => write!(f, "树为空"),
+            MerkleError::StructMismatch => write!(f, "两棵树结构不同无法比较"),
+            MerkleError::IndexOutOfRange { index, leaves } => {
+                write!(f, "下标({})越界，树只有{}个数据块", index, leaves)
+            }
+            MerkleError::InvalidArgument(msg) => write!(f, "{}", msg),
+            MerkleError::InvalidData(msg) => write!(f, "数据格式有误: {}", msg),
+            MerkleError::VerifyFailed(msg) => write!(f, "验证失败: {}", msg),
+            MerkleError::Protocol(msg) => write!(f, "协议错误: {}", msg),
+            #[cfg(feature = "std")]
+            MerkleError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MerkleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MerkleError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for MerkleError {
+    fn from(e: io::Error) -> MerkleError {
+        MerkleError::Io(e)
+    }
+}
@@ -0,0 +1,154 @@
+//! 把Merkle树导出为Graphviz DOT、终端ASCII树和嵌套JSON，用于调试compare的结果
+use std::fmt::Write;
+
+use crate::{
+    digest::Digest,
+    error::{MerkleError, Result},
+    json::Value,
+    node::NodeId,
+    tree::MerkleTree,
+};
+
+// ASCII树和DOT标签中显示的哈希前缀长度(十六进制字符数)
+const SHORT_HASH: usize = 8;
+
+// 导出时的节点，children为空且level大于0说明受深度限制没有展开
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewNode {
+    pub level: usize, // 所在层，叶子为第0层
+    pub index: usize, // 在该层中的下标
+    pub hash: Digest,
+    pub leaves: (usize, usize), // 覆盖的叶子下标范围[start, end)
+    pub mismatch: bool,         // 与另一棵树相同位置的节点不同
+    pub children: Vec<ViewNode>,
+}
+
+impl ViewNode {
+    // 从根节点开始展开树，depth限制展开的层数(根节点为第0层)
+    // 给定other时标出与other相同位置(层、下标)哈希不同的节点，
+    // 与compare一样要求两棵树结构相同，否则相同位置的节点没有对应关系
+    pub fn new(
+        tree: &MerkleTree,
+        other: Option<&MerkleTree>,
+        depth: Option<usize>,
+    ) -> Result<ViewNode> {
+        if other.is_some_and(|o| !tree.struct_eq(o)) {
+            return Err(MerkleError::StructMismatch);
+        }
+        let root = tree.root_id().ok_or(MerkleError::EmptyTree)?;
+        let lowest = depth.map_or(0, |d| root.level.saturating_sub(d));
+        Ok(view(tree, other, root, lowest))
+    }
+
+    fn is_truncated(&self) -> bool {
+        self.level > 0 && self.children.is_empty()
+    }
+}
+
+fn view(tree: &MerkleTree, other: Option<&MerkleTree>, id: NodeId, lowest: usize) -> ViewNode {
+    let hash = tree.nodes[id.level][id.index];
+    let mismatch = other.is_some_and(|o| o.node_hash(id) != Some(hash));
+    // 提升后的节点只有一个子节点
+    let children = if id.level > lowest {
+        tree.children(id)
+            .map(|child| view(tree, other, child, lowest))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let leaves = tree.leaf_range(id).unwrap();
+    ViewNode {
+        level: id.level,
+        index: id.index,
+        hash,
+        leaves: (leaves.start, leaves.end),
+        mismatch,
+        children,
+    }
+}
+
+fn short(hash: &Digest) -> String {
+    hash.to_string()[..SHORT_HASH].to_string()
+}
+
+// Graphviz DOT，不同的节点用红色填充
+pub fn to_dot(root: &ViewNode) -> String {
+    let mut out = String::from("digraph merkle {\n    node [shape=box, fontname=\"monospace\"];\n");
+    fn walk(node: &ViewNode, out: &mut String) {
+        let style = if node.mismatch {
+            ", style=filled, fillcolor=\"#f4cccc\", color=red"
+        } else {
+            ""
+        };
+        writeln!(
+            out,
+            "    n{}_{} [label=\"L{}#{}\\n{}\", tooltip=\"{}\"{}];",
+            node.level,
+            node.index,
+            node.level,
+            node.index,
+            short(&node.hash),
+            node.hash,
+            style
+        )
+        .unwrap();
+        for child in &node.children {
+            writeln!(
+                out,
+                "    n{}_{} -> n{}_{};",
+                node.level, node.index, child.level, child.index
+            )
+            .unwrap();
+            walk(child, out);
+        }
+    }
+    walk(root, &mut out);
+    out.push_str("}\n");
+    out
+}
+
+// 终端显示的ASCII树，哈希截断显示，不同的节点后加'*'，未展开的节点后加"..."
+pub fn to_ascii(root: &ViewNode) -> String {
+    let mut out = String::new();
+    fn walk(node: &ViewNode, prefix: &str, connector: &str, child_prefix: &str, out: &mut String) {
+        writeln!(
+            out,
+            "{}{}L{}#{} {} [{}..{}){}{}",
+            prefix,
+            connector,
+            node.level,
+            node.index,
+            short(&node.hash),
+            node.leaves.0,
+            node.leaves.1,
+            if node.mismatch { " *" } else { "" },
+            if node.is_truncated() { " ..." } else { "" },
+        )
+        .unwrap();
+        let prefix = format!("{}{}", prefix, child_prefix);
+        for (i, child) in node.children.iter().enumerate() {
+            if i + 1 == node.children.len() {
+                walk(child, &prefix, "└── ", "    ", out);
+            } else {
+                walk(child, &prefix, "├── ", "│   ", out);
+            }
+        }
+    }
+    walk(root, "", "", "", &mut out);
+    out
+}
+
+// 嵌套的JSON文档，每个节点包含其子节点
+pub fn to_json(root: &ViewNode) -> Value {
+    Value::obj(vec![
+        ("level", Value::num(root.level)),
+        ("index", Value::num(root.index)),
+        ("hash", Value::Str(root.hash.to_string())),
+        ("leaves", Value::nums(&[root.leaves.0, root.leaves.1])),
+        ("mismatch", Value::Bool(root.mismatch)),
+        (
+            "children",
+            Value::Arr(root.children.iter().map(to_json).collect()),
+        ),
+    ])
+}
@@ -1,26 +1,56 @@
 //! 为Merkle树中的数据块实现Hash trait
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::digest::Digest;
+
 // trait类似于面向对象中的接口
-pub fn hash_to_str(hash: &Vec<u8>) -> String {
+// 把字节串或摘要转换为十六进制字符串
+pub fn hash_to_str<H: AsRef<[u8]> + ?Sized>(hash: &H) -> String {
     let mut result = String::new();
-    for num in hash {
+    for num in hash.as_ref() {
         result.push_str(&format!("{:02x}", num));
     }
 
     result
 }
 
+// 把十六进制字符串解析为字节，格式有误时返回None
+pub fn str_to_hash(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
 pub trait HashSM3 {
-    fn sm3(&self) -> Vec<u8>;
+    fn sm3(&self) -> Digest;
 
     fn sm3_str(&self) -> String;
 }
 
 impl HashSM3 for Vec<u8> {
-    fn sm3(&self) -> Vec<u8> {
-        crate::sm3::sm3(&self).to_vec()
+    fn sm3(&self) -> Digest {
+        Digest::of(self)
+    }
+
+    fn sm3_str(&self) -> String {
+        self.sm3().to_string()
+    }
+}
+
+impl HashSM3 for &[u8] {
+    fn sm3(&self) -> Digest {
+        Digest::of(self)
     }
 
     fn sm3_str(&self) -> String {
-        hash_to_str(&self.sm3())
+        self.sm3().to_string()
     }
 }
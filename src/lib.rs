@@ -1,9 +1,84 @@
+//! 基于国密算法SM3的Merkle树
+// 默认的std特性提供文件读写、命令行、网络同步和本地透明日志等功能；
+// 关闭std后，sm3、sm2签名和sm4加密可以在没有alloc的#![no_std]环境中使用，
+// 开启alloc时还可以构建Merkle树、生成和验证proof
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "std")]
 pub mod config;
 
+#[cfg(feature = "alloc")]
+pub mod error;
+
+#[cfg(feature = "alloc")]
 pub mod tree;
 
+#[cfg(feature = "alloc")]
+pub mod node;
+
+#[cfg(feature = "alloc")]
+pub mod lookup;
+
+#[cfg(feature = "alloc")]
 pub mod proof;
 
+#[cfg(feature = "alloc")]
+pub mod subtree;
+
+#[cfg(feature = "alloc")]
+pub mod merge;
+
+#[cfg(feature = "alloc")]
+pub mod consistency;
+
 pub mod sm3;
 
+pub mod sm2;
+
+pub mod sm4;
+
+pub mod digest;
+
+pub mod ct;
+
+#[cfg(feature = "alloc")]
 pub mod hash;
+
+#[cfg(feature = "alloc")]
+pub mod chunking;
+
+#[cfg(feature = "std")]
+pub mod delta;
+
+#[cfg(feature = "std")]
+pub mod sync;
+
+#[cfg(feature = "std")]
+pub mod log;
+
+#[cfg(feature = "std")]
+pub mod dirtree;
+
+#[cfg(feature = "std")]
+pub mod manifest;
+
+#[cfg(feature = "std")]
+pub mod store;
+
+#[cfg(feature = "alloc")]
+mod codec;
+
+#[cfg(feature = "std")]
+pub mod json;
+
+#[cfg(feature = "std")]
+pub mod output;
+
+#[cfg(feature = "std")]
+pub mod export;
+
+#[cfg(feature = "std")]
+pub mod bench;
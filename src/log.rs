@@ -0,0 +1,630 @@
+//! 仿照证书透明(RFC 6962)的本地透明日志服务
+// 条目只能追加，持久保存在目录下的entries文件中，以每个条目的SM3哈希为叶子构建Merkle树。
+// 日志定期用SM2私钥对当前的根哈希和条目数量签名，得到签名的树头(STH)，
+// 并通过只监听127.0.0.1的HTTP/JSON接口提供树头、添加条目、包含proof和一致性proof。
+//
+// entries文件中每个条目为 4字节大端长度 + 条目内容，打开日志时截掉追加到一半的条目
+use std::{
+    convert::TryInto,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    consistency::ConsistencyProof,
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    hash::{hash_to_str, str_to_hash},
+    json::Value,
+    proof::LeafProof,
+    sm2::{sm2_curve, PrivateKey, PublicKey, Rng, SignedRoot, Sm2Error},
+    tree::MerkleTree,
+};
+
+const ENTRIES_FILE: &str = "entries";
+const KEY_FILE: &str = "log.key";
+const STH_FILE: &str = "sth";
+
+pub const MAX_ENTRY: usize = 1 << 20; // 单个条目的最大长度
+const MAX_HEAD: usize = 16 << 10; // HTTP请求头的最大长度
+const IO_TIMEOUT: Duration = Duration::from_secs(5); // 读写一个请求的总时限
+const MAX_CONNECTIONS: usize = 64; // 同时处理的连接数上限
+
+// 从操作系统读取随机数，用于生成私钥和签名
+struct OsRng(File);
+
+impl OsRng {
+    fn new() -> Result<OsRng> {
+        Ok(OsRng(File::open("/dev/urandom")?))
+    }
+}
+
+impl Rng for OsRng {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> core::result::Result<(), Sm2Error> {
+        self.0.read_exact(dest).map_err(|_| Sm2Error::Rng)
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// 树头中的根哈希，空日志的根哈希与RFC 6962一样定义为空串的哈希
+pub fn head_root(tree: &MerkleTree, size: usize) -> Result<Digest> {
+    if size == 0 {
+        Ok(Digest::of(&[]))
+    } else {
+        tree.prefix_root(size)
+    }
+}
+
+pub struct Log {
+    dir: PathBuf,
+    file: File, // 以追加方式打开的entries文件
+    len: u64,   // entries文件中完整条目的总长度
+    tree: MerkleTree,
+    key: PrivateKey,
+    public_key: PublicKey,
+    rng: OsRng,
+    sth: SignedRoot,
+}
+
+impl Log {
+    // 打开目录中的日志，目录或其中的文件不存在时创建，第一次打开时生成签名私钥
+    pub fn open(dir: &Path) -> Result<Log> {
+        fs::create_dir_all(dir)?;
+        let mut rng = OsRng::new()?;
+        let key = load_key(&dir.join(KEY_FILE), &mut rng)?;
+        let public_key = sm2_curve().public_key(&key)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(dir.join(ENTRIES_FILE))?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        let mut hashes = Vec::new();
+        let mut pos = 0;
+        while let Some(head) = data.get(pos..pos + 4) {
+            let len = u32::from_be_bytes(head.try_into().unwrap()) as usize;
+            // 超过上限的长度不可能由append写入，说明文件已损坏
+            if len > MAX_ENTRY {
+                return Err(MerkleError::InvalidData(Reason::CorruptEntry {
+                    offset: pos,
+                    len,
+                    max: MAX_ENTRY,
+                }));
+            }
+            match data.get(pos + 4..pos + 4 + len) {
+                Some(entry) => hashes.push(Digest::of(entry)),
+                None => break,
+            }
+            pos += 4 + len;
+        }
+        let tree = MerkleTree::from_hashes(hashes, 0).with_index();
+
+        let sth = match fs::read(dir.join(STH_FILE)) {
+            Ok(bytes) => {
+                let sth = SignedRoot::from_bytes(&bytes)?;
+                sth.verify(&public_key)?;
+                if sth.leaves > tree.leaves || head_root(&tree, sth.leaves)? != sth.root {
+                    return Err(MerkleError::InvalidData(Reason::SthMismatch {
+                        signed: sth.leaves,
+                        leaves: tree.leaves,
+                    }));
+                }
+                sth
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => SignedRoot::new(
+                head_root(&tree, tree.leaves)?,
+                tree.leaves,
+                0,
+                now(),
+                &key,
+                &mut rng,
+            )?,
+            Err(e) => return Err(e.into()),
+        };
+        // 文件末尾是上次追加时中断留下的不完整条目，已发布的树头不包含它，可以截掉
+        if pos < data.len() {
+            file.set_len(pos as u64)?;
+        }
+
+        let log = Log {
+            dir: dir.to_path_buf(),
+            file,
+            len: pos as u64,
+            tree,
+            key,
+            public_key,
+            rng,
+            sth,
+        };
+        log.save_sth()?;
+        Ok(log)
+    }
+
+    // 日志中的条目数量
+    pub fn size(&self) -> usize {
+        self.tree.leaves
+    }
+
+    pub fn tree(&self) -> &MerkleTree {
+        &self.tree
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    // 最近一次签名的树头，新追加的条目在下一次签名后才出现在树头中
+    pub fn sth(&self) -> &SignedRoot {
+        &self.sth
+    }
+
+    // 追加条目并写入磁盘，返回条目下标和哈希；相同的条目已存在时直接返回原来的下标
+    pub fn append(&mut self, entry: &[u8]) -> Result<(usize, Digest)> {
+        if entry.len() > MAX_ENTRY {
+            return Err(MerkleError::InvalidArgument(Reason::EntryTooLong {
+                len: entry.len(),
+                max: MAX_ENTRY,
+            }));
+        }
+        let hash = Digest::of(entry);
+        if let Some(i) = self.tree.find(&hash).first() {
+            return Ok((*i, hash));
+        }
+        let mut record = Vec::with_capacity(4 + entry.len());
+        record.extend_from_slice(&(entry.len() as u32).to_be_bytes());
+        record.extend_from_slice(entry);
+        if let Err(e) = self
+            .file
+            .write_all(&record)
+            .and_then(|_| self.file.sync_data())
+        {
+            // 丢弃写了一半的条目，保证文件中只有完整的条目
+            let _ = self.file.set_len(self.len);
+            return Err(e.into());
+        }
+        self.len += record.len() as u64;
+        self.tree.push(hash);
+        Ok((self.tree.leaves - 1, hash))
+    }
+
+    // 对当前的根哈希和条目数量签名，并保存为最新的树头
+    pub fn sign_head(&mut self, timestamp: u64) -> Result<&SignedRoot> {
+        let root = head_root(&self.tree, self.tree.leaves)?;
+        let sth = SignedRoot::new(
+            root,
+            self.tree.leaves,
+            0,
+            timestamp,
+            &self.key,
+            &mut self.rng,
+        )?;
+        let previous = std::mem::replace(&mut self.sth, sth);
+        if let Err(e) = self.save_sth() {
+            self.sth = previous;
+            return Err(e);
+        }
+        Ok(&self.sth)
+    }
+
+    // 哈希为hash的条目在前size个条目构成的树中的包含proof
+    pub fn inclusion_proof(&self, hash: &Digest, size: usize) -> Result<LeafProof> {
+        match self.tree.find(hash).first() {
+            Some(i) if *i < size => self.tree.prefix_proof(*i, size),
+            _ => Err(MerkleError::InvalidArgument(Reason::NoSuchHash(*hash))),
+        }
+    }
+
+    // 前first个条目构成的树与前second个条目构成的树之间的一致性proof
+    pub fn consistency_proof(&self, first: usize, second: usize) -> Result<ConsistencyProof> {
+        self.tree.consistency_proof(first, second)
+    }
+
+    fn save_sth(&self) -> Result<()> {
+        write_file(&self.dir.join(STH_FILE), &self.sth.to_bytes(), 0o644)
+    }
+}
+
+// 先写临时文件并同步到磁盘再改名，崩溃后只会留下旧文件或完整的新文件
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn write_file(path: &Path, bytes: &[u8], mode: u32) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+    let mut file = options.open(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+// 读取私钥，文件不存在时生成新的私钥并只允许所有者读写
+fn load_key(path: &Path, rng: &mut OsRng) -> Result<PrivateKey> {
+    match fs::read(path) {
+        Ok(bytes) => bytes
+            .try_into()
+            .map(PrivateKey)
+            .map_err(|_| MerkleError::InvalidData(Reason::KeyFile(path.display().to_string()))),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let key = sm2_curve().generate_key(rng)?;
+            write_file(path, &key.0, 0o600)?;
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn lock(log: &Mutex<Log>) -> MutexGuard<'_, Log> {
+    // 处理请求时发生panic不会破坏日志的状态，继续使用
+    log.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// 在后台运行的日志服务：一个线程接受连接并为每个连接启动处理线程，一个线程定期签名树头
+pub struct LogServer {
+    addr: SocketAddr,
+    log: Arc<Mutex<Log>>,
+    stop: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl LogServer {
+    // 在127.0.0.1的port端口上启动服务，port为0时由系统分配，每隔interval签名一次树头
+    pub fn start(log: Log, port: u16, interval: Duration) -> Result<LogServer> {
+        if interval.is_zero() {
+            return Err(MerkleError::InvalidArgument(Reason::ZeroInterval));
+        }
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        let addr = listener.local_addr()?;
+        let log = Arc::new(Mutex::new(log));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let server = {
+            let (log, stop) = (log.clone(), stop.clone());
+            thread::spawn(move || {
+                let mut workers: Vec<JoinHandle<()>> = Vec::new();
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    workers.retain(|w| !w.is_finished());
+                    // 连接数达到上限时直接关闭新连接，已有的连接最多占用IO_TIMEOUT
+                    let stream = match stream {
+                        Ok(stream) if workers.len() < MAX_CONNECTIONS => stream,
+                        _ => continue,
+                    };
+                    let log = log.clone();
+                    // 单个连接出错不影响服务
+                    workers.push(thread::spawn(move || {
+                        let _ = serve(&log, stream);
+                    }));
+                }
+                // 停止时等待正在处理的请求完成
+                for w in workers {
+                    let _ = w.join();
+                }
+            })
+        };
+        let signer = {
+            let (log, stop) = (log.clone(), stop.clone());
+            thread::spawn(move || {
+                let mut next = Instant::now() + interval;
+                while !stop.load(Ordering::SeqCst) {
+                    let now = Instant::now();
+                    if now < next {
+                        thread::park_timeout(next - now);
+                        continue;
+                    }
+                    // 签名失败(如磁盘已满)时保留上一个树头，下个周期重试
+                    let _ = lock(&log).sign_head(self::now());
+                    next += interval;
+                }
+            })
+        };
+
+        Ok(LogServer {
+            addr,
+            log,
+            stop,
+            threads: vec![server, signer],
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    // 在服务运行时直接访问日志
+    pub fn log(&self) -> MutexGuard<'_, Log> {
+        lock(&self.log)
+    }
+
+    // 停止服务并等待后台线程退出，drop时也会调用
+    pub fn shutdown(self) {}
+}
+
+impl Drop for LogServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        for t in &self.threads {
+            t.thread().unpark();
+        }
+        // 连接一次监听端口，让阻塞在accept上的线程退出
+        let _ = TcpStream::connect(self.addr);
+        for t in self.threads.drain(..) {
+            let _ = t.join();
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn param(&self, name: &str) -> Result<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+            .ok_or_else(|| MerkleError::InvalidArgument(Reason::MissingParam(name.to_string())))
+    }
+
+    fn number(&self, name: &str) -> Result<usize> {
+        let v = self.param(name)?;
+        v.parse().map_err(|_| {
+            MerkleError::InvalidArgument(Reason::ParamValue {
+                name: name.to_string(),
+                value: v.to_string(),
+            })
+        })
+    }
+}
+
+// 一个连接的读写共用一个截止时间，每次读写前按剩余时间设置超时，
+// 逐字节发送或接收的客户端不能长时间占用处理请求的线程
+struct Deadline {
+    stream: TcpStream,
+    end: Instant,
+}
+
+impl Deadline {
+    fn remaining(&self) -> io::Result<Duration> {
+        let left = self.end.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "请求超时"));
+        }
+        Ok(left)
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.remaining()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.remaining()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn read_request<R: Read>(stream: &mut R) -> Result<Request> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(i) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break i;
+        }
+        if buf.len() > MAX_HEAD {
+            return Err(MerkleError::Protocol(Reason::RequestTooLong));
+        }
+        let n = stream.read(&mut chunk)?;
+        if n == 0 {
+            return Err(MerkleError::Protocol(Reason::BadRequest));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let mut body = buf.split_off(head_end + 4);
+    let head = std::str::from_utf8(&buf[..head_end])
+        .map_err(|_| MerkleError::Protocol(Reason::BadRequest))?;
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next().unwrap_or("").split(' ');
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(m), Some(t)) if !m.is_empty() => (m.to_string(), t),
+        _ => return Err(MerkleError::Protocol(Reason::BadRequest)),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (k.to_string(), v.to_string())
+        })
+        .collect();
+
+    let mut length = 0;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| MerkleError::Protocol(Reason::BadRequest))?;
+            }
+        }
+    }
+    // 条目以十六进制传输，请求体最多是条目长度的两倍加上JSON的其他部分
+    if length > MAX_ENTRY * 2 + 1024 {
+        return Err(MerkleError::Protocol(Reason::RequestTooLong));
+    }
+    if body.len() < length {
+        let have = body.len();
+        body.resize(length, 0);
+        stream.read_exact(&mut body[have..])?;
+    }
+    body.truncate(length);
+    Ok(Request {
+        method,
+        path: path.to_string(),
+        query,
+        body,
+    })
+}
+
+fn serve(log: &Mutex<Log>, stream: TcpStream) -> Result<()> {
+    let mut stream = Deadline {
+        stream,
+        end: Instant::now() + IO_TIMEOUT,
+    };
+    let (status, body) = match read_request(&mut stream) {
+        Ok(req) => route(log, &req),
+        Err(MerkleError::Io(e)) => return Err(e.into()),
+        Err(e) => (400, error_body(&e)),
+    };
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    Ok(stream.flush()?)
+}
+
+fn error_body(e: &MerkleError) -> Value {
+    Value::obj(vec![("error", Value::Str(e.to_string()))])
+}
+
+const ENDPOINTS: &[&str] = &[
+    "/ct/v1/get-sth",
+    "/ct/v1/add-entry",
+    "/ct/v1/get-proof-by-hash",
+    "/ct/v1/get-consistency",
+];
+
+// 返回HTTP状态码和JSON响应
+fn route(log: &Mutex<Log>, req: &Request) -> (u16, Value) {
+    let result = match (req.method.as_str(), req.path.as_str()) {
+        ("GET", "/ct/v1/get-sth") => {
+            let log = lock(log);
+            Ok(sth_json(log.sth(), log.public_key()))
+        }
+        ("POST", "/ct/v1/add-entry") => add_entry(log, &req.body),
+        ("GET", "/ct/v1/get-proof-by-hash") => get_proof_by_hash(log, req),
+        ("GET", "/ct/v1/get-consistency") => get_consistency(log, req),
+        (_, path) if ENDPOINTS.contains(&path) => {
+            return (
+                405,
+                error_body(&MerkleError::Protocol(Reason::MethodNotAllowed {
+                    path: path.to_string(),
+                    method: req.method.clone(),
+                })),
+            )
+        }
+        (_, path) => {
+            return (
+                404,
+                error_body(&MerkleError::Protocol(Reason::UnknownEndpoint(
+                    path.to_string(),
+                ))),
+            )
+        }
+    };
+    match result {
+        Ok(v) => (200, v),
+        Err(e @ MerkleError::Io(_)) => (500, error_body(&e)),
+        Err(e) => (400, error_body(&e)),
+    }
+}
+
+fn hashes(chain: &[Digest]) -> Value {
+    Value::Arr(chain.iter().map(|h| Value::Str(h.to_string())).collect())
+}
+
+// get-sth的响应，签名覆盖tree_size、timestamp和root_hash(数据块大小为0)
+pub fn sth_json(sth: &SignedRoot, key: &PublicKey) -> Value {
+    Value::obj(vec![
+        ("tree_size", Value::num(sth.leaves)),
+        ("timestamp", Value::Num(sth.timestamp)),
+        ("root_hash", Value::Str(sth.root.to_string())),
+        (
+            "tree_head_signature",
+            Value::Str(hash_to_str(&sth.signature.to_bytes())),
+        ),
+        ("public_key", Value::Str(hash_to_str(&key.to_bytes()))),
+    ])
+}
+
+// 请求体为{"entry": "十六进制条目内容"}
+fn add_entry(log: &Mutex<Log>, body: &[u8]) -> Result<Value> {
+    let text = std::str::from_utf8(body).map_err(|_| MerkleError::Protocol(Reason::BadRequest))?;
+    let entry = Value::parse(text)?
+        .get("entry")
+        .and_then(Value::as_str)
+        .and_then(str_to_hash)
+        .ok_or_else(|| MerkleError::InvalidArgument(Reason::MissingParam("entry".to_string())))?;
+    let (index, hash) = lock(log).append(&entry)?;
+    Ok(Value::obj(vec![
+        ("leaf_index", Value::num(index)),
+        ("leaf_hash", Value::Str(hash.to_string())),
+    ]))
+}
+
+// 参数为hash和tree_size
+fn get_proof_by_hash(log: &Mutex<Log>, req: &Request) -> Result<Value> {
+    let value = req.param("hash")?;
+    let hash: Digest = value.parse().map_err(|_| {
+        MerkleError::InvalidArgument(Reason::ParamValue {
+            name: "hash".to_string(),
+            value: value.to_string(),
+        })
+    })?;
+    let proof = lock(log).inclusion_proof(&hash, req.number("tree_size")?)?;
+    Ok(Value::obj(vec![
+        ("leaf_index", Value::num(proof.index)),
+        ("audit_path", hashes(&proof.chain)),
+    ]))
+}
+
+// 参数为first和second
+fn get_consistency(log: &Mutex<Log>, req: &Request) -> Result<Value> {
+    let proof = lock(log).consistency_proof(req.number("first")?, req.number("second")?)?;
+    Ok(Value::obj(vec![("consistency", hashes(&proof.chain))]))
+}
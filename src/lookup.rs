@@ -0,0 +1,84 @@
+//! 按数据块哈希查找叶子位置，调用方只知道数据内容时也能生成proof
+// 索引是可选的：没有建立索引时find逐个比较叶子，建立索引后按哈希直接查找。
+// 相同内容的数据块在树中出现多次时，返回所有位置
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    proof::LeafProof,
+    tree::MerkleTree,
+};
+
+// 叶子哈希到叶子下标的索引，下标按从小到大排列
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LeafIndex {
+    map: BTreeMap<Digest, Vec<usize>>,
+}
+
+impl LeafIndex {
+    pub fn new(leaves: &[Digest]) -> LeafIndex {
+        let mut index = LeafIndex::default();
+        for (i, hash) in leaves.iter().enumerate() {
+            index.insert(*hash, i);
+        }
+        index
+    }
+
+    // 下标必须比已有的下标都大
+    pub fn insert(&mut self, hash: Digest, index: usize) {
+        self.map.entry(hash).or_default().push(index);
+    }
+
+    pub fn get(&self, hash: &Digest) -> &[usize] {
+        self.map.get(hash).map_or(&[], |v| v.as_slice())
+    }
+
+    // 不同的叶子哈希的数量
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl MerkleTree {
+    // 为当前的叶子建立索引，之后push追加的叶子也会加入索引
+    pub fn build_index(&mut self) {
+        let leaves = self.nodes.first().map_or(&[][..], |l| l.as_slice());
+        self.leaf_index = Some(LeafIndex::new(leaves));
+    }
+
+    pub fn with_index(mut self) -> MerkleTree {
+        self.build_index();
+        self
+    }
+
+    // 哈希为hash的所有数据块下标，没有时为空
+    pub fn find(&self, hash: &Digest) -> Vec<usize> {
+        match (&self.leaf_index, self.nodes.first()) {
+            (Some(index), _) => index.get(hash).to_vec(),
+            (None, Some(leaves)) => leaves
+                .iter()
+                .enumerate()
+                .filter(|(_, leaf)| *leaf == hash)
+                .map(|(i, _)| i)
+                .collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    // 为哈希为hash的每个数据块生成proof，树中没有该数据块时返回错误
+    pub fn prove_by_hash(&self, hash: &Digest) -> Result<Vec<LeafProof>> {
+        let found = self.find(hash);
+        if found.is_empty() {
+            return Err(MerkleError::InvalidArgument(Reason::NoSuchHash(*hash)));
+        }
+        found
+            .into_iter()
+            .map(|i| LeafProof::new(self, i, self.blocksize))
+            .collect()
+    }
+}
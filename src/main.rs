@@ -1,29 +1,254 @@
-use merkle::{config::run, proof::Proof, sm3::sm3, tree::MerkleTree};
 use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use merkle::bench::{run as run_bench, BenchConfig};
+use merkle::chunking::{Blocking, FastCdc};
+use merkle::config::{
+    command_usage, data_to_blocks, lang_from_args, usage, Command, Config, Format, EXIT_ERROR,
+    EXIT_MISMATCH, EXIT_OK, EXIT_USAGE,
+};
+use merkle::delta::make_patch;
+use merkle::error::{MerkleError, Reason, Result};
+use merkle::export::ViewNode;
+use merkle::hash::str_to_hash;
+use merkle::manifest::Manifest;
+use merkle::output::{error_text, tr, Lang, Msg, ProofReport, Report};
+use merkle::proof::Proof;
+use merkle::tree::MerkleTree;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    run(args);
-    // proof_test(args[1].clone().parse().unwrap());
+    std::process::exit(run(args));
+}
+
+// 解析参数并执行命令，返回进程退出码
+fn run(args: Vec<String>) -> i32 {
+    let config = match Config::new(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            let lang = lang_from_args(&args);
+            eprintln!("{}\n\n{}", error_text(lang, &e), usage(lang));
+            return EXIT_USAGE;
+        }
+    };
+    let lang = config.lang;
+    if let Command::Help(name) = &config.command {
+        return help(name.as_deref(), lang);
+    }
+
+    match execute(&config) {
+        Ok(report) => {
+            match config.format {
+                Format::Json => println!("{}", report.to_json()),
+                Format::Text => print!("{}", report.to_text(lang)),
+            }
+            if report.success() {
+                EXIT_OK
+            } else {
+                EXIT_MISMATCH
+            }
+        }
+        Err(MerkleError::IndexOutOfRange { index, leaves }) => {
+            eprintln!("{}", tr(lang, Msg::IndexOutOfRange, &[&index, &leaves]));
+            EXIT_USAGE
+        }
+        Err(e) => {
+            eprintln!("{}", tr(lang, Msg::Error, &[&error_text(lang, &e)]));
+            EXIT_ERROR
+        }
+    }
 }
 
-fn ana(i: u32) {
-    for _ in 0..i {
-        sm3(&String::from("abc").as_bytes().to_vec());
+fn help(name: Option<&str>, lang: Lang) -> i32 {
+    match name {
+        Some(name) => match command_usage(name, lang) {
+            Some(text) => println!("{}", text),
+            None => {
+                eprintln!(
+                    "{}\n\n{}",
+                    tr(lang, Msg::UnknownCommand, &[&name]),
+                    usage(lang)
+                );
+                return EXIT_USAGE;
+            }
+        },
+        None => println!("{}", usage(lang)),
     }
+    EXIT_OK
+}
+
+fn read_tree(path: &str, blocksize: usize) -> Result<(Vec<Vec<u8>>, MerkleTree)> {
+    let blocks = data_to_blocks(&fs::read(path)?, blocksize);
+    let tree = MerkleTree::new(&blocks, blocksize);
+    Ok((blocks, tree))
 }
 
-fn build(i: u32) {
-    let data = merkle::config::data_to_blocks(&std::fs::read("files/1.txt").unwrap(), 2);
-    for _ in 0..i {
-        let tree: MerkleTree = MerkleTree::new(&data, 2);
+// 数据块参数是已存在的文件时读取文件内容，否则按十六进制解析
+fn read_block(block: &str, lang: Lang) -> Result<Vec<u8>> {
+    if Path::new(block).is_file() {
+        return Ok(fs::read(block)?);
     }
+    str_to_hash(block).ok_or_else(|| {
+        MerkleError::InvalidArgument(Reason::Message(tr(lang, Msg::InvalidBlock, &[&block])))
+    })
 }
 
-fn proof_test(i: u32) {
-    let data = merkle::config::data_to_blocks(&std::fs::read("files/1.txt").unwrap(), 2);
-    let tree: MerkleTree = MerkleTree::new(&data, 2);
-    for _ in 0..i {
-        let proof = Proof::new(&tree, data.get(0).unwrap().clone(), 0, 2);
+fn execute(config: &Config) -> Result<Report> {
+    let blocksize = config.blocksize;
+    let lang = config.lang;
+    match &config.command {
+        Command::Help(_) => unreachable!(),
+        Command::Root { file } | Command::Build { file } => {
+            let (_, tree) = read_tree(file, blocksize)?;
+            let build = matches!(config.command, Command::Build { .. });
+            Ok(Report::Root {
+                file: file.clone(),
+                blocksize,
+                leaves: tree.leaves,
+                height: tree.height,
+                levels: if build {
+                    Some(tree.nodes.iter().map(|l| l.len()).collect())
+                } else {
+                    None
+                },
+                root: tree.root_hash()?,
+                expected: config.hash,
+            })
+        }
+        Command::Compare { file1, file2 } => {
+            let (_, tree1) = read_tree(file1, blocksize)?;
+            let (_, tree2) = read_tree(file2, blocksize)?;
+            let different = match tree1.compare(&tree2) {
+                Ok(different) => Some(different),
+                Err(MerkleError::StructMismatch) => None,
+                Err(e) => return Err(e),
+            };
+            Ok(Report::Compare {
+                blocksize,
+                root1: tree1.root_hash()?,
+                root2: tree2.root_hash()?,
+                leaves1: tree1.leaves,
+                leaves2: tree2.leaves,
+                different,
+            })
+        }
+        Command::Prove { file, index, out } => {
+            let (blocks, tree) = read_tree(file, blocksize)?;
+            let block = blocks.get(*index).cloned().unwrap_or_default();
+            let proof = Proof::new(&tree, block, *index, blocksize)?;
+            if let Some(out) = out {
+                fs::write(out, proof.to_bytes())?;
+            }
+            Ok(Report::Proof(ProofReport::new(&proof)))
+        }
+        Command::Verify {
+            file1,
+            file2,
+            index,
+        } => {
+            let (f1, _) = read_tree(file1, blocksize)?;
+            let (_, tree2) = read_tree(file2, blocksize)?;
+            let block = f1
+                .get(*index)
+                .cloned()
+                .ok_or(MerkleError::IndexOutOfRange {
+                    index: *index,
+                    leaves: f1.len(),
+                })?;
+            // 利用树2生成proof，验证文件1中的数据块
+            let proof = Proof::new(&tree2, block, *index, blocksize)?;
+            let root = tree2.root_hash()?;
+            let reason = proof.verify(&root).err().map(|e| error_text(lang, &e));
+            Ok(Report::Proof(
+                ProofReport::new(&proof).verified(root, reason),
+            ))
+        }
+        Command::VerifyProof { root, proof, block } => {
+            // 文件读不出来是错误，内容无法解码则是验证失败
+            let proof = match Proof::from_bytes(&fs::read(proof)?, read_block(block, lang)?) {
+                Ok(proof) => proof,
+                Err(e @ MerkleError::InvalidData(_)) => {
+                    return Ok(Report::BadProof {
+                        target_root: *root,
+                        reason: error_text(lang, &e),
+                    })
+                }
+                Err(e) => return Err(e),
+            };
+            let reason = proof.verify(root).err().map(|e| error_text(lang, &e));
+            Ok(Report::Proof(
+                ProofReport::new(&proof).verified(*root, reason),
+            ))
+        }
+        Command::Diff { old, new, out, cdc } => {
+            let old_data = fs::read(old)?;
+            let new_data = fs::read(new)?;
+            let blocking = if *cdc {
+                Blocking::FastCdc(FastCdc::with_avg(blocksize)?)
+            } else {
+                Blocking::Fixed(blocksize)
+            };
+            let old_tree = MerkleTree::from_bytes(&old_data, &blocking)?;
+            let new_tree = MerkleTree::from_bytes(&new_data, &blocking)?;
+            let patch = make_patch(&old_data, &old_tree, &new_data, &new_tree)?;
+            let bytes = patch.to_bytes();
+            fs::write(out, &bytes)?;
+            Ok(Report::Diff {
+                patch: out.clone(),
+                ops: patch.ops.len(),
+                literal_bytes: patch.literal_len(),
+                patch_bytes: bytes.len(),
+                new_root: patch.new_root,
+            })
+        }
+        Command::Bench {
+            max_size,
+            millis,
+            blocksize,
+        } => {
+            let mut bench = BenchConfig {
+                max_size: *max_size,
+                min_time: Duration::from_millis(*millis),
+                ..BenchConfig::default()
+            };
+            if let Some(bs) = blocksize {
+                bench.blocksizes = vec![*bs];
+            }
+            Ok(Report::Bench(run_bench(&bench, |_| {})))
+        }
+        Command::Manifest { dir, out, leaves } => {
+            let manifest = Manifest::generate(Path::new(dir), blocksize, *leaves)?;
+            let data = if out.ends_with(".bin") {
+                manifest.to_bytes()
+            } else {
+                manifest.to_text().into_bytes()
+            };
+            fs::write(out, data)?;
+            Ok(Report::Manifest {
+                manifest: out.clone(),
+                files: manifest.entries.len(),
+            })
+        }
+        Command::Check { dir, manifest } => Ok(Report::Check(
+            Manifest::parse(&fs::read(manifest)?)?.check(Path::new(dir))?,
+        )),
+        Command::Show {
+            file,
+            other,
+            depth,
+            dot,
+        } => {
+            let (_, tree) = read_tree(file, blocksize)?;
+            let other = match other {
+                Some(f) => Some(read_tree(f, blocksize)?.1),
+                None => None,
+            };
+            Ok(Report::Show {
+                tree: ViewNode::new(&tree, other.as_ref(), *depth)?,
+                dot: *dot,
+            })
+        }
     }
 }
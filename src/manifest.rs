@@ -0,0 +1,341 @@
+//! 清单文件：记录目录中每个文件的大小、数据块大小、叶子数量和根哈希(可选记录叶子哈希)，
+//! 下游可以离线校验任意文件或数据块
+// 文本格式：
+//   merkle-manifest 1
+//   file <大小> <数据块大小> <叶子数量> <根哈希> <路径>
+//     路径中的'\'、换行和回车分别写作"\\"、"\n"和"\r"
+//   leaf <叶子哈希>            (可选，紧跟在对应的file行之后，按下标顺序)
+// 二进制格式以"MMAN"开头，字段使用变长整数编码
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::{
+    chunking::Blocking,
+    codec::{invalid, put_bytes, put_varint, Reader},
+    digest::{Digest, DIGEST_LEN},
+    dirtree::DirTree,
+    error::{DataKind, MerkleError, Reason, Result},
+    tree::MerkleTree,
+};
+
+const TEXT_HEADER: &str = "merkle-manifest 1";
+const MAGIC: &[u8; 4] = b"MMAN";
+const VERSION: u8 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub path: String, // 以'/'分隔的相对路径
+    pub size: u64,
+    pub blocksize: usize,
+    pub leaves: usize,
+    pub root: Digest,
+    pub leaf_hashes: Option<Vec<Digest>>,
+}
+
+impl ManifestEntry {
+    fn from_tree(
+        path: String,
+        size: u64,
+        tree: &MerkleTree,
+        with_leaves: bool,
+    ) -> Result<ManifestEntry> {
+        Ok(ManifestEntry {
+            path,
+            size,
+            blocksize: tree.blocksize,
+            leaves: tree.leaves,
+            root: tree.root_hash()?,
+            leaf_hashes: if with_leaves {
+                tree.nodes.first().cloned()
+            } else {
+                None
+            },
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>, // 按路径排序
+}
+
+// 内容有变化的文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Modified {
+    pub path: String,
+    pub blocks: Option<Vec<usize>>, // 不同的数据块下标，清单未记录叶子哈希时为None
+}
+
+// 用清单检查目录的结果
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CheckReport {
+    pub missing: Vec<String>, // 清单中有但目录中没有的文件
+    pub extra: Vec<String>,   // 目录中有但清单中没有的文件
+    pub modified: Vec<Modified>,
+    pub invalid: Vec<String>, // 清单中记录有误(如数据块大小为0)而无法检查的文件
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.modified.is_empty()
+            && self.invalid.is_empty()
+    }
+}
+
+impl Manifest {
+    // 为目录中的所有普通文件生成清单，符号链接不记录
+    pub fn generate(dir: &Path, blocksize: usize, with_leaves: bool) -> Result<Manifest> {
+        let tree = DirTree::build(dir, blocksize)?;
+        let mut entries = Vec::new();
+        for (path, file) in tree.files() {
+            let size = fs::metadata(dir.join(&path))?.len();
+            entries.push(ManifestEntry::from_tree(path, size, file, with_leaves)?);
+        }
+        Ok(Manifest { entries })
+    }
+
+    // 用清单检查目录，报告缺失、多余和被修改的文件
+    // 每个文件按清单中为它记录的数据块大小建树，空清单只会报告多余的文件
+    pub fn check(&self, dir: &Path) -> Result<CheckReport> {
+        let files = list_files(dir, "")?;
+
+        let mut report = CheckReport::default();
+        for entry in &self.entries {
+            if entry.blocksize == 0 {
+                report.invalid.push(entry.path.clone());
+                continue;
+            }
+            if !files.contains(&entry.path) {
+                report.missing.push(entry.path.clone());
+                continue;
+            }
+            let data = fs::read(dir.join(&entry.path))?;
+            let file = MerkleTree::from_bytes(&data, &Blocking::Fixed(entry.blocksize))?;
+            if data.len() as u64 == entry.size
+                && file.leaves == entry.leaves
+                && file.root_hash()? == entry.root
+            {
+                continue;
+            }
+            let blocks = entry.leaf_hashes.as_ref().map(|expect| {
+                let actual = &file.nodes[0];
+                (0..expect.len().max(actual.len()))
+                    .filter(|i| expect.get(*i) != actual.get(*i))
+                    .collect()
+            });
+            report.modified.push(Modified {
+                path: entry.path.clone(),
+                blocks,
+            });
+        }
+
+        let known: BTreeSet<&String> = self.entries.iter().map(|e| &e.path).collect();
+        report.extra = files
+            .into_iter()
+            .filter(|path| !known.contains(path))
+            .collect();
+        Ok(report)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::from(TEXT_HEADER);
+        out.push('\n');
+        for e in &self.entries {
+            out.push_str(&format!(
+                "file {} {} {} {} {}\n",
+                e.size,
+                e.blocksize,
+                e.leaves,
+                e.root,
+                escape_path(&e.path)
+            ));
+            for leaf in e.leaf_hashes.iter().flatten() {
+                out.push_str(&format!("leaf {}\n", leaf));
+            }
+        }
+        out
+    }
+
+    pub fn from_text(text: &str) -> Result<Manifest> {
+        let mut lines = text.lines();
+        if lines.next().map(|l| l.trim_end()) != Some(TEXT_HEADER) {
+            return Err(invalid(Reason::NotFormat(DataKind::Manifest)));
+        }
+        let mut entries: Vec<ManifestEntry> = Vec::new();
+        // 第1行是文件头，出错时报告的行号从1开始
+        for (i, line) in lines.enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let bad = || invalid(Reason::ManifestLine(i + 2));
+            if let Some(hash) = line.strip_prefix("leaf ") {
+                let hash = parse_digest(hash.trim())?;
+                let entry = entries.last_mut().ok_or_else(bad)?;
+                entry.leaf_hashes.get_or_insert_with(Vec::new).push(hash);
+            } else if let Some(rest) = line.strip_prefix("file ") {
+                let fields: Vec<&str> = rest.splitn(5, ' ').collect();
+                if fields.len() != 5 {
+                    return Err(bad());
+                }
+                entries.push(ManifestEntry {
+                    size: fields[0].parse().map_err(|_| bad())?,
+                    blocksize: fields[1].parse().map_err(|_| bad())?,
+                    leaves: fields[2].parse().map_err(|_| bad())?,
+                    root: parse_digest(fields[3])?,
+                    path: unescape_path(fields[4]).ok_or_else(bad)?,
+                    leaf_hashes: None,
+                });
+            } else {
+                return Err(bad());
+            }
+        }
+        Ok(Manifest { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        put_varint(&mut out, self.entries.len() as u64);
+        for e in &self.entries {
+            put_bytes(&mut out, e.path.as_bytes());
+            put_varint(&mut out, e.size);
+            put_varint(&mut out, e.blocksize as u64);
+            put_varint(&mut out, e.leaves as u64);
+            put_bytes(&mut out, e.root.as_ref());
+            match &e.leaf_hashes {
+                Some(leaves) => {
+                    out.push(1);
+                    put_varint(&mut out, leaves.len() as u64);
+                    for leaf in leaves {
+                        put_bytes(&mut out, leaf.as_ref());
+                    }
+                }
+                None => out.push(0),
+            }
+        }
+        out
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Manifest> {
+        let mut r = Reader::new(data);
+        if r.take(4)? != MAGIC || r.u8()? != VERSION {
+            return Err(invalid(Reason::NotFormat(DataKind::Manifest)));
+        }
+        let count = r.usize()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let path = String::from_utf8(r.bytes()?.to_vec())
+                .map_err(|_| invalid(Reason::NotFormat(DataKind::Manifest)))?;
+            let size = r.varint()?;
+            let blocksize = r.usize()?;
+            let leaves = r.usize()?;
+            let root = r.digest()?;
+            let leaf_hashes = match r.u8()? {
+                0 => None,
+                1 => {
+                    let n = r.usize()?;
+                    let mut hashes = Vec::new();
+                    for _ in 0..n {
+                        hashes.push(r.digest()?);
+                    }
+                    Some(hashes)
+                }
+                _ => return Err(invalid(Reason::NotFormat(DataKind::Manifest))),
+            };
+            entries.push(ManifestEntry {
+                path,
+                size,
+                blocksize,
+                leaves,
+                root,
+                leaf_hashes,
+            });
+        }
+        if !r.is_empty() {
+            return Err(invalid(Reason::TrailingData(DataKind::Manifest)));
+        }
+        Ok(Manifest { entries })
+    }
+
+    // 根据开头的标记自动识别文本或二进制格式
+    pub fn parse(data: &[u8]) -> Result<Manifest> {
+        if data.starts_with(MAGIC) {
+            Manifest::from_bytes(data)
+        } else {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| invalid(Reason::NotFormat(DataKind::Manifest)))?;
+            Manifest::from_text(text)
+        }
+    }
+}
+
+// 文本格式中每个文件占一行，路径中的换行和回车需要转义
+fn escape_path(path: &str) -> String {
+    let mut out = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape_path(s: &str) -> Option<String> {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+// 文本格式中的哈希必须是32字节(64个十六进制字符)
+fn parse_digest(s: &str) -> Result<Digest> {
+    let bad = || invalid(Reason::HashFormat(s.to_string()));
+    if s.len() != DIGEST_LEN * 2 {
+        return Err(bad());
+    }
+    s.parse().map_err(|_| bad())
+}
+
+// 按与DirTree::files相同的规则列出目录中的所有普通文件，符号链接不记录
+fn list_files(dir: &Path, prefix: &str) -> Result<BTreeSet<String>> {
+    let mut result = BTreeSet::new();
+    for item in fs::read_dir(dir)? {
+        let item = item?;
+        let name = item
+            .file_name()
+            .into_string()
+            .map_err(|name| MerkleError::InvalidData(Reason::FileName(format!("{:?}", name))))?;
+        let path = format!("{}{}", prefix, name);
+        let file_type = fs::symlink_metadata(item.path())?.file_type();
+        if file_type.is_symlink() {
+            continue;
+        } else if file_type.is_dir() {
+            result.append(&mut list_files(&item.path(), &format!("{}/", path))?);
+        } else if file_type.is_file() {
+            result.insert(path);
+        } else {
+            return Err(MerkleError::InvalidArgument(Reason::FileType(
+                item.path().display().to_string(),
+            )));
+        }
+    }
+    Ok(result)
+}
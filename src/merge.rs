@@ -0,0 +1,116 @@
+//! 合并分别构建的相邻数据段的Merkle树
+// 各段的数据块按顺序拼接后构建的树，可以直接复用各段已有的层：
+// 除最后一段外每段的叶子数量必须是2的幂，并且每段的起始下标必须是该段大小(向上取到2的幂)的整数倍，
+// 这样各段的节点正好是合并后的树中的节点，只需要计算跨越段边界的少数节点。
+// 不满足条件时concat返回错误，可以改用forest_root得到定义明确的"森林根"。
+// 注意按固定大小切分的数据末尾总有一个不满(可能为空)的数据块，合并的结果是各段数据块拼接后的树，
+// 只有各段的数据块在拼接后与整个文件的数据块一致时，才与整个文件的树相同
+use alloc::vec::Vec;
+
+use crate::{
+    chunking::Chunk,
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    tree::MerkleTree,
+};
+
+// 叶子数量为n的树中真正参与合并的最高层，即log2(n)向上取整
+fn top_level(n: usize) -> usize {
+    n.next_power_of_two().trailing_zeros() as usize
+}
+
+impl MerkleTree {
+    // 得到与在拼接后的数据块上构建的树相同的树，空树被忽略
+    pub fn concat(trees: &[MerkleTree]) -> Result<MerkleTree> {
+        let segments: Vec<&MerkleTree> = trees.iter().filter(|t| t.leaves > 0).collect();
+        let blocksize = segments.first().map_or(0, |t| t.blocksize);
+        let mut offsets = Vec::with_capacity(segments.len());
+        let mut leaves = 0;
+        for (i, t) in segments.iter().enumerate() {
+            if t.blocksize != blocksize {
+                return Err(MerkleError::InvalidArgument(Reason::MergeBlocksize {
+                    segment: i + 1,
+                    blocksize: t.blocksize,
+                    expected: blocksize,
+                }));
+            }
+            let last = i + 1 == segments.len();
+            if (!last && !t.leaves.is_power_of_two()) || leaves % (1 << top_level(t.leaves)) != 0 {
+                return Err(MerkleError::InvalidArgument(Reason::MergeAlign {
+                    segment: i + 1,
+                    start: leaves,
+                    leaves: t.leaves,
+                }));
+            }
+            offsets.push(leaves);
+            leaves += t.leaves;
+        }
+        if leaves == 0 {
+            return Ok(MerkleTree::from_hashes(Vec::new(), blocksize));
+        }
+
+        // 逐层生成节点：完全位于某一段内的节点直接复制，其余节点由下一层的两个子节点计算
+        let mut nodes: Vec<Vec<Digest>> = Vec::new();
+        let mut count = leaves;
+        for level in 0.. {
+            let mut cur = Vec::with_capacity(count);
+            let mut seg = 0;
+            for j in 0..count {
+                let start = j << level;
+                while seg + 1 < segments.len() && offsets[seg + 1] <= start {
+                    seg += 1;
+                }
+                if level <= top_level(segments[seg].leaves) {
+                    cur.push(segments[seg].nodes[level][(start - offsets[seg]) >> level]);
+                } else {
+                    let below = &nodes[level - 1];
+                    cur.push(match below.get(j * 2 + 1) {
+                        Some(right) => Digest::combine(&below[j * 2], right),
+                        None => below[j * 2],
+                    });
+                }
+            }
+            nodes.push(cur);
+            if count == 1 && level > 0 {
+                break;
+            }
+            count = count.div_ceil(2);
+        }
+
+        // 各段都记录了数据块位置时，按拼接后的数据重新计算偏移
+        let chunks = if segments.iter().all(|t| t.chunks.len() == t.leaves) {
+            let mut base = 0;
+            let mut chunks = Vec::with_capacity(leaves);
+            for t in &segments {
+                chunks.extend(t.chunks.iter().map(|c| Chunk {
+                    offset: base + c.offset,
+                    length: c.length,
+                }));
+                base += t.chunks.last().map_or(0, |c| c.offset + c.length);
+            }
+            chunks
+        } else {
+            Vec::new()
+        };
+
+        Ok(MerkleTree {
+            height: nodes.len() - 1,
+            nodes,
+            leaves,
+            blocksize,
+            chunks,
+            leaf_index: None,
+        })
+    }
+}
+
+// 无法对齐时使用的森林根：以各段(忽略空树)的根哈希为叶子构建树得到的根
+// 只有除最后一段外每段的大小都是同一个2的幂、最后一段不超过这个大小时，才与concat得到的根相同
+pub fn forest_root(trees: &[MerkleTree]) -> Result<Digest> {
+    let roots = trees
+        .iter()
+        .filter(|t| t.leaves > 0)
+        .map(|t| t.root_hash())
+        .collect::<Result<Vec<Digest>>>()?;
+    MerkleTree::from_hashes(roots, 0).root_hash()
+}
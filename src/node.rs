@@ -0,0 +1,131 @@
+//! 按节点位置浏览Merkle树：父节点、子节点、兄弟节点、覆盖的叶子范围以及各种遍历方式
+// 某一层节点数为奇数时最后一个节点直接提升到上一层，提升后的节点只有一个子节点，
+// 提升前的节点没有兄弟节点
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::{digest::Digest, tree::MerkleTree};
+
+// 节点位置，叶子为第0层，根节点位于第height层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NodeId {
+    pub level: usize,
+    pub index: usize, // 在该层中的下标
+}
+
+impl NodeId {
+    pub fn new(level: usize, index: usize) -> NodeId {
+        NodeId { level, index }
+    }
+
+    // 第index个叶子节点
+    pub fn leaf(index: usize) -> NodeId {
+        NodeId { level: 0, index }
+    }
+}
+
+impl MerkleTree {
+    // 根节点的位置，空树没有根节点
+    pub fn root_id(&self) -> Option<NodeId> {
+        if self.nodes.is_empty() {
+            None
+        } else {
+            Some(NodeId::new(self.height, 0))
+        }
+    }
+
+    pub fn contains(&self, id: NodeId) -> bool {
+        self.nodes
+            .get(id.level)
+            .is_some_and(|level| id.index < level.len())
+    }
+
+    pub fn node_hash(&self, id: NodeId) -> Option<Digest> {
+        self.nodes.get(id.level)?.get(id.index).copied()
+    }
+
+    // 根节点和不存在的节点没有父节点
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        if !self.contains(id) || id.level == self.height {
+            return None;
+        }
+        Some(NodeId::new(id.level + 1, id.index / 2))
+    }
+
+    // 子节点按从左到右的顺序返回，叶子没有子节点，提升后的节点只有一个子节点
+    pub fn children(&self, id: NodeId) -> impl DoubleEndedIterator<Item = NodeId> {
+        let (level, range) = if id.level > 0 && self.contains(id) {
+            let below = self.nodes[id.level - 1].len();
+            (id.level - 1, id.index * 2..(id.index * 2 + 2).min(below))
+        } else {
+            (0, 0..0)
+        };
+        range.map(move |index| NodeId::new(level, index))
+    }
+
+    // 与该节点共同组成父节点的另一个节点，根节点和被提升的节点没有兄弟节点
+    pub fn sibling(&self, id: NodeId) -> Option<NodeId> {
+        if id.level == self.height {
+            return None;
+        }
+        let other = NodeId::new(id.level, id.index ^ 1);
+        if self.contains(id) && self.contains(other) {
+            Some(other)
+        } else {
+            None
+        }
+    }
+
+    // 节点覆盖的叶子下标范围
+    pub fn leaf_range(&self, id: NodeId) -> Option<Range<usize>> {
+        if !self.contains(id) {
+            return None;
+        }
+        // 第level层的节点覆盖2^level个叶子，最后一个节点可能不满
+        let start = id.index << id.level;
+        let end = ((id.index + 1) << id.level).min(self.leaves);
+        Some(start..end)
+    }
+
+    // 广度优先遍历，从根节点开始逐层从左到右
+    pub fn bfs(&self) -> impl Iterator<Item = (NodeId, Digest)> + '_ {
+        (0..self.nodes.len())
+            .rev()
+            .flat_map(move |level| self.level_nodes(level))
+    }
+
+    // 深度优先(先序)遍历，先访问节点本身，再依次访问左、右子树
+    pub fn dfs(&self) -> Dfs<'_> {
+        Dfs {
+            tree: self,
+            stack: self.root_id().into_iter().collect(),
+        }
+    }
+
+    // 遍历某一层的所有节点，层不存在时为空
+    pub fn level_nodes(&self, level: usize) -> impl Iterator<Item = (NodeId, Digest)> + '_ {
+        self.nodes
+            .get(level)
+            .map(|nodes| nodes.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .enumerate()
+            .map(move |(index, hash)| (NodeId::new(level, index), *hash))
+    }
+}
+
+pub struct Dfs<'a> {
+    tree: &'a MerkleTree,
+    stack: Vec<NodeId>,
+}
+
+impl Iterator for Dfs<'_> {
+    type Item = (NodeId, Digest);
+
+    fn next(&mut self) -> Option<(NodeId, Digest)> {
+        let id = self.stack.pop()?;
+        // 右子节点先入栈，保证左子树先被访问
+        self.stack.extend(self.tree.children(id).rev());
+        Some((id, self.tree.nodes[id.level][id.index]))
+    }
+}
@@ -0,0 +1,1162 @@
+//! 命令行的结构化输出：每个命令的结果先整理为Report，再输出为JSON或本地化文本
+// JSON字段名是稳定的接口，脚本可以直接依赖；文本输出按Lang从消息目录中取得
+use std::fmt::{Display, Write};
+
+use crate::{
+    bench::BenchResult,
+    digest::Digest,
+    error::{DataKind, MerkleError, Reason},
+    export::{to_ascii, to_dot, to_json, ViewNode},
+    hash::HashSM3,
+    json::Value,
+    manifest::CheckReport,
+    proof::{fold_path, Proof},
+    sm2::{Sm2Error, MAX_ID_LEN},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    Zh,
+    En,
+}
+
+impl Lang {
+    // 根据LC_ALL/LANG环境变量选择语言，以"en"开头时使用英文，其余默认中文
+    pub fn from_env() -> Lang {
+        let var = std::env::var("LC_ALL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .or_else(|| std::env::var("LANG").ok())
+            .unwrap_or_default();
+        if var.starts_with("en") {
+            Lang::En
+        } else {
+            Lang::Zh
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Lang> {
+        match s {
+            "zh" => Some(Lang::Zh),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+}
+
+// 消息目录中的条目，文本中的{0}、{1}...依次替换为参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Msg {
+    RootHash,
+    MatchExpected,
+    MismatchExpected,
+    TreeInfo,
+    Levels,
+    Tree1Root,
+    Tree2Root,
+    DiffBlocks,
+    StructMismatch,
+    ProofHeader,
+    ProofLeft,
+    ProofRight,
+    ProofItem,
+    ProofSteps,
+    LeftHash,
+    RightHash,
+    Combined,
+    ProofRoot,
+    TargetRoot,
+    VerifyResult,
+    VerifyReason,
+    IndexOutOfRange,
+    PatchWritten,
+    BenchThroughput,
+    BenchOps,
+    ManifestWritten,
+    Missing,
+    Extra,
+    Modified,
+    ModifiedBlocks,
+    InvalidEntry,
+    CheckOk,
+    Error,
+    // 错误信息
+    EmptyTree,
+    OutOfRange,
+    InvalidData,
+    VerifyFailed,
+    ProtocolError,
+    // 库返回的错误原因
+    Truncated,
+    DigestLength,
+    VarintOverflow,
+    NumberOutOfRange,
+    NotFormat,
+    TrailingData,
+    KindProof,
+    KindPatch,
+    KindManifest,
+    KindSignedRoot,
+    ProofIndex,
+    ProofLength,
+    PatchOp,
+    BlockCount,
+    BlockRange,
+    CopyRange,
+    PatchLength,
+    ChunkLengths,
+    ManifestLine,
+    FileName,
+    FrameLength,
+    ReplyCount,
+    CorruptEntry,
+    SthMismatch,
+    KeyFile,
+    JsonError,
+    MissingLeaves,
+    Sm2InvalidKey,
+    Sm2IdTooLong,
+    Sm2Rng,
+    LeafHash,
+    NodeRange,
+    PathShape,
+    RootMismatch,
+    Children,
+    BlockProof,
+    Signature,
+    DirChainEmpty,
+    DirPosition,
+    DirEntry,
+    StoreLeaves,
+    StoreBlock,
+    FileSize,
+    Tag,
+    Consistency,
+    ServerError,
+    UnexpectedMessage,
+    Closed,
+    BadRequest,
+    RequestTooLong,
+    UnknownEndpoint,
+    MethodNotAllowed,
+    FastCdc,
+    CdcAvg,
+    NoSuchHash,
+    NoSuchNode,
+    ShardCount,
+    MergeBlocksize,
+    MergeAlign,
+    ConsistencySize,
+    NoVersion,
+    EntryTooLong,
+    ZeroInterval,
+    MissingParam,
+    ParamValue,
+    EmptyName,
+    NoSuchFile,
+    FileType,
+    // 命令行参数错误
+    MissingValue,
+    UnknownOption,
+    InvalidValue,
+    UnknownFormat,
+    ZeroBlocksize,
+    UnknownLang,
+    InvalidHash,
+    MissingArg,
+    NeedOption,
+    NeedHexRoot,
+    UnknownCommand,
+    ExtraArg,
+    InvalidBlock,
+    ArgFile,
+    ArgFile1,
+    ArgFile2,
+    ArgOldFile,
+    ArgNewFile,
+    ArgDir,
+    ArgManifest,
+    // 帮助
+    Usage,
+    CommandUsage,
+    CommonHelp,
+    HelpAbout,
+    RootUsage,
+    RootAbout,
+    BuildUsage,
+    BuildAbout,
+    CompareUsage,
+    CompareAbout,
+    ProveUsage,
+    ProveAbout,
+    VerifyUsage,
+    VerifyAbout,
+    DiffUsage,
+    DiffAbout,
+    BenchUsage,
+    BenchAbout,
+    ManifestUsage,
+    ManifestAbout,
+    CheckUsage,
+    CheckAbout,
+    ShowUsage,
+    ShowAbout,
+}
+
+fn zh(msg: Msg) -> &'static str {
+    match msg {
+        Msg::RootHash => "根哈希: {0}",
+        Msg::MatchExpected => "与期望值一致",
+        Msg::MismatchExpected => "与期望值不一致",
+        Msg::TreeInfo => "数据块大小: {0}  叶子节点数量: {1}  树高: {2}",
+        Msg::Levels => "每层节点数(自下而上): {0}",
+        Msg::Tree1Root => "树1根哈希: {0}",
+        Msg::Tree2Root => "树2根哈希: {0}",
+        Msg::DiffBlocks => "对比得到不同的数据块为(blocksize: {0}) \n{1}",
+        Msg::StructMismatch => "两棵树结构不同无法比较",
+        Msg::ProofHeader => {
+            "====PROOF====\n数据块大小: {0}  数据块下标: {1}  叶子数量: {2}\n数据块哈希值: {3}"
+        }
+        Msg::ProofLeft => "左节点",
+        Msg::ProofRight => "右节点",
+        Msg::ProofItem => "proof{0} {1}: {2}",
+        Msg::ProofSteps => "====生成根哈希过程====",
+        Msg::LeftHash => "左节点哈希值: {0}",
+        Msg::RightHash => "右节点哈希值: {0}",
+        Msg::Combined => "组合后哈希值: {0}\n",
+        Msg::ProofRoot => "根节点哈希值: {0}\n====================",
+        Msg::TargetRoot => "目标树的根杂凑值为{0}",
+        Msg::VerifyResult => "验证proof结果 {0}",
+        Msg::VerifyReason => "原因: {0}",
+        Msg::IndexOutOfRange => "生成proof失败， 下标({0})越界， 只有{1}个数据块",
+        Msg::PatchWritten => "补丁已写入{0}: {1}个操作, 新数据{2}字节, 补丁大小{3}字节",
+        Msg::BenchThroughput => "{0} {1} MB/s  {2} 次/秒",
+        Msg::BenchOps => "{0} {1} 次/秒",
+        Msg::ManifestWritten => "已为{0}个文件生成清单: {1}",
+        Msg::Missing => "缺失: {0}",
+        Msg::Extra => "多余: {0}",
+        Msg::Modified => "已修改: {0}",
+        Msg::ModifiedBlocks => "已修改: {0} 不同的数据块为{1}",
+        Msg::InvalidEntry => "清单记录有误: {0}",
+        Msg::CheckOk => "目录与清单一致",
+        Msg::Error => "错误: {0}",
+        Msg::EmptyTree => "树为空",
+        Msg::OutOfRange => "下标({0})越界，树只有{1}个数据块",
+        Msg::InvalidData => "数据格式有误: {0}",
+        Msg::VerifyFailed => "验证失败: {0}",
+        Msg::ProtocolError => "协议错误: {0}",
+        Msg::Truncated => "数据被截断",
+        Msg::DigestLength => "哈希长度有误",
+        Msg::VarintOverflow => "变长整数溢出",
+        Msg::NumberOutOfRange => "数值超出范围",
+        Msg::NotFormat => "不是有效的{0}",
+        Msg::TrailingData => "{0}末尾有多余数据",
+        Msg::KindProof => "proof文件",
+        Msg::KindPatch => "补丁文件",
+        Msg::KindManifest => "清单文件",
+        Msg::KindSignedRoot => "签名的根哈希",
+        Msg::ProofIndex => "proof中数据块下标越界",
+        Msg::ProofLength => "proof链长度与数据块下标不符",
+        Msg::PatchOp => "未知的补丁操作{0}",
+        Msg::BlockCount => "文件的数据块数量与树不符",
+        Msg::BlockRange => "数据块超出文件范围",
+        Msg::CopyRange => "复制区间超出旧文件范围",
+        Msg::PatchLength => "重建后的文件长度与补丁不符",
+        Msg::ChunkLengths => "数据块长度与文件长度不符",
+        Msg::ManifestLine => "清单第{0}行有误",
+        Msg::FileName => "文件名不是有效的UTF-8: {0}",
+        Msg::FrameLength => "消息长度有误",
+        Msg::ReplyCount => "服务端返回的数量有误",
+        Msg::CorruptEntry => "entries文件偏移{0}处的条目长度{1}超过上限{2}",
+        Msg::SthMismatch => "已发布的树头({0}个条目)与日志中的{1}个条目不一致",
+        Msg::KeyFile => "私钥文件{0}有误",
+        Msg::JsonError => "JSON第{0}个字节处格式有误",
+        Msg::MissingLeaves => "存储的清单中{0}没有记录叶子哈希",
+        Msg::Sm2InvalidKey => "私钥不在[1, n-2]中",
+        Msg::Sm2IdTooLong => "用户标识超过{0}字节",
+        Msg::Sm2Rng => "无法获取随机数",
+        Msg::LeafHash => "数据块的哈希与proof中的叶子哈希{0}不一致",
+        Msg::NodeRange => "第{0}层下标{1}的节点超出范围(叶子数量{2})",
+        Msg::PathShape => "proof链与节点位置(第{0}层下标{1})和叶子数量{2}不符",
+        Msg::RootMismatch => "计算出的根哈希{0}与期望的根哈希{1}不一致",
+        Msg::Children => "第{0}层第{1}个节点的子节点与其哈希不符",
+        Msg::BlockProof => "数据块{0}的proof无效",
+        Msg::Signature => "根哈希{0}的签名无效",
+        Msg::DirChainEmpty => "目录链为空",
+        Msg::DirPosition => "目录链第{0}级的位置{1}超出目录项范围",
+        Msg::DirEntry => "目录链第{0}级的目录项{1}与计算结果不符",
+        Msg::StoreLeaves => "{0}的叶子哈希与根哈希不一致",
+        Msg::StoreBlock => "{0}的第{1}个数据块缺失或损坏",
+        Msg::FileSize => "{0}的大小为{1}，清单中为{2}",
+        Msg::Tag => "数据块{0}的认证标签不符",
+        Msg::Consistency => "一致性proof与根哈希不符",
+        Msg::ServerError => "服务端错误: {0}",
+        Msg::UnexpectedMessage => "收到意外的消息类型",
+        Msg::Closed => "服务端关闭了连接",
+        Msg::BadRequest => "HTTP请求格式有误",
+        Msg::RequestTooLong => "HTTP请求过长",
+        Msg::UnknownEndpoint => "未知的接口{0}",
+        Msg::MethodNotAllowed => "{0}不支持{1}",
+        Msg::FastCdc => "FastCDC的数据块大小需满足 0 < 最小值({0}) <= 平均值({1}) <= 最大值({2})",
+        Msg::CdcAvg => "FastCDC的平均数据块大小{0}过大",
+        Msg::NoSuchHash => "没有哈希为{0}的数据块",
+        Msg::NoSuchNode => "第{0}层没有下标为{1}的节点",
+        Msg::ShardCount => "{0}个数据块按每片2^{1}个应分为{2}片，实际有{3}个子树根",
+        Msg::MergeBlocksize => "第{0}段的数据块大小{1}与第1段的{2}不同",
+        Msg::MergeAlign => "第{0}段(起始下标{1}，{2}个数据块)与合并后的树不对齐，无法直接合并",
+        Msg::ConsistencySize => "无法证明{0}个叶子的树与{1}个叶子的树一致",
+        Msg::NoVersion => "树有{0}个叶子，没有叶子数量为{1}的版本",
+        Msg::EntryTooLong => "条目长度{0}超过上限{1}",
+        Msg::ZeroInterval => "签名树头的间隔必须大于0",
+        Msg::MissingParam => "缺少参数{0}",
+        Msg::ParamValue => "参数{0}的值无效: {1}",
+        Msg::EmptyName => "文件名不能为空",
+        Msg::NoSuchFile => "存储中没有文件{0}",
+        Msg::FileType => "不支持的文件类型: {0}",
+        Msg::MissingValue => "选项--{0}缺少参数值",
+        Msg::UnknownOption => "未知的选项: {0}",
+        Msg::InvalidValue => "选项--{0}的值无效: {1}",
+        Msg::UnknownFormat => "未知的输出格式: {0}",
+        Msg::ZeroBlocksize => "数据块大小必须大于0",
+        Msg::UnknownLang => "未知的语言: {0}",
+        Msg::InvalidHash => "哈希值格式有误: {0}",
+        Msg::MissingArg => "{0}缺少参数: {1}",
+        Msg::NeedOption => "{0}需要--{1}",
+        Msg::NeedHexRoot => "verify需要十六进制的--root",
+        Msg::UnknownCommand => "未知的命令: {0}",
+        Msg::ExtraArg => "多余的参数: {0}",
+        Msg::InvalidBlock => "数据块既不是文件也不是十六进制数据: {0}",
+        Msg::ArgFile => "文件",
+        Msg::ArgFile1 => "文件1",
+        Msg::ArgFile2 => "文件2",
+        Msg::ArgOldFile => "旧文件",
+        Msg::ArgNewFile => "新文件",
+        Msg::ArgDir => "目录",
+        Msg::ArgManifest => "清单文件",
+        Msg::Usage => "用法: merkle <命令> [参数]\n\n命令:",
+        Msg::CommandUsage => "用法: merkle {0}\n\n{1}\n\n{2}",
+        Msg::CommonHelp => {
+            "通用选项:\n  --format json|text  输出格式(默认text)\n  --lang zh|en        文本输出的语言(默认根据LANG环境变量)\n  -h, --help          显示帮助\n\n退出码: 0 成功, 1 不一致或验证失败, 2 参数有误, 3 运行错误"
+        }
+        Msg::HelpAbout => "显示命令的帮助",
+        Msg::RootUsage => "root <文件> [--block-size N] [--hash 十六进制]",
+        Msg::RootAbout => "计算文件的根哈希，给定--hash时检查是否与之一致",
+        Msg::BuildUsage => "build <文件> [--block-size N] [--hash 十六进制]",
+        Msg::BuildAbout => "构建Merkle树并输出叶子数量、树高和每层节点数",
+        Msg::CompareUsage => "compare <文件1> <文件2> [--block-size N]",
+        Msg::CompareAbout => "比较两个文件，输出不同的数据块下标",
+        Msg::ProveUsage => "prove <文件> --index N [--block-size N] [--out proof文件]",
+        Msg::ProveAbout => "为文件中第N个数据块生成proof，给定--out时把proof写入文件",
+        Msg::VerifyUsage => {
+            "verify <文件1> <文件2> --index N [--block-size N]\n       merkle verify --root 十六进制 --proof proof文件 --block 文件|十六进制"
+        }
+        Msg::VerifyAbout => {
+            "用文件2的树验证文件1中第N个数据块，或用proof文件和根哈希验证数据块(无需原始文件)"
+        }
+        Msg::DiffUsage => "diff <旧文件> <新文件> --out 补丁文件 [--block-size N] [--cdc]",
+        Msg::DiffAbout => {
+            "生成从旧文件到新文件的补丁，--cdc使用内容定义分块(平均大小为block-size)"
+        }
+        Msg::BenchUsage => "bench [--max-size 32M] [--time 毫秒] [--block-size N]",
+        Msg::BenchAbout => "测试SM3、构建树、生成proof、比较和验证的性能(MB/s、ops/s)",
+        Msg::ManifestUsage => "manifest <目录> <清单文件> [--block-size N] [--no-leaves]",
+        Msg::ManifestAbout => {
+            "为目录生成清单，清单文件名以.bin结尾时使用二进制格式，--no-leaves不记录叶子哈希"
+        }
+        Msg::CheckUsage => "check <目录> <清单文件>",
+        Msg::CheckAbout => "用清单检查目录",
+        Msg::ShowUsage => "show <文件> [文件2] [--depth N] [--dot] [--block-size N]",
+        Msg::ShowAbout => {
+            "显示文件的Merkle树(ASCII，--dot输出Graphviz DOT)，给定文件2时标出不同的节点"
+        }
+    }
+}
+
+fn en(msg: Msg) -> &'static str {
+    match msg {
+        Msg::RootHash => "Root hash: {0}",
+        Msg::MatchExpected => "matches the expected hash",
+        Msg::MismatchExpected => "does not match the expected hash",
+        Msg::TreeInfo => "Block size: {0}  leaves: {1}  height: {2}",
+        Msg::Levels => "Nodes per level (bottom-up): {0}",
+        Msg::Tree1Root => "Tree 1 root: {0}",
+        Msg::Tree2Root => "Tree 2 root: {0}",
+        Msg::DiffBlocks => "Differing blocks (blocksize: {0})\n{1}",
+        Msg::StructMismatch => "the two trees have different shapes and cannot be compared",
+        Msg::ProofHeader => {
+            "====PROOF====\nBlock size: {0}  block index: {1}  leaves: {2}\nBlock hash: {3}"
+        }
+        Msg::ProofLeft => "left",
+        Msg::ProofRight => "right",
+        Msg::ProofItem => "proof{0} {1}: {2}",
+        Msg::ProofSteps => "====Computing the root====",
+        Msg::LeftHash => "Left hash:     {0}",
+        Msg::RightHash => "Right hash:    {0}",
+        Msg::Combined => "Combined hash: {0}\n",
+        Msg::ProofRoot => "Root hash: {0}\n====================",
+        Msg::TargetRoot => "Target root: {0}",
+        Msg::VerifyResult => "Proof verification: {0}",
+        Msg::VerifyReason => "reason: {0}",
+        Msg::IndexOutOfRange => "cannot generate proof: index {0} out of range, only {1} blocks",
+        Msg::PatchWritten => {
+            "Patch written to {0}: {1} operations, {2} literal bytes, {3} bytes total"
+        }
+        Msg::BenchThroughput => "{0} {1} MB/s  {2} ops/s",
+        Msg::BenchOps => "{0} {1} ops/s",
+        Msg::ManifestWritten => "Wrote manifest for {0} files: {1}",
+        Msg::Missing => "missing: {0}",
+        Msg::Extra => "extra: {0}",
+        Msg::Modified => "modified: {0}",
+        Msg::ModifiedBlocks => "modified: {0} differing blocks {1}",
+        Msg::InvalidEntry => "invalid manifest entry: {0}",
+        Msg::CheckOk => "directory matches the manifest",
+        Msg::Error => "error: {0}",
+        Msg::EmptyTree => "the tree is empty",
+        Msg::OutOfRange => "index {0} out of range, the tree has only {1} blocks",
+        Msg::InvalidData => "invalid data: {0}",
+        Msg::VerifyFailed => "verification failed: {0}",
+        Msg::ProtocolError => "protocol error: {0}",
+        Msg::Truncated => "data is truncated",
+        Msg::DigestLength => "wrong hash length",
+        Msg::VarintOverflow => "varint overflow",
+        Msg::NumberOutOfRange => "number out of range",
+        Msg::NotFormat => "not a valid {0}",
+        Msg::TrailingData => "trailing data after the {0}",
+        Msg::KindProof => "proof file",
+        Msg::KindPatch => "patch file",
+        Msg::KindManifest => "manifest",
+        Msg::KindSignedRoot => "signed root",
+        Msg::ProofIndex => "block index in the proof is out of range",
+        Msg::ProofLength => "proof chain length does not match the block index",
+        Msg::PatchOp => "unknown patch operation {0}",
+        Msg::BlockCount => "block count of the file does not match the tree",
+        Msg::BlockRange => "block lies outside the file",
+        Msg::CopyRange => "copy range lies outside the old file",
+        Msg::PatchLength => "rebuilt file length does not match the patch",
+        Msg::ChunkLengths => "block lengths do not match the file length",
+        Msg::ManifestLine => "invalid manifest line {0}",
+        Msg::FileName => "file name is not valid UTF-8: {0}",
+        Msg::FrameLength => "invalid message length",
+        Msg::ReplyCount => "the server returned the wrong number of items",
+        Msg::CorruptEntry => {
+            "entry length {1} at offset {0} of the entries file exceeds the limit {2}"
+        }
+        Msg::SthMismatch => {
+            "the published tree head ({0} entries) does not match the {1} entries in the log"
+        }
+        Msg::KeyFile => "invalid private key file {0}",
+        Msg::JsonError => "malformed JSON at byte {0}",
+        Msg::MissingLeaves => "the store manifest has no leaf hashes for {0}",
+        Msg::Sm2InvalidKey => "private key is not in [1, n-2]",
+        Msg::Sm2IdTooLong => "user ID is longer than {0} bytes",
+        Msg::Sm2Rng => "cannot get random numbers",
+        Msg::LeafHash => "block hash does not match the leaf hash {0} in the proof",
+        Msg::NodeRange => "node {1} at level {0} is out of range ({2} leaves)",
+        Msg::PathShape => "proof chain does not match node {1} at level {0} with {2} leaves",
+        Msg::RootMismatch => "computed root {0} does not match the expected root {1}",
+        Msg::Children => "children of node {1} at level {0} do not match its hash",
+        Msg::BlockProof => "invalid proof for block {0}",
+        Msg::Signature => "invalid signature on root {0}",
+        Msg::DirChainEmpty => "the directory chain is empty",
+        Msg::DirPosition => "position {1} at step {0} of the directory chain is out of range",
+        Msg::DirEntry => "entry {1} at step {0} of the directory chain does not match",
+        Msg::StoreLeaves => "leaf hashes of {0} do not match its root",
+        Msg::StoreBlock => "block {1} of {0} is missing or corrupt",
+        Msg::FileSize => "{0} has {1} bytes, the manifest says {2}",
+        Msg::Tag => "authentication tag of block {0} does not match",
+        Msg::Consistency => "the consistency proof does not match the roots",
+        Msg::ServerError => "server error: {0}",
+        Msg::UnexpectedMessage => "unexpected message type",
+        Msg::Closed => "the server closed the connection",
+        Msg::BadRequest => "malformed HTTP request",
+        Msg::RequestTooLong => "HTTP request too long",
+        Msg::UnknownEndpoint => "unknown endpoint {0}",
+        Msg::MethodNotAllowed => "{0} does not support {1}",
+        Msg::FastCdc => "FastCDC sizes must satisfy 0 < min ({0}) <= avg ({1}) <= max ({2})",
+        Msg::CdcAvg => "FastCDC average block size {0} is too large",
+        Msg::NoSuchHash => "no block has hash {0}",
+        Msg::NoSuchNode => "level {0} has no node {1}",
+        Msg::ShardCount => {
+            "{0} blocks in shards of 2^{1} make {2} shards, but there are {3} subtree roots"
+        }
+        Msg::MergeBlocksize => "segment {0} has block size {1}, segment 1 has {2}",
+        Msg::MergeAlign => {
+            "segment {0} (starting at {1}, {2} blocks) is not aligned in the merged tree"
+        }
+        Msg::ConsistencySize => {
+            "cannot prove a tree of {0} leaves consistent with one of {1} leaves"
+        }
+        Msg::NoVersion => "the tree has {0} leaves, there is no version with {1} leaves",
+        Msg::EntryTooLong => "entry length {0} exceeds the limit {1}",
+        Msg::ZeroInterval => "the signing interval must be greater than 0",
+        Msg::MissingParam => "missing parameter {0}",
+        Msg::ParamValue => "invalid value for parameter {0}: {1}",
+        Msg::EmptyName => "file name must not be empty",
+        Msg::NoSuchFile => "no file {0} in the store",
+        Msg::FileType => "unsupported file type: {0}",
+        Msg::MissingValue => "option --{0} needs a value",
+        Msg::UnknownOption => "unknown option: {0}",
+        Msg::InvalidValue => "invalid value for --{0}: {1}",
+        Msg::UnknownFormat => "unknown output format: {0}",
+        Msg::ZeroBlocksize => "block size must be greater than 0",
+        Msg::UnknownLang => "unknown language: {0}",
+        Msg::InvalidHash => "malformed hash: {0}",
+        Msg::MissingArg => "{0}: missing argument {1}",
+        Msg::NeedOption => "{0} needs --{1}",
+        Msg::NeedHexRoot => "verify needs a hexadecimal --root",
+        Msg::UnknownCommand => "unknown command: {0}",
+        Msg::ExtraArg => "unexpected argument: {0}",
+        Msg::InvalidBlock => "block is neither a file nor hexadecimal data: {0}",
+        Msg::ArgFile => "<file>",
+        Msg::ArgFile1 => "<file1>",
+        Msg::ArgFile2 => "<file2>",
+        Msg::ArgOldFile => "<old>",
+        Msg::ArgNewFile => "<new>",
+        Msg::ArgDir => "<dir>",
+        Msg::ArgManifest => "<manifest>",
+        Msg::Usage => "Usage: merkle <command> [options]\n\nCommands:",
+        Msg::CommandUsage => "Usage: merkle {0}\n\n{1}\n\n{2}",
+        Msg::CommonHelp => {
+            "Common options:\n  --format json|text  output format (default text)\n  --lang zh|en        language of text output (default from LANG)\n  -h, --help          show help\n\nExit codes: 0 success, 1 mismatch or failed verification, 2 invalid arguments, 3 runtime error"
+        }
+        Msg::HelpAbout => "show help for a command",
+        Msg::RootUsage => "root <file> [--block-size N] [--hash HEX]",
+        Msg::RootAbout => "compute the root hash of a file; with --hash, check that it matches",
+        Msg::BuildUsage => "build <file> [--block-size N] [--hash HEX]",
+        Msg::BuildAbout => "build the Merkle tree and print the leaves, height and nodes per level",
+        Msg::CompareUsage => "compare <file1> <file2> [--block-size N]",
+        Msg::CompareAbout => "compare two files and print the indices of differing blocks",
+        Msg::ProveUsage => "prove <file> --index N [--block-size N] [--out PROOF]",
+        Msg::ProveAbout => "generate a proof for block N of the file; with --out, write it to a file",
+        Msg::VerifyUsage => {
+            "verify <file1> <file2> --index N [--block-size N]\n       merkle verify --root HEX --proof PROOF --block FILE|HEX"
+        }
+        Msg::VerifyAbout => {
+            "verify block N of file1 against the tree of file2, or verify a block with a proof file and a root hash (no original file needed)"
+        }
+        Msg::DiffUsage => "diff <old> <new> --out PATCH [--block-size N] [--cdc]",
+        Msg::DiffAbout => {
+            "write a patch from the old file to the new one; --cdc uses content-defined chunking with block-size as the average"
+        }
+        Msg::BenchUsage => "bench [--max-size 32M] [--time MS] [--block-size N]",
+        Msg::BenchAbout => {
+            "benchmark SM3, tree building, proof generation, comparison and verification (MB/s, ops/s)"
+        }
+        Msg::ManifestUsage => "manifest <dir> <manifest> [--block-size N] [--no-leaves]",
+        Msg::ManifestAbout => {
+            "write a manifest for a directory; a manifest name ending in .bin uses the binary format, --no-leaves omits leaf hashes"
+        }
+        Msg::CheckUsage => "check <dir> <manifest>",
+        Msg::CheckAbout => "check a directory against a manifest",
+        Msg::ShowUsage => "show <file> [file2] [--depth N] [--dot] [--block-size N]",
+        Msg::ShowAbout => {
+            "print the Merkle tree of a file (ASCII, or Graphviz DOT with --dot); with file2, mark the differing nodes"
+        }
+    }
+}
+
+// 取出消息并替换参数
+// 从左到右只扫描一遍，参数中出现的"{1}"等文本不会再被替换
+pub fn tr(lang: Lang, msg: Msg, args: &[&dyn Display]) -> String {
+    let mut rest = match lang {
+        Lang::Zh => zh(msg),
+        Lang::En => en(msg),
+    };
+    let mut text = String::with_capacity(rest.len());
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let arg = after.find('}').and_then(|end| {
+            let i: usize = after[..end].parse().ok()?;
+            Some((args.get(i)?, end))
+        });
+        match arg {
+            Some((arg, end)) => {
+                let _ = write!(text, "{}", arg);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+// 本地化的错误信息，库返回的原因同样按语言从消息目录中取得
+pub fn error_text(lang: Lang, e: &MerkleError) -> String {
+    match e {
+        MerkleError::EmptyTree => tr(lang, Msg::EmptyTree, &[]),
+        MerkleError::StructMismatch => tr(lang, Msg::StructMismatch, &[]),
+        MerkleError::IndexOutOfRange { index, leaves } => {
+            tr(lang, Msg::OutOfRange, &[index, leaves])
+        }
+        MerkleError::InvalidArgument(r) => reason_text(lang, r),
+        MerkleError::InvalidData(r) => tr(lang, Msg::InvalidData, &[&reason_text(lang, r)]),
+        MerkleError::VerifyFailed(r) => tr(lang, Msg::VerifyFailed, &[&reason_text(lang, r)]),
+        MerkleError::Protocol(r) => tr(lang, Msg::ProtocolError, &[&reason_text(lang, r)]),
+        MerkleError::Io(e) => e.to_string(),
+    }
+}
+
+pub fn reason_text(lang: Lang, r: &Reason) -> String {
+    let t = |msg: Msg, args: &[&dyn Display]| tr(lang, msg, args);
+    let kind = |k: &DataKind| {
+        t(
+            match k {
+                DataKind::Proof => Msg::KindProof,
+                DataKind::Patch => Msg::KindPatch,
+                DataKind::Manifest => Msg::KindManifest,
+                DataKind::SignedRoot => Msg::KindSignedRoot,
+            },
+            &[],
+        )
+    };
+    match r {
+        Reason::Truncated => t(Msg::Truncated, &[]),
+        Reason::DigestLength => t(Msg::DigestLength, &[]),
+        Reason::VarintOverflow => t(Msg::VarintOverflow, &[]),
+        Reason::NumberOutOfRange => t(Msg::NumberOutOfRange, &[]),
+        Reason::NotFormat(k) => t(Msg::NotFormat, &[&kind(k)]),
+        Reason::TrailingData(k) => t(Msg::TrailingData, &[&kind(k)]),
+        Reason::ProofIndex => t(Msg::ProofIndex, &[]),
+        Reason::ProofLength => t(Msg::ProofLength, &[]),
+        Reason::PatchOp(op) => t(Msg::PatchOp, &[op]),
+        Reason::BlockCount => t(Msg::BlockCount, &[]),
+        Reason::BlockRange => t(Msg::BlockRange, &[]),
+        Reason::CopyRange => t(Msg::CopyRange, &[]),
+        Reason::PatchLength => t(Msg::PatchLength, &[]),
+        Reason::ChunkLengths => t(Msg::ChunkLengths, &[]),
+        Reason::ManifestLine(line) => t(Msg::ManifestLine, &[line]),
+        Reason::HashFormat(s) => t(Msg::InvalidHash, &[s]),
+        Reason::FileName(name) => t(Msg::FileName, &[name]),
+        Reason::FrameLength => t(Msg::FrameLength, &[]),
+        Reason::ReplyCount => t(Msg::ReplyCount, &[]),
+        Reason::CorruptEntry { offset, len, max } => t(Msg::CorruptEntry, &[offset, len, max]),
+        Reason::SthMismatch { signed, leaves } => t(Msg::SthMismatch, &[signed, leaves]),
+        Reason::KeyFile(path) => t(Msg::KeyFile, &[path]),
+        Reason::Json(pos) => t(Msg::JsonError, &[pos]),
+        Reason::MissingLeaves(path) => t(Msg::MissingLeaves, &[path]),
+        Reason::Sm2(Sm2Error::InvalidKey) => t(Msg::Sm2InvalidKey, &[]),
+        Reason::Sm2(Sm2Error::IdTooLong) => t(Msg::Sm2IdTooLong, &[&MAX_ID_LEN]),
+        Reason::Sm2(Sm2Error::Rng) => t(Msg::Sm2Rng, &[]),
+        Reason::LeafHash(leaf) => t(Msg::LeafHash, &[leaf]),
+        Reason::NodeRange {
+            level,
+            index,
+            leaves,
+        } => t(Msg::NodeRange, &[level, index, leaves]),
+        Reason::PathShape {
+            level,
+            index,
+            leaves,
+        } => t(Msg::PathShape, &[level, index, leaves]),
+        Reason::RootMismatch { computed, expected } => t(Msg::RootMismatch, &[computed, expected]),
+        Reason::Children { level, parent } => t(Msg::Children, &[level, parent]),
+        Reason::BlockProof(index) => t(Msg::BlockProof, &[index]),
+        Reason::Signature(root) => t(Msg::Signature, &[root]),
+        Reason::DirChainEmpty => t(Msg::DirChainEmpty, &[]),
+        Reason::DirPosition { step, position } => t(Msg::DirPosition, &[step, position]),
+        Reason::DirEntry { step, name } => t(Msg::DirEntry, &[step, name]),
+        Reason::StoreLeaves(name) => t(Msg::StoreLeaves, &[name]),
+        Reason::StoreBlock { name, index } => t(Msg::StoreBlock, &[name, index]),
+        Reason::FileSize {
+            name,
+            size,
+            expected,
+        } => t(Msg::FileSize, &[name, size, expected]),
+        Reason::Tag(index) => t(Msg::Tag, &[index]),
+        Reason::Consistency => t(Msg::Consistency, &[]),
+        Reason::ServerError(msg) => t(Msg::ServerError, &[msg]),
+        Reason::UnexpectedMessage => t(Msg::UnexpectedMessage, &[]),
+        Reason::Closed => t(Msg::Closed, &[]),
+        Reason::BadRequest => t(Msg::BadRequest, &[]),
+        Reason::RequestTooLong => t(Msg::RequestTooLong, &[]),
+        Reason::UnknownEndpoint(path) => t(Msg::UnknownEndpoint, &[path]),
+        Reason::MethodNotAllowed { path, method } => t(Msg::MethodNotAllowed, &[path, method]),
+        Reason::Message(msg) => msg.clone(),
+        Reason::ZeroBlocksize => t(Msg::ZeroBlocksize, &[]),
+        Reason::FastCdc { min, avg, max } => t(Msg::FastCdc, &[min, avg, max]),
+        Reason::CdcAvg(avg) => t(Msg::CdcAvg, &[avg]),
+        Reason::NoSuchHash(hash) => t(Msg::NoSuchHash, &[hash]),
+        Reason::NoSuchNode { level, index } => t(Msg::NoSuchNode, &[level, index]),
+        Reason::ShardCount {
+            leaves,
+            level,
+            expected,
+            actual,
+        } => t(Msg::ShardCount, &[leaves, level, expected, actual]),
+        Reason::MergeBlocksize {
+            segment,
+            blocksize,
+            expected,
+        } => t(Msg::MergeBlocksize, &[segment, blocksize, expected]),
+        Reason::MergeAlign {
+            segment,
+            start,
+            leaves,
+        } => t(Msg::MergeAlign, &[segment, start, leaves]),
+        Reason::ConsistencySize { old, size } => t(Msg::ConsistencySize, &[old, size]),
+        Reason::NoVersion { leaves, size } => t(Msg::NoVersion, &[leaves, size]),
+        Reason::EntryTooLong { len, max } => t(Msg::EntryTooLong, &[len, max]),
+        Reason::ZeroInterval => t(Msg::ZeroInterval, &[]),
+        Reason::MissingParam(name) => t(Msg::MissingParam, &[name]),
+        Reason::ParamValue { name, value } => t(Msg::ParamValue, &[name, value]),
+        Reason::EmptyName => t(Msg::EmptyName, &[]),
+        Reason::NoSuchFile(name) => t(Msg::NoSuchFile, &[name]),
+        Reason::FileType(path) => t(Msg::FileType, &[path]),
+    }
+}
+
+// proof链中的一个节点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainItem {
+    pub hash: Digest,
+    pub left: bool, // 该节点位于左侧
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProofReport {
+    pub index: usize,
+    pub blocksize: usize,
+    pub leaves: usize,
+    pub leaf: Digest, // 数据块的哈希
+    pub chain: Vec<ChainItem>,
+    pub root: Digest,                // 由proof计算出的根哈希
+    pub target_root: Option<Digest>, // 验证时使用的根哈希
+    pub valid: Option<bool>,         // 验证结果
+    pub reason: Option<String>,      // 验证失败的原因
+}
+
+impl ProofReport {
+    pub fn new<T: HashSM3>(proof: &Proof<T>) -> ProofReport {
+        ProofReport {
+            index: proof.index,
+            blocksize: proof.blocksize,
+            leaves: proof.leaves,
+            leaf: proof.data.sm3(),
+            chain: proof
+                .chain
+                .iter()
+                .zip(proof.pos_chain.iter())
+                .map(|(hash, left)| ChainItem {
+                    hash: *hash,
+                    left: *left,
+                })
+                .collect(),
+            root: fold_path(proof.data.sm3(), &proof.chain, &proof.pos_chain),
+            target_root: None,
+            valid: None,
+            reason: None,
+        }
+    }
+
+    // 记录用目标根哈希验证的结果，reason为验证失败的原因
+    pub fn verified(mut self, target_root: Digest, reason: Option<String>) -> ProofReport {
+        self.target_root = Some(target_root);
+        self.valid = Some(reason.is_none());
+        self.reason = reason;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Report {
+    Root {
+        file: String,
+        blocksize: usize,
+        leaves: usize,
+        height: usize,
+        levels: Option<Vec<usize>>, // 仅build命令输出每层节点数
+        root: Digest,
+        expected: Option<Digest>,
+    },
+    Compare {
+        blocksize: usize,
+        root1: Digest,
+        root2: Digest,
+        leaves1: usize,
+        leaves2: usize,
+        different: Option<Vec<usize>>, // 结构不同无法比较时为None
+    },
+    Proof(ProofReport),
+    // proof文件无法解码，按验证失败处理
+    BadProof {
+        target_root: Digest,
+        reason: String,
+    },
+    Diff {
+        patch: String,
+        ops: usize,
+        literal_bytes: usize,
+        patch_bytes: usize,
+        new_root: Digest,
+    },
+    Bench(Vec<BenchResult>),
+    Manifest {
+        manifest: String,
+        files: usize,
+    },
+    Check(CheckReport),
+    Show {
+        tree: ViewNode,
+        dot: bool, // 文本输出使用Graphviz DOT而不是ASCII树
+    },
+}
+
+fn hex(hash: &Digest) -> Value {
+    Value::Str(hash.to_string())
+}
+
+fn strs(v: &[String]) -> Value {
+    Value::Arr(v.iter().map(|s| Value::str(s)).collect())
+}
+
+impl Report {
+    // 结果是否表示成功(一致、验证通过)，决定进程退出码
+    pub fn success(&self) -> bool {
+        match self {
+            Report::Root { root, expected, .. } => expected.as_ref().is_none_or(|e| e == root),
+            Report::Compare { different, .. } => different.as_ref().is_some_and(|d| d.is_empty()),
+            Report::Proof(p) => p.valid != Some(false),
+            Report::BadProof { .. } => false,
+            Report::Check(report) => report.is_ok(),
+            _ => true,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Report::Root {
+                file,
+                blocksize,
+                leaves,
+                height,
+                levels,
+                root,
+                expected,
+            } => {
+                let mut fields = vec![
+                    (
+                        "command",
+                        Value::str(if levels.is_some() { "build" } else { "root" }),
+                    ),
+                    ("file", Value::str(file)),
+                    ("blocksize", Value::num(*blocksize)),
+                    ("leaves", Value::num(*leaves)),
+                    ("height", Value::num(*height)),
+                    ("root", hex(root)),
+                ];
+                if let Some(levels) = levels {
+                    fields.push(("levels", Value::nums(levels)));
+                }
+                if let Some(expected) = expected {
+                    fields.push(("expected", hex(expected)));
+                    fields.push(("match", Value::Bool(expected == root)));
+                }
+                Value::obj(fields)
+            }
+            Report::Compare {
+                blocksize,
+                root1,
+                root2,
+                leaves1,
+                leaves2,
+                different,
+            } => Value::obj(vec![
+                ("command", Value::str("compare")),
+                ("blocksize", Value::num(*blocksize)),
+                ("root1", hex(root1)),
+                ("root2", hex(root2)),
+                ("leaves1", Value::num(*leaves1)),
+                ("leaves2", Value::num(*leaves2)),
+                ("comparable", Value::Bool(different.is_some())),
+                (
+                    "different",
+                    different.as_deref().map(Value::nums).unwrap_or(Value::Null),
+                ),
+            ]),
+            Report::Proof(p) => {
+                let chain = p
+                    .chain
+                    .iter()
+                    .map(|c| {
+                        Value::obj(vec![
+                            ("hash", hex(&c.hash)),
+                            (
+                                "position",
+                                Value::str(if c.left { "left" } else { "right" }),
+                            ),
+                        ])
+                    })
+                    .collect();
+                let mut fields = vec![
+                    (
+                        "command",
+                        Value::str(if p.valid.is_some() { "verify" } else { "prove" }),
+                    ),
+                    ("index", Value::num(p.index)),
+                    ("blocksize", Value::num(p.blocksize)),
+                    ("leaves", Value::num(p.leaves)),
+                    ("leaf", hex(&p.leaf)),
+                    ("chain", Value::Arr(chain)),
+                    ("root", hex(&p.root)),
+                ];
+                if let Some(target) = &p.target_root {
+                    fields.push(("target_root", hex(target)));
+                }
+                if let Some(valid) = p.valid {
+                    fields.push(("valid", Value::Bool(valid)));
+                    fields.push((
+                        "reason",
+                        p.reason.as_deref().map(Value::str).unwrap_or(Value::Null),
+                    ));
+                }
+                Value::obj(fields)
+            }
+            Report::BadProof {
+                target_root,
+                reason,
+            } => Value::obj(vec![
+                ("command", Value::str("verify")),
+                ("target_root", hex(target_root)),
+                ("valid", Value::Bool(false)),
+                ("reason", Value::str(reason)),
+            ]),
+            Report::Diff {
+                patch,
+                ops,
+                literal_bytes,
+                patch_bytes,
+                new_root,
+            } => Value::obj(vec![
+                ("command", Value::str("diff")),
+                ("patch", Value::str(patch)),
+                ("ops", Value::num(*ops)),
+                ("literal_bytes", Value::num(*literal_bytes)),
+                ("patch_bytes", Value::num(*patch_bytes)),
+                ("new_root", hex(new_root)),
+            ]),
+            Report::Bench(results) => Value::obj(vec![
+                ("command", Value::str("bench")),
+                (
+                    "results",
+                    Value::Arr(
+                        results
+                            .iter()
+                            .map(|r| {
+                                Value::obj(vec![
+                                    ("name", Value::str(&r.name)),
+                                    ("bytes", Value::num(r.bytes)),
+                                    ("ops", Value::Num(r.ops)),
+                                    ("seconds", Value::Float(r.elapsed.as_secs_f64())),
+                                    ("ops_per_sec", Value::Float(r.ops_per_sec())),
+                                    (
+                                        "mb_per_sec",
+                                        r.mb_per_sec().map(Value::Float).unwrap_or(Value::Null),
+                                    ),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                ),
+            ]),
+            Report::Manifest { manifest, files } => Value::obj(vec![
+                ("command", Value::str("manifest")),
+                ("manifest", Value::str(manifest)),
+                ("files", Value::num(*files)),
+            ]),
+            Report::Check(report) => {
+                let modified = report
+                    .modified
+                    .iter()
+                    .map(|m| {
+                        Value::obj(vec![
+                            ("path", Value::str(&m.path)),
+                            (
+                                "blocks",
+                                m.blocks.as_deref().map(Value::nums).unwrap_or(Value::Null),
+                            ),
+                        ])
+                    })
+                    .collect();
+                Value::obj(vec![
+                    ("command", Value::str("check")),
+                    ("ok", Value::Bool(report.is_ok())),
+                    ("missing", strs(&report.missing)),
+                    ("extra", strs(&report.extra)),
+                    ("modified", Value::Arr(modified)),
+                    ("invalid", strs(&report.invalid)),
+                ])
+            }
+            Report::Show { tree, .. } => Value::obj(vec![
+                ("command", Value::str("show")),
+                ("tree", to_json(tree)),
+            ]),
+        }
+    }
+
+    pub fn to_text(&self, lang: Lang) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        match self {
+            Report::Root {
+                blocksize,
+                leaves,
+                height,
+                levels,
+                root,
+                expected,
+                ..
+            } => {
+                if let Some(levels) = levels {
+                    lines.push(tr(lang, Msg::TreeInfo, &[blocksize, leaves, height]));
+                    lines.push(tr(lang, Msg::Levels, &[&format!("{:?}", levels)]));
+                }
+                lines.push(tr(lang, Msg::RootHash, &[root]));
+                if let Some(expected) = expected {
+                    let msg = if expected == root {
+                        Msg::MatchExpected
+                    } else {
+                        Msg::MismatchExpected
+                    };
+                    lines.push(tr(lang, msg, &[]));
+                }
+            }
+            Report::Compare {
+                blocksize,
+                root1,
+                root2,
+                different,
+                ..
+            } => {
+                lines.push(tr(lang, Msg::Tree1Root, &[root1]));
+                lines.push(tr(lang, Msg::Tree2Root, &[root2]));
+                match different {
+                    Some(d) => {
+                        lines.push(tr(lang, Msg::DiffBlocks, &[blocksize, &format!("{:?}", d)]))
+                    }
+                    None => lines.push(tr(lang, Msg::StructMismatch, &[])),
+                }
+            }
+            Report::Proof(p) => {
+                lines.push(tr(
+                    lang,
+                    Msg::ProofHeader,
+                    &[&p.blocksize, &p.index, &p.leaves, &p.leaf],
+                ));
+                for (i, c) in p.chain.iter().enumerate() {
+                    let pos = tr(
+                        lang,
+                        if c.left {
+                            Msg::ProofLeft
+                        } else {
+                            Msg::ProofRight
+                        },
+                        &[],
+                    );
+                    lines.push(tr(lang, Msg::ProofItem, &[&i, &pos, &c.hash]));
+                }
+                lines.push(tr(lang, Msg::ProofSteps, &[]));
+                let mut hash = p.leaf;
+                for c in &p.chain {
+                    let (left, right) = if c.left {
+                        (c.hash, hash)
+                    } else {
+                        (hash, c.hash)
+                    };
+                    lines.push(tr(lang, Msg::LeftHash, &[&left]));
+                    lines.push(tr(lang, Msg::RightHash, &[&right]));
+                    hash = Digest::combine(&left, &right);
+                    lines.push(tr(lang, Msg::Combined, &[&hash]));
+                }
+                lines.push(tr(lang, Msg::ProofRoot, &[&hash]));
+                if let (Some(target), Some(valid)) = (&p.target_root, p.valid) {
+                    lines.push(tr(lang, Msg::TargetRoot, &[target]));
+                    lines.push(tr(lang, Msg::VerifyResult, &[&valid]));
+                    if let Some(reason) = &p.reason {
+                        lines.push(tr(lang, Msg::VerifyReason, &[reason]));
+                    }
+                }
+            }
+            Report::BadProof {
+                target_root,
+                reason,
+            } => {
+                lines.push(tr(lang, Msg::TargetRoot, &[target_root]));
+                lines.push(tr(lang, Msg::VerifyResult, &[&false]));
+                lines.push(tr(lang, Msg::VerifyReason, &[reason]));
+            }
+            Report::Diff {
+                patch,
+                ops,
+                literal_bytes,
+                patch_bytes,
+                ..
+            } => lines.push(tr(
+                lang,
+                Msg::PatchWritten,
+                &[patch, ops, literal_bytes, patch_bytes],
+            )),
+            Report::Bench(results) => {
+                for r in results {
+                    let line = match r.mb_per_sec() {
+                        Some(mb) => tr(
+                            lang,
+                            Msg::BenchThroughput,
+                            &[
+                                &format!("{:<24}", r.name),
+                                &format!("{:.1}", mb),
+                                &format!("{:.0}", r.ops_per_sec()),
+                            ],
+                        ),
+                        None => tr(
+                            lang,
+                            Msg::BenchOps,
+                            &[
+                                &format!("{:<24}", r.name),
+                                &format!("{:.0}", r.ops_per_sec()),
+                            ],
+                        ),
+                    };
+                    lines.push(line);
+                }
+            }
+            Report::Manifest { manifest, files } => {
+                lines.push(tr(lang, Msg::ManifestWritten, &[files, manifest]))
+            }
+            Report::Check(report) => {
+                for path in &report.missing {
+                    lines.push(tr(lang, Msg::Missing, &[path]));
+                }
+                for path in &report.extra {
+                    lines.push(tr(lang, Msg::Extra, &[path]));
+                }
+                for m in &report.modified {
+                    match &m.blocks {
+                        Some(blocks) => lines.push(tr(
+                            lang,
+                            Msg::ModifiedBlocks,
+                            &[&m.path, &format!("{:?}", blocks)],
+                        )),
+                        None => lines.push(tr(lang, Msg::Modified, &[&m.path])),
+                    }
+                }
+                for path in &report.invalid {
+                    lines.push(tr(lang, Msg::InvalidEntry, &[path]));
+                }
+                if report.is_ok() {
+                    lines.push(tr(lang, Msg::CheckOk, &[]));
+                }
+            }
+            Report::Show { tree, dot } => {
+                let text = if *dot { to_dot(tree) } else { to_ascii(tree) };
+                lines.push(text.trim_end().to_string());
+            }
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        text
+    }
+}
@@ -1,107 +1,304 @@
+use alloc::vec::Vec;
+
 use crate::{
-    hash::{hash_to_str, HashSM3},
-    tree::MerkleTree,
+    codec::{invalid, put_bytes, put_varint, Reader},
+    digest::Digest,
+    error::{DataKind, MerkleError, Reason, Result},
+    hash::HashSM3,
+    node::NodeId,
+    tree::{node_path_positions, path_positions, MerkleTree},
 };
 
+// proof文件的格式标识和版本
+const MAGIC: &[u8; 4] = b"MPRF";
+// 版本2起记录叶子数量，节点位置由下标和叶子数量推出，不再写入文件
+const VERSION: u8 = 2;
+// 只含叶子哈希的proof文件
+const LEAF_MAGIC: &[u8; 4] = b"MLPF";
+const LEAF_VERSION: u8 = 1;
+
 pub struct Proof<T: HashSM3> {
-    pub chain: Vec<Vec<u8>>,  // 认证哈希串
+    pub chain: Vec<Digest>,   // 认证哈希串
     pub pos_chain: Vec<bool>, // true表示这个哈希值位于左侧节点
     pub data: T,              // 要验证的数据块
     pub index: usize,         // 验证的数据块下标
+    pub leaves: usize,        // 树的叶子数量
     pub blocksize: usize,     // 数据块大小
-    pub roothash: Vec<u8>,    // 利用proof链生成的根哈希
+    pub roothash: Digest,     // 利用proof链生成的根哈希
 }
 
 impl<T: HashSM3> Proof<T> {
-    pub fn new(tree: &MerkleTree, data: T, index: usize, blocksize: usize) -> Proof<T> {
+    pub fn new(tree: &MerkleTree, data: T, index: usize, blocksize: usize) -> Result<Proof<T>> {
         // 从树中得到一个proof数据链以及相应的位置链
-        let (chain, pos_chain) = tree.gen_proof(index);
+        let (chain, pos_chain) = tree.gen_proof(index)?;
         let mut result = Proof {
             chain,
             pos_chain,
             data,
             index,
+            leaves: tree.leaves,
             blocksize,
-            roothash: vec![],
+            roothash: Digest::default(),
         };
         result.cal_root_hash();
-        result
+        Ok(result)
+    }
+
+    // 检查下标、叶子数量与proof链长度和节点位置是否一致
+    // 防止把一个下标的proof改写成另一个下标的proof
+    pub fn check_path(&self) -> Result<()> {
+        check_path(
+            NodeId::leaf(self.index),
+            self.leaves,
+            &self.chain,
+            &self.pos_chain,
+        )
     }
 
     pub fn cal_root_hash(&mut self) {
-        let mut hash = self.data.sm3();
-        let mut i: usize = 0;
-        for h in &self.chain {
-            let pos = *self.pos_chain.get(i).unwrap();
-            i += 1;
-            let mut other = h.clone();
-
-            // 如果pos为true，说明链中节点为左节点，把之前的数据拼接到链中
-            // 数据之后，否则把链中数据拼接到之前的数据后
-            if pos {
-                other.append(&mut hash);
-                hash = other;
-            } else {
-                hash.append(&mut other);
-            }
-            hash = hash.sm3();
+        self.roothash = fold_path(self.data.sm3(), &self.chain, &self.pos_chain);
+    }
+
+    // 序列化认证路径(下标、数据块大小、叶子数量和proof链)，不包含数据块本身
+    // 节点位置由下标和叶子数量决定，不单独写入
+    pub fn path_to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_path(
+            &mut out,
+            self.index,
+            self.blocksize,
+            self.leaves,
+            &self.chain,
+        );
+        out
+    }
+
+    // 从序列化的认证路径和数据块恢复proof，并重新计算根哈希
+    pub fn from_path_bytes(bytes: &[u8], data: T) -> Result<Proof<T>> {
+        let mut r = Reader::new(bytes);
+        let (index, blocksize, leaves, chain, pos_chain) = read_path(&mut r)?;
+        if !r.is_empty() {
+            return Err(invalid(Reason::TrailingData(DataKind::Proof)));
         }
-        self.roothash = hash;
+        let mut result = Proof {
+            chain,
+            pos_chain,
+            data,
+            index,
+            leaves,
+            blocksize,
+            roothash: Digest::default(),
+        };
+        result.cal_root_hash();
+        Ok(result)
     }
 
-    pub fn root_hash(&self) -> Vec<u8> {
-        return self.roothash.clone();
+    // 序列化为proof文件，在认证路径前加上格式标识和版本
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        out.extend(self.path_to_bytes());
+        out
     }
 
-    // 从数据节点开始从上运算输出根哈希
-    // 展示这整个过程
-    pub fn show(&self) {
-        // 数据块的哈希
-        let mut hash = self.data.sm3();
-        println!(
-            "====PROOF====\n数据块大小: {}  数据块下标: {}\n数据块哈希值: {}",
-            self.blocksize,
+    // 读取to_bytes写出的proof文件，data为要验证的数据块
+    pub fn from_bytes(bytes: &[u8], data: T) -> Result<Proof<T>> {
+        match bytes.strip_prefix(MAGIC.as_slice()) {
+            Some([VERSION, path @ ..]) => Proof::from_path_bytes(path, data),
+            _ => Err(invalid(Reason::NotFormat(DataKind::Proof))),
+        }
+    }
+
+    pub fn root_hash(&self) -> Digest {
+        self.roothash
+    }
+
+    // 不依赖原始的树，用可信的根哈希验证proof，失败时返回原因
+    // 根哈希由当前的数据块和proof链重新计算，不使用缓存的roothash
+    pub fn verify(&self, root: &Digest) -> Result<()> {
+        self.check_path()?;
+        check_root(
+            &fold_path(self.data.sm3(), &self.chain, &self.pos_chain),
+            root,
+        )
+    }
+
+    // 去掉数据块，只保留叶子哈希
+    pub fn to_leaf_proof(&self) -> LeafProof {
+        LeafProof {
+            chain: self.chain.clone(),
+            pos_chain: self.pos_chain.clone(),
+            leaf: self.data.sm3(),
+            index: self.index,
+            leaves: self.leaves,
+            blocksize: self.blocksize,
+            roothash: self.roothash,
+        }
+    }
+}
+
+// 只携带叶子哈希的proof，数据块由验证方另外提供
+// 适用于验证方已有数据块或数据块较大的情况
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeafProof {
+    pub chain: Vec<Digest>,   // 认证哈希串
+    pub pos_chain: Vec<bool>, // true表示这个哈希值位于左侧节点
+    pub leaf: Digest,         // 数据块的哈希
+    pub index: usize,         // 验证的数据块下标
+    pub leaves: usize,        // 树的叶子数量
+    pub blocksize: usize,     // 数据块大小
+    pub roothash: Digest,     // 利用proof链生成的根哈希
+}
+
+impl LeafProof {
+    pub fn new(tree: &MerkleTree, index: usize, blocksize: usize) -> Result<LeafProof> {
+        let (chain, pos_chain) = tree.gen_proof(index)?;
+        // gen_proof已检查下标，叶子哈希直接取树的第0层
+        let leaf = tree.nodes[0][index];
+        Ok(LeafProof {
+            roothash: fold_path(leaf, &chain, &pos_chain),
+            chain,
+            pos_chain,
+            leaf,
+            index,
+            leaves: tree.leaves,
+            blocksize,
+        })
+    }
+
+    pub fn check_path(&self) -> Result<()> {
+        check_path(
+            NodeId::leaf(self.index),
+            self.leaves,
+            &self.chain,
+            &self.pos_chain,
+        )
+    }
+
+    pub fn root_hash(&self) -> Digest {
+        self.roothash
+    }
+
+    // 先计算数据块的哈希并与proof中的叶子哈希比较，再用可信的根哈希验证认证路径
+    pub fn verify(&self, data: &[u8], root: &Digest) -> Result<()> {
+        if Digest::of(data) != self.leaf {
+            return Err(MerkleError::VerifyFailed(Reason::LeafHash(self.leaf)));
+        }
+        self.check_path()?;
+        check_root(&fold_path(self.leaf, &self.chain, &self.pos_chain), root)
+    }
+
+    // 序列化为proof文件：格式标识、版本、叶子哈希和认证路径
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(LEAF_MAGIC);
+        out.push(LEAF_VERSION);
+        put_bytes(&mut out, self.leaf.as_ref());
+        put_path(
+            &mut out,
             self.index,
-            hash_to_str(&hash),
+            self.blocksize,
+            self.leaves,
+            &self.chain,
         );
+        out
+    }
 
-        for (i, proof) in self.chain.iter().enumerate() {
-            let pos = if self.pos_chain.get(i).unwrap().clone() {
-                String::from("左节点")
-            } else {
-                String::from("右节点")
-            };
-            println!("proof{} {}: {}", i, pos, hash_to_str(proof));
+    pub fn from_bytes(bytes: &[u8]) -> Result<LeafProof> {
+        let body = match bytes.strip_prefix(LEAF_MAGIC.as_slice()) {
+            Some([LEAF_VERSION, body @ ..]) => body,
+            _ => return Err(invalid(Reason::NotFormat(DataKind::Proof))),
+        };
+        let mut r = Reader::new(body);
+        let leaf = r.digest()?;
+        let (index, blocksize, leaves, chain, pos_chain) = read_path(&mut r)?;
+        if !r.is_empty() {
+            return Err(invalid(Reason::TrailingData(DataKind::Proof)));
         }
+        Ok(LeafProof {
+            roothash: fold_path(leaf, &chain, &pos_chain),
+            chain,
+            pos_chain,
+            leaf,
+            index,
+            leaves,
+            blocksize,
+        })
+    }
+}
 
-        println!("====生成根哈希过程====");
-        let mut i: usize = 0;
-        for h in &self.chain {
-            let pos = *self.pos_chain.get(i).unwrap();
-            i += 1;
-            let mut other = h.clone();
-
-            // 如果pos为true，说明链中节点为左节点，把之前的数据拼接到链中
-            // 数据之后，否则把链中数据拼接到之前的数据后
-            if pos {
-                println!(
-                    "左节点哈希值: {}\n右节点哈希值: {}",
-                    hash_to_str(&other),
-                    hash_to_str(&hash)
-                );
-                other.append(&mut hash);
-                hash = other;
-            } else {
-                println!(
-                    "左节点哈希值: {}\n右节点哈希值: {}",
-                    hash_to_str(&hash),
-                    hash_to_str(&other)
-                );
-                hash.append(&mut other);
-            }
-            hash = hash.sm3();
-            println!("组合后哈希值: {}\n", hash_to_str(&hash));
-        }
-        println!("根节点哈希值: {}\n====================", hash_to_str(&hash));
+// 从叶子哈希开始沿proof链向上计算根哈希
+pub(crate) fn fold_path(leaf: Digest, chain: &[Digest], pos_chain: &[bool]) -> Digest {
+    let mut hash = leaf;
+    for (h, pos) in chain.iter().zip(pos_chain.iter()) {
+        // 如果pos为true，说明链中节点为左节点，把之前的数据拼接到链中
+        // 数据之后，否则把链中数据拼接到之前的数据后
+        hash = if *pos {
+            Digest::combine(h, &hash)
+        } else {
+            Digest::combine(&hash, h)
+        };
+    }
+    hash
+}
+
+pub(crate) fn check_path(
+    id: NodeId,
+    leaves: usize,
+    chain: &[Digest],
+    pos_chain: &[bool],
+) -> Result<()> {
+    let expected =
+        node_path_positions(id, leaves).ok_or(MerkleError::VerifyFailed(Reason::NodeRange {
+            level: id.level,
+            index: id.index,
+            leaves,
+        }))?;
+    if expected.len() != chain.len() || expected != pos_chain {
+        return Err(MerkleError::VerifyFailed(Reason::PathShape {
+            level: id.level,
+            index: id.index,
+            leaves,
+        }));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_root(computed: &Digest, root: &Digest) -> Result<()> {
+    if computed != root {
+        return Err(MerkleError::VerifyFailed(Reason::RootMismatch {
+            computed: *computed,
+            expected: *root,
+        }));
+    }
+    Ok(())
+}
+
+fn put_path(out: &mut Vec<u8>, index: usize, blocksize: usize, leaves: usize, chain: &[Digest]) {
+    put_varint(out, index as u64);
+    put_varint(out, blocksize as u64);
+    put_varint(out, leaves as u64);
+    put_varint(out, chain.len() as u64);
+    for h in chain {
+        put_bytes(out, h.as_ref());
+    }
+}
+
+type Path = (usize, usize, usize, Vec<Digest>, Vec<bool>);
+
+// 读取put_path写出的认证路径，节点位置由下标和叶子数量推出
+fn read_path(r: &mut Reader) -> Result<Path> {
+    let index = r.usize()?;
+    let blocksize = r.usize()?;
+    let leaves = r.usize()?;
+    let pos_chain = path_positions(index, leaves).ok_or_else(|| invalid(Reason::ProofIndex))?;
+    if r.usize()? != pos_chain.len() {
+        return Err(invalid(Reason::ProofLength));
+    }
+    let mut chain = Vec::new();
+    for _ in 0..pos_chain.len() {
+        chain.push(r.digest()?);
     }
+    Ok((index, blocksize, leaves, chain, pos_chain))
 }
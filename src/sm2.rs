@@ -0,0 +1,729 @@
+//! SM2椭圆曲线数字签名(GB/T 32918.2)，用于对发布的根哈希签名
+// 素域和曲线运算都用纯Rust实现：256比特整数用4个u64(小端)表示，
+// 模p和模n的乘法都使用Montgomery乘法，模数在运行时给定，
+// 因此同一套代码既可以用于推荐曲线，也可以用于标准附录示例中的曲线。
+// 点乘使用固定运算序列的Montgomery阶梯，但点加中对无穷远点等特殊情况的分支不是常数时间的。
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use crate::error::{DataKind, Reason};
+use crate::{ct::ct_eq, digest::Digest, sm3::Sm3};
+
+type U256 = [u64; 4];
+
+const ZERO: U256 = [0; 4];
+const ONE: U256 = [1, 0, 0, 0];
+
+// 签名时默认使用的用户标识
+pub const DEFAULT_ID: &[u8] = b"1234567812345678";
+
+// ID_A的比特长度用两个字节表示
+pub const MAX_ID_LEN: usize = 8191;
+
+// 调用方给出的私钥或用户标识不能用于计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sm2Error {
+    InvalidKey, // 私钥不在[1, n-2]中
+    IdTooLong,  // 用户标识超过8191字节
+    Rng,        // 随机数来源无法提供随机数
+}
+
+impl fmt::Display for Sm2Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sm2Error::InvalidKey => write!(f, "私钥不在[1, n-2]中"),
+            Sm2Error::IdTooLong => write!(f, "用户标识超过{}字节", MAX_ID_LEN),
+            Sm2Error::Rng => write!(f, "无法获取随机数"),
+        }
+    }
+}
+
+fn adc(a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = a as u128 + b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+fn sbb(a: u64, b: u64, borrow: u64) -> (u64, u64) {
+    let t = (a as u128).wrapping_sub(b as u128 + borrow as u128);
+    (t as u64, (t >> 127) as u64)
+}
+
+// acc + a * b + carry
+fn mac(acc: u64, a: u64, b: u64, carry: u64) -> (u64, u64) {
+    let t = acc as u128 + a as u128 * b as u128 + carry as u128;
+    (t as u64, (t >> 64) as u64)
+}
+
+fn add(a: &U256, b: &U256) -> (U256, u64) {
+    let mut r = ZERO;
+    let mut carry = 0;
+    for i in 0..4 {
+        (r[i], carry) = adc(a[i], b[i], carry);
+    }
+    (r, carry)
+}
+
+fn sub(a: &U256, b: &U256) -> (U256, u64) {
+    let mut r = ZERO;
+    let mut borrow = 0;
+    for i in 0..4 {
+        (r[i], borrow) = sbb(a[i], b[i], borrow);
+    }
+    (r, borrow)
+}
+
+// flag为1时选b，为0时选a，不产生分支
+fn select(a: &U256, b: &U256, flag: u64) -> U256 {
+    let mask = flag.wrapping_neg();
+    let mut r = ZERO;
+    for i in 0..4 {
+        r[i] = a[i] ^ ((a[i] ^ b[i]) & mask);
+    }
+    r
+}
+
+fn is_zero(a: &U256) -> bool {
+    a.iter().fold(0, |acc, x| acc | x) == 0
+}
+
+fn less(a: &U256, b: &U256) -> bool {
+    sub(a, b).1 == 1
+}
+
+fn from_be(bytes: &[u8; 32]) -> U256 {
+    let mut r = ZERO;
+    for (i, chunk) in bytes.chunks_exact(8).enumerate() {
+        let mut word = [0u8; 8];
+        word.copy_from_slice(chunk);
+        r[3 - i] = u64::from_be_bytes(word);
+    }
+    r
+}
+
+fn to_be(a: &U256) -> [u8; 32] {
+    let mut r = [0u8; 32];
+    for i in 0..4 {
+        r[i * 8..i * 8 + 8].copy_from_slice(&a[3 - i].to_be_bytes());
+    }
+    r
+}
+
+// 模一个奇数m的运算，mul的参数和结果都是Montgomery形式(乘以R=2^256)
+#[derive(Clone, Copy)]
+struct Field {
+    m: U256,
+    inv: u64, // -m^-1 mod 2^64
+    r2: U256, // R^2 mod m
+}
+
+impl Field {
+    fn new(m: U256) -> Field {
+        // 牛顿迭代求m[0]在模2^64下的逆，每次迭代有效位数翻倍
+        let mut x = 1u64;
+        for _ in 0..6 {
+            x = x.wrapping_mul(2u64.wrapping_sub(m[0].wrapping_mul(x)));
+        }
+        let mut field = Field {
+            m,
+            inv: x.wrapping_neg(),
+            r2: ZERO,
+        };
+        // 1连续加倍512次得到R^2 mod m
+        let mut r2 = ONE;
+        for _ in 0..512 {
+            r2 = field.add(&r2, &r2);
+        }
+        field.r2 = r2;
+        field
+    }
+
+    // a、b都小于m
+    fn add(&self, a: &U256, b: &U256) -> U256 {
+        let (s, carry) = add(a, b);
+        let (d, borrow) = sub(&s, &self.m);
+        // 有进位或者s >= m时减去m
+        select(&s, &d, carry | (borrow ^ 1))
+    }
+
+    fn sub(&self, a: &U256, b: &U256) -> U256 {
+        let (d, borrow) = sub(a, b);
+        select(&d, &add(&d, &self.m).0, borrow)
+    }
+
+    // Montgomery乘法(CIOS)，返回a * b / R mod m，要求a * b < m * R
+    fn mul(&self, a: &U256, b: &U256) -> U256 {
+        let m = &self.m;
+        let mut t = [0u64; 6];
+        for bi in b {
+            let mut c = 0;
+            for j in 0..4 {
+                (t[j], c) = mac(t[j], a[j], *bi, c);
+            }
+            (t[4], t[5]) = adc(t[4], c, 0);
+
+            let q = t[0].wrapping_mul(self.inv);
+            let (_, mut c) = mac(t[0], q, m[0], 0);
+            for j in 1..4 {
+                (t[j - 1], c) = mac(t[j], q, m[j], c);
+            }
+            let (v, carry) = adc(t[4], c, 0);
+            t[3] = v;
+            t[4] = t[5] + carry;
+        }
+        let r = [t[0], t[1], t[2], t[3]];
+        let (d, borrow) = sub(&r, m);
+        select(&r, &d, (t[4] != 0) as u64 | (borrow ^ 1))
+    }
+
+    // 转为Montgomery形式a * R mod m
+    fn encode(&self, a: &U256) -> U256 {
+        self.mul(a, &self.r2)
+    }
+
+    // 从Montgomery形式转回普通形式
+    fn decode(&self, a: &U256) -> U256 {
+        self.mul(a, &ONE)
+    }
+
+    // 任意256比特整数模m
+    fn reduce(&self, a: &U256) -> U256 {
+        self.decode(&self.encode(a))
+    }
+
+    fn pow(&self, a: &U256, e: &U256) -> U256 {
+        let mut r = self.encode(&ONE);
+        for i in (0..256).rev() {
+            r = self.mul(&r, &r);
+            let ra = self.mul(&r, a);
+            r = select(&r, &ra, (e[i / 64] >> (i % 64)) & 1);
+        }
+        r
+    }
+
+    // m为素数，由费马小定理a^-1 = a^(m-2)
+    fn inv(&self, a: &U256) -> U256 {
+        self.pow(a, &sub(&self.m, &[2, 0, 0, 0]).0)
+    }
+}
+
+// Jacobian坐标(X/Z^2, Y/Z^3)，坐标为模p的Montgomery形式，Z为0时表示无穷远点
+#[derive(Clone, Copy)]
+struct Point {
+    x: U256,
+    y: U256,
+    z: U256,
+}
+
+const INFINITY: Point = Point {
+    x: ZERO,
+    y: ZERO,
+    z: ZERO,
+};
+
+// 椭圆曲线y^2 = x^3 + ax + b，基点G的阶为n
+pub struct Curve {
+    p: Field,
+    n: Field,
+    a: U256, // 以下为模p的Montgomery形式
+    b: U256,
+    g: Point,
+    params: [[u8; 32]; 4], // a、b、Gx、Gy的字节形式，计算Z_A时使用
+}
+
+// 曲线参数的十六进制字符串转为字节
+fn hex(s: &str) -> [u8; 32] {
+    s.parse::<Digest>().expect("曲线参数").0
+}
+
+impl Curve {
+    // GB/T 32918.5推荐的256比特素域曲线
+    pub fn sm2() -> Curve {
+        Curve::new(
+            &hex("fffffffeffffffffffffffffffffffffffffffff00000000ffffffffffffffff"),
+            &hex("fffffffeffffffffffffffffffffffffffffffff00000000fffffffffffffffc"),
+            &hex("28e9fa9e9d9f5e344d5a9e4bcf6509a7f39789f515ab8f92ddbcbd414d940e93"),
+            &hex("32c4ae2c1f1981195f9904466a39c9948fe30bbff2660be1715a4589334c74c7"),
+            &hex("bc3736a2f4f6779c59bdcee36b692153d0a9877cc62a474002df32e52139f0a0"),
+            &hex("fffffffeffffffffffffffffffffffff7203df6b21c6052b53bbf40939d54123"),
+        )
+    }
+
+    // 由素数p、系数a和b、基点G和G的阶n构造曲线，参数均为大端字节
+    pub fn new(
+        p: &[u8; 32],
+        a: &[u8; 32],
+        b: &[u8; 32],
+        gx: &[u8; 32],
+        gy: &[u8; 32],
+        n: &[u8; 32],
+    ) -> Curve {
+        let pf = Field::new(from_be(p));
+        Curve {
+            p: pf,
+            n: Field::new(from_be(n)),
+            a: pf.encode(&from_be(a)),
+            b: pf.encode(&from_be(b)),
+            g: Point {
+                x: pf.encode(&from_be(gx)),
+                y: pf.encode(&from_be(gy)),
+                z: pf.encode(&ONE),
+            },
+            params: [*a, *b, *gx, *gy],
+        }
+    }
+
+    fn double(&self, q: &Point) -> Point {
+        let f = &self.p;
+        if is_zero(&q.z) {
+            return INFINITY;
+        }
+        let xx = f.mul(&q.x, &q.x);
+        let yy = f.mul(&q.y, &q.y);
+        let yyyy = f.mul(&yy, &yy);
+        let zz = f.mul(&q.z, &q.z);
+        // S = 2((X + YY)^2 - XX - YYYY)
+        let t = f.add(&q.x, &yy);
+        let t = f.sub(&f.sub(&f.mul(&t, &t), &xx), &yyyy);
+        let s = f.add(&t, &t);
+        // M = 3XX + a * ZZ^2
+        let m = f.add(&f.add(&xx, &xx), &xx);
+        let m = f.add(&m, &f.mul(&self.a, &f.mul(&zz, &zz)));
+        // X3 = M^2 - 2S, Y3 = M(S - X3) - 8YYYY, Z3 = (Y + Z)^2 - YY - ZZ
+        let x3 = f.sub(&f.mul(&m, &m), &f.add(&s, &s));
+        let y8 = f.add(&yyyy, &yyyy);
+        let y8 = f.add(&y8, &y8);
+        let y8 = f.add(&y8, &y8);
+        let y3 = f.sub(&f.mul(&m, &f.sub(&s, &x3)), &y8);
+        let t = f.add(&q.y, &q.z);
+        let z3 = f.sub(&f.sub(&f.mul(&t, &t), &yy), &zz);
+        Point {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    fn add(&self, p1: &Point, p2: &Point) -> Point {
+        let f = &self.p;
+        if is_zero(&p1.z) {
+            return *p2;
+        }
+        if is_zero(&p2.z) {
+            return *p1;
+        }
+        let z1z1 = f.mul(&p1.z, &p1.z);
+        let z2z2 = f.mul(&p2.z, &p2.z);
+        let u1 = f.mul(&p1.x, &z2z2);
+        let u2 = f.mul(&p2.x, &z1z1);
+        let s1 = f.mul(&p1.y, &f.mul(&p2.z, &z2z2));
+        let s2 = f.mul(&p2.y, &f.mul(&p1.z, &z1z1));
+        let h = f.sub(&u2, &u1);
+        let r = f.sub(&s2, &s1);
+        if is_zero(&h) {
+            // 两点横坐标相同：同一个点时加倍，互为相反数时得到无穷远点
+            return if is_zero(&r) {
+                self.double(p1)
+            } else {
+                INFINITY
+            };
+        }
+        let r = f.add(&r, &r);
+        let i = f.add(&h, &h);
+        let i = f.mul(&i, &i);
+        let j = f.mul(&h, &i);
+        let v = f.mul(&u1, &i);
+        // X3 = r^2 - J - 2V, Y3 = r(V - X3) - 2 S1 J, Z3 = ((Z1 + Z2)^2 - Z1Z1 - Z2Z2)H
+        let x3 = f.sub(&f.sub(&f.mul(&r, &r), &j), &f.add(&v, &v));
+        let s1j = f.mul(&s1, &j);
+        let y3 = f.sub(&f.mul(&r, &f.sub(&v, &x3)), &f.add(&s1j, &s1j));
+        let z = f.add(&p1.z, &p2.z);
+        let z3 = f.mul(&f.sub(&f.sub(&f.mul(&z, &z), &z1z1), &z2z2), &h);
+        Point {
+            x: x3,
+            y: y3,
+            z: z3,
+        }
+    }
+
+    // Montgomery阶梯计算kP，每一位都做一次点加和一次倍点
+    fn mul(&self, k: &U256, q: &Point) -> Point {
+        let mut r0 = INFINITY;
+        let mut r1 = *q;
+        for i in (0..256).rev() {
+            let bit = (k[i / 64] >> (i % 64)) & 1;
+            swap(&mut r0, &mut r1, bit);
+            r1 = self.add(&r0, &r1);
+            r0 = self.double(&r0);
+            swap(&mut r0, &mut r1, bit);
+        }
+        r0
+    }
+
+    // 转为仿射坐标(普通形式)，无穷远点返回None
+    fn affine(&self, q: &Point) -> Option<(U256, U256)> {
+        if is_zero(&q.z) {
+            return None;
+        }
+        let f = &self.p;
+        let zinv = f.inv(&q.z);
+        let zinv2 = f.mul(&zinv, &zinv);
+        let x = f.mul(&q.x, &zinv2);
+        let y = f.mul(&q.y, &f.mul(&zinv2, &zinv));
+        Some((f.decode(&x), f.decode(&y)))
+    }
+
+    // 检查公钥是曲线上的点(不是无穷远点，坐标小于p)，返回Jacobian坐标
+    fn point(&self, key: &PublicKey) -> Option<Point> {
+        let (x, y) = (from_be(&key.x), from_be(&key.y));
+        if !less(&x, &self.p.m) || !less(&y, &self.p.m) {
+            return None;
+        }
+        let f = &self.p;
+        let (x, y) = (f.encode(&x), f.encode(&y));
+        let rhs = f.add(&f.mul(&f.add(&f.mul(&x, &x), &self.a), &x), &self.b);
+        if f.mul(&y, &y) != rhs {
+            return None;
+        }
+        Some(Point {
+            x,
+            y,
+            z: f.encode(&ONE),
+        })
+    }
+
+    // 取[1, max)中的随机数
+    fn random<R: Rng + ?Sized>(&self, rng: &mut R, max: &U256) -> Result<U256, Sm2Error> {
+        loop {
+            let mut bytes = [0u8; 32];
+            rng.fill_bytes(&mut bytes)?;
+            let k = from_be(&bytes);
+            if !is_zero(&k) && less(&k, max) {
+                return Ok(k);
+            }
+        }
+    }
+
+    // 私钥d取[1, n-2]中的随机数，公钥为dG
+    pub fn generate_key<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<PrivateKey, Sm2Error> {
+        let max = sub(&self.n.m, &ONE).0;
+        Ok(PrivateKey(to_be(&self.random(rng, &max)?)))
+    }
+
+    // d = n-1时签名中的(1 + d)^-1不存在，因此同样拒绝
+    pub fn public_key(&self, key: &PrivateKey) -> Result<PublicKey, Sm2Error> {
+        let d = from_be(&key.0);
+        let max = sub(&self.n.m, &ONE).0;
+        if is_zero(&d) || !less(&d, &max) {
+            return Err(Sm2Error::InvalidKey);
+        }
+        let (x, y) = self
+            .affine(&self.mul(&d, &self.g))
+            .ok_or(Sm2Error::InvalidKey)?;
+        Ok(PublicKey {
+            x: to_be(&x),
+            y: to_be(&y),
+        })
+    }
+
+    // 用户身份的杂凑值Z_A = SM3(ENTL_A || ID_A || a || b || x_G || y_G || x_A || y_A)
+    // ENTL_A为ID_A的比特长度，占两个字节，ID_A最长8191字节
+    pub fn z_a(&self, id: &[u8], key: &PublicKey) -> Result<[u8; 32], Sm2Error> {
+        if id.len() > MAX_ID_LEN {
+            return Err(Sm2Error::IdTooLong);
+        }
+        let mut hasher = Sm3::new();
+        hasher.update(&((id.len() * 8) as u16).to_be_bytes());
+        hasher.update(id);
+        for param in &self.params {
+            hasher.update(param);
+        }
+        hasher.update(&key.x);
+        hasher.update(&key.y);
+        Ok(hasher.finalize())
+    }
+
+    // e = SM3(Z_A || M)模n
+    fn message_hash(&self, id: &[u8], key: &PublicKey, msg: &[u8]) -> Result<U256, Sm2Error> {
+        let mut hasher = Sm3::new();
+        hasher.update(&self.z_a(id, key)?);
+        hasher.update(msg);
+        Ok(self.n.reduce(&from_be(&hasher.finalize())))
+    }
+
+    pub fn sign<R: Rng + ?Sized>(
+        &self,
+        key: &PrivateKey,
+        id: &[u8],
+        msg: &[u8],
+        rng: &mut R,
+    ) -> Result<Signature, Sm2Error> {
+        let n = &self.n;
+        let e = self.message_hash(id, &self.public_key(key)?, msg)?;
+        let d = n.encode(&from_be(&key.0));
+        // (1 + d)^-1
+        let inv = n.inv(&n.add(&n.encode(&ONE), &d));
+        loop {
+            let k = self.random(rng, &n.m)?;
+            let (x1, _) = self.affine(&self.mul(&k, &self.g)).unwrap();
+            // r = (e + x1) mod n，r = 0或r + k = n时重新选k
+            let r = n.add(&e, &n.reduce(&x1));
+            if is_zero(&r) || is_zero(&n.add(&r, &k)) {
+                continue;
+            }
+            // s = (1 + d)^-1 (k - rd) mod n
+            let rd = n.mul(&n.encode(&r), &d);
+            let s = n.decode(&n.mul(&inv, &n.sub(&n.encode(&k), &rd)));
+            if is_zero(&s) {
+                continue;
+            }
+            return Ok(Signature {
+                r: to_be(&r),
+                s: to_be(&s),
+            });
+        }
+    }
+
+    pub fn verify(&self, key: &PublicKey, id: &[u8], msg: &[u8], sig: &Signature) -> bool {
+        let n = &self.n;
+        let (r, s) = (from_be(&sig.r), from_be(&sig.s));
+        // r、s都在[1, n-1]中
+        if is_zero(&r) || is_zero(&s) || !less(&r, &n.m) || !less(&s, &n.m) {
+            return false;
+        }
+        let pa = match self.point(key) {
+            Some(p) => p,
+            None => return false,
+        };
+        let t = n.add(&r, &s);
+        if is_zero(&t) {
+            return false;
+        }
+        // (x1, y1) = sG + tP_A，R = (e + x1) mod n
+        let q = self.add(&self.mul(&s, &self.g), &self.mul(&t, &pa));
+        let x1 = match self.affine(&q) {
+            Some((x1, _)) => x1,
+            None => return false,
+        };
+        let e = match self.message_hash(id, key, msg) {
+            Ok(e) => e,
+            Err(_) => return false,
+        };
+        ct_eq(&to_be(&n.add(&e, &n.reduce(&x1))), &sig.r)
+    }
+}
+
+fn swap(a: &mut Point, b: &mut Point, flag: u64) {
+    let (x, y, z) = (a.x, a.y, a.z);
+    a.x = select(&a.x, &b.x, flag);
+    a.y = select(&a.y, &b.y, flag);
+    a.z = select(&a.z, &b.z, flag);
+    b.x = select(&b.x, &x, flag);
+    b.y = select(&b.y, &y, flag);
+    b.z = select(&b.z, &z, flag);
+}
+
+// 由调用方提供的随机数来源，生成密钥和签名时使用，必须是密码学安全的随机数
+// 无法提供随机数时返回Sm2Error::Rng，生成密钥或签名随之失败
+pub trait Rng {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Sm2Error>;
+}
+
+// 私钥d的大端字节，Debug不输出私钥内容
+#[derive(Clone)]
+pub struct PrivateKey(pub [u8; 32]);
+
+impl core::fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.write_str("PrivateKey(..)")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey {
+    pub x: [u8; 32],
+    pub y: [u8; 32],
+}
+
+impl PublicKey {
+    // 未压缩形式04 || x || y
+    pub fn to_bytes(&self) -> [u8; 65] {
+        let mut out = [0u8; 65];
+        out[0] = 4;
+        out[1..33].copy_from_slice(&self.x);
+        out[33..].copy_from_slice(&self.y);
+        out
+    }
+
+    // 只检查格式，点是否在曲线上在验证签名时检查
+    pub fn from_bytes(bytes: &[u8]) -> Option<PublicKey> {
+        match bytes {
+            [4, rest @ ..] if rest.len() == 64 => Some(PublicKey {
+                x: Digest::from_slice(&rest[..32])?.0,
+                y: Digest::from_slice(&rest[32..])?.0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Signature {
+    pub r: [u8; 32],
+    pub s: [u8; 32],
+}
+
+impl Signature {
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        out[..32].copy_from_slice(&self.r);
+        out[32..].copy_from_slice(&self.s);
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Signature> {
+        if bytes.len() != 64 {
+            return None;
+        }
+        Some(Signature {
+            r: Digest::from_slice(&bytes[..32])?.0,
+            s: Digest::from_slice(&bytes[32..])?.0,
+        })
+    }
+}
+
+// 签名的根哈希：用推荐曲线和默认用户标识对根哈希、叶子数量、数据块大小和时间戳一起签名
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRoot {
+    pub root: Digest,
+    pub leaves: usize,
+    pub blocksize: usize,
+    pub timestamp: u64, // 签名时间(Unix时间，秒)，由调用方提供
+    pub signature: Signature,
+}
+
+// 被签名消息的前缀，避免与其他用途的签名混淆
+#[cfg(feature = "alloc")]
+const SIGNED_ROOT_TAG: &[u8; 4] = b"MSRT";
+#[cfg(feature = "alloc")]
+const MESSAGE_LEN: usize = 4 + 32 + 8 * 3;
+
+// 推荐曲线只构造一次，之后的签名和验证都复用它
+#[cfg(feature = "std")]
+pub(crate) fn sm2_curve() -> &'static Curve {
+    static CURVE: std::sync::OnceLock<Curve> = std::sync::OnceLock::new();
+    CURVE.get_or_init(Curve::sm2)
+}
+
+// 没有std时无法安全地共享初始化的结果，每次重新构造
+#[cfg(all(feature = "alloc", not(feature = "std")))]
+fn sm2_curve() -> Curve {
+    Curve::sm2()
+}
+
+#[cfg(feature = "alloc")]
+impl From<Sm2Error> for crate::error::MerkleError {
+    fn from(e: Sm2Error) -> crate::error::MerkleError {
+        match e {
+            Sm2Error::InvalidKey => crate::error::MerkleError::InvalidData(Reason::Sm2(e)),
+            Sm2Error::IdTooLong => crate::error::MerkleError::InvalidArgument(Reason::Sm2(e)),
+            // 有std时随机数来自操作系统，按IO错误处理
+            #[cfg(feature = "std")]
+            Sm2Error::Rng => std::io::Error::other(e.to_string()).into(),
+            #[cfg(not(feature = "std"))]
+            Sm2Error::Rng => crate::error::MerkleError::InvalidArgument(Reason::Sm2(e)),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl SignedRoot {
+    pub fn new<R: Rng + ?Sized>(
+        root: Digest,
+        leaves: usize,
+        blocksize: usize,
+        timestamp: u64,
+        key: &PrivateKey,
+        rng: &mut R,
+    ) -> crate::error::Result<SignedRoot> {
+        let msg = SignedRoot::message(&root, leaves, blocksize, timestamp);
+        Ok(SignedRoot {
+            root,
+            leaves,
+            blocksize,
+            timestamp,
+            signature: sm2_curve().sign(key, DEFAULT_ID, &msg, rng)?,
+        })
+    }
+
+    pub fn for_tree<R: Rng + ?Sized>(
+        tree: &crate::tree::MerkleTree,
+        timestamp: u64,
+        key: &PrivateKey,
+        rng: &mut R,
+    ) -> crate::error::Result<SignedRoot> {
+        SignedRoot::new(
+            tree.root_hash()?,
+            tree.leaves,
+            tree.blocksize,
+            timestamp,
+            key,
+            rng,
+        )
+    }
+
+    // 被签名的消息: 前缀 || 根哈希 || 叶子数量 || 数据块大小 || 时间戳，整数为8字节大端
+    fn message(
+        root: &Digest,
+        leaves: usize,
+        blocksize: usize,
+        timestamp: u64,
+    ) -> [u8; MESSAGE_LEN] {
+        let mut msg = [0u8; MESSAGE_LEN];
+        msg[..4].copy_from_slice(SIGNED_ROOT_TAG);
+        msg[4..36].copy_from_slice(root.as_bytes());
+        msg[36..44].copy_from_slice(&(leaves as u64).to_be_bytes());
+        msg[44..52].copy_from_slice(&(blocksize as u64).to_be_bytes());
+        msg[52..].copy_from_slice(&timestamp.to_be_bytes());
+        msg
+    }
+
+    pub fn verify(&self, key: &PublicKey) -> crate::error::Result<()> {
+        let msg = SignedRoot::message(&self.root, self.leaves, self.blocksize, self.timestamp);
+        if sm2_curve().verify(key, DEFAULT_ID, &msg, &self.signature) {
+            Ok(())
+        } else {
+            Err(crate::error::MerkleError::VerifyFailed(Reason::Signature(
+                self.root,
+            )))
+        }
+    }
+
+    // 被签名的消息后接64字节的签名
+    pub fn to_bytes(&self) -> alloc::vec::Vec<u8> {
+        let mut out =
+            SignedRoot::message(&self.root, self.leaves, self.blocksize, self.timestamp).to_vec();
+        out.extend_from_slice(&self.signature.to_bytes());
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> crate::error::Result<SignedRoot> {
+        let invalid =
+            || crate::error::MerkleError::InvalidData(Reason::NotFormat(DataKind::SignedRoot));
+        if bytes.len() != MESSAGE_LEN + 64 || &bytes[..4] != SIGNED_ROOT_TAG {
+            return Err(invalid());
+        }
+        let u64_at = |i: usize| {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_be_bytes(word)
+        };
+        Ok(SignedRoot {
+            root: Digest::from_slice(&bytes[4..36]).ok_or_else(invalid)?,
+            leaves: u64_at(36) as usize,
+            blocksize: u64_at(44) as usize,
+            timestamp: u64_at(52),
+            signature: Signature::from_bytes(&bytes[MESSAGE_LEN..]).ok_or_else(invalid)?,
+        })
+    }
+}
@@ -1,70 +1,96 @@
-pub fn sm3(data: &Vec<u8>) -> [u8; 32] {
-    let mut obj = SM3::new(data);
-    let hash = obj.hash();
-    hash
-}
+use core::convert::TryInto;
 
-struct SM3 {
-    digest: [u32; 8], // 哈希值（初始值、迭代压缩中间值）
-    length: u64,      // 原始长度（比特）
-    message: Vec<u8>, // 原始或填充后的消息
+pub fn sm3(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Sm3::new();
+    hasher.update(data);
+    hasher.finalize()
 }
 
-// 初始值
-const IV: [u32; 8] = [
-    0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e,
-];
+// 可以分多次输入消息的SM3，只使用固定大小的缓冲区，不需要分配内存
+#[derive(Clone)]
+pub struct Sm3 {
+    digest: [u32; 8], // 迭代压缩的中间值
+    buffer: [u8; 64], // 还不满一个分组的数据
+    buffered: usize,  // buffer中的字节数
+    length: u64,      // 已输入的消息长度(字节)
+}
 
-// 常量
-const T0_15: u32 = 0x79cc4519;
-const T16_63: u32 = 0x7a879d8a;
+impl Sm3 {
+    pub fn new() -> Sm3 {
+        Sm3 {
+            digest: IV,
+            buffer: [0; 64],
+            buffered: 0,
+            length: 0,
+        }
+    }
 
-fn get_tt(j: u32) -> u32 {
-    if j < 16 {
-        T0_15
-    } else if j < 64 {
-        T16_63
-    } else {
-        panic!("SM3 get_tt: j out of range 0-63")
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.length += data.len() as u64;
+        // 先补满上次剩下的分组
+        if self.buffered > 0 {
+            let n = data.len().min(64 - self.buffered);
+            self.buffer[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+            if self.buffered < 64 {
+                return;
+            }
+            cf(&mut self.digest, &self.buffer);
+            self.buffered = 0;
+        }
+        // 完整的512比特分组直接从输入中压缩，不复制数据
+        let mut blocks = data.chunks_exact(64);
+        for block in &mut blocks {
+            cf(&mut self.digest, block.try_into().unwrap());
+        }
+        let rest = blocks.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
     }
-}
 
-// 布尔函数
-fn ff0_15(x: u32, y: u32, z: u32) -> u32 {
-    x ^ y ^ z
-}
+    pub fn finalize(mut self) -> [u8; 32] {
+        // 剩余不足一组的数据填充后再压缩
+        let (buffer, len) = pad(&self.buffer[..self.buffered], self.length);
+        for block in buffer[..len].chunks_exact(64) {
+            cf(&mut self.digest, block.try_into().unwrap());
+        }
 
-fn ff16_63(x: u32, y: u32, z: u32) -> u32 {
-    (x & y) | (x & z) | (y & z)
+        // 以字节形式（大端）输出散列值
+        let mut output: [u8; 32] = [0; 32];
+        for (i, num) in self.digest.iter().enumerate() {
+            u32_to_u8(&mut output, i, *num);
+        }
+        output
+    }
 }
 
-fn ff(x: u32, y: u32, z: u32, j: u32) -> u32 {
-    if j < 16 {
-        ff0_15(x, y, z)
-    } else if j < 64 {
-        ff16_63(x, y, z)
-    } else {
-        panic!("SM3 ff: j out of range j = {}", j);
+impl Default for Sm3 {
+    fn default() -> Sm3 {
+        Sm3::new()
     }
 }
 
-fn gg0_15(x: u32, y: u32, z: u32) -> u32 {
-    x ^ y ^ z
-}
+// 初始值
+const IV: [u32; 8] = [
+    0x7380166f, 0x4914b2b9, 0x172442d7, 0xda8a0600, 0xa96f30bc, 0x163138aa, 0xe38dee4d, 0xb0fb0e4e,
+];
 
-fn gg16_63(x: u32, y: u32, z: u32) -> u32 {
-    (x & y) | (!x & z)
-}
+// 常量
+const T0_15: u32 = 0x79cc4519;
+const T16_63: u32 = 0x7a879d8a;
 
-fn gg(x: u32, y: u32, z: u32, j: u32) -> u32 {
-    if j < 16 {
-        gg0_15(x, y, z)
-    } else if j < 64 {
-        gg16_63(x, y, z)
-    } else {
-        panic!("SM3 gg: j out of range j = {}", j);
+// 每一轮使用的常量T_j <<< j，编译时预先计算
+const TJ: [u32; 64] = {
+    let mut t = [0; 64];
+    let mut j = 0;
+    while j < 64 {
+        let tj = if j < 16 { T0_15 } else { T16_63 };
+        t[j] = tj.rotate_left(j as u32);
+        j += 1;
     }
-}
+    t
+};
 
 // 置换函数
 fn p0(x: u32) -> u32 {
@@ -75,136 +101,120 @@ fn p1(x: u32) -> u32 {
     x ^ x.rotate_left(15) ^ x.rotate_left(23)
 }
 
-// 把4个u8整数转化为u32整数(大端)
-fn u8_to_u32(buffer: &[u8], i: usize) -> u32 {
-    u32::from(buffer[i]) << 24
-        | u32::from(buffer[i + 1]) << 16
-        | u32::from(buffer[i + 2]) << 8
-        | u32::from(buffer[i + 3])
-}
-
 // 把u32整数转化为4个8u整数（大端）并放入指定位置
 fn u32_to_u8(buffer: &mut [u8], i: usize, num: u32) {
-    for j in (0..4).rev() {
-        buffer[i * 4 + 3 - j] = (num >> (j * 8)) as u8;
-    }
+    buffer[i * 4..i * 4 + 4].copy_from_slice(&num.to_be_bytes());
 }
 
-impl SM3 {
-    fn new(data: &Vec<u8>) -> SM3 {
-        SM3 {
-            digest: IV,
-            length: (data.len() << 3) as u64,
-            message: data.clone(),
-        }
-    }
+// 设消息m的长度为l比特。首先将比特“1”添加到消息的末尾，再添加k个“0”，k是
+// 满足l + 1 + k == 448mod512的最小的非负整数。然后再添加一个64位比特串，
+// 该比特串是长度l的二进制表示。填充后的消息m′的比特长度为512的倍数。
+// tail为消息最后不足64字节的部分，len为整个消息的字节数，
+// 返回填充后的最后一个或两个分组以及它们的总长度
+fn pad(tail: &[u8], len: u64) -> ([u8; 128], usize) {
+    debug_assert!(tail.len() < 64);
+    let mut buffer = [0u8; 128];
+    buffer[..tail.len()].copy_from_slice(tail);
+    buffer[tail.len()] = 0x80;
+    // 剩余空间放不下64位长度时需要再填充一个分组
+    let total = if tail.len() < 56 { 64 } else { 128 };
+    // 以大端将原始长度(比特)存入最后8个字节
+    buffer[total - 8..total].copy_from_slice(&(len << 3).to_be_bytes());
+    (buffer, total)
+}
 
-    // 设消息m的长度为l比特。首先将比特“1”添加到消息的末尾，再添加k个“0”，k是
-    // 满足l + 1 + k == 448mod512的最小的非负整数。然后再添加一个64位比特串，
-    // 该比特串是长度l的二进制表示。填充后的消息m′的比特长度为512的倍数。
-    fn pad(&mut self) {
-        self.message.push(0x80);
-        let blocksize = 64;
-        // 填充至l + 1 + k == 448mod512
-        let mut fill = self.message.len() % blocksize;
-        if fill > 56 {
-            fill = 120 - fill;
-        } else {
-            fill = 56 - fill;
-        }
-        for _ in 0..fill {
-            self.message.push(0x00);
-        }
+// 消息扩展时在长度为16的循环数组中就地计算W_i，覆盖不再使用的W_(i-16)
+#[inline(always)]
+fn expand_word(w: &mut [u32; 16], i: usize) -> u32 {
+    let x = p1(w[(i - 16) & 15] ^ w[(i - 9) & 15] ^ w[(i - 3) & 15].rotate_left(15))
+        ^ w[(i - 13) & 15].rotate_left(7)
+        ^ w[(i - 6) & 15];
+    w[i & 15] = x;
+    x
+}
 
-        // 添加一个64位比特串，该比特串是长度l的二进制表示
-        // 以大端将原始长度存入message
-        for i in (0..8).rev() {
-            self.message.push((self.length >> (i * 8) & 0xff) as u8)
-        }
+// 布尔函数，前16轮FF和GG都是X ^ Y ^ Z
+#[inline(always)]
+fn ff16_63(x: u32, y: u32, z: u32) -> u32 {
+    // 等价于(X & Y) | (X & Z) | (Y & Z)
+    (x & y) | ((x | y) & z)
+}
 
-        if self.message.len() % 64 != 0 {
-            panic!("SM3::pad : 填充后消息长度有误");
-        }
-    }
+#[inline(always)]
+fn gg16_63(x: u32, y: u32, z: u32) -> u32 {
+    // 等价于(X & Y) | (!X & Z)
+    ((y ^ z) & x) ^ z
+}
 
-    fn expand(&mut self, w: &mut [u32; 68], w1: &mut [u32; 64], buffer: &[u8; 64]) {
-        // 以大端形式把字节转换为字放入w中
-        for i in 0..16 {
-            w[i] = u8_to_u32(buffer, i * 4);
-        }
-        for i in 16..68 {
-            w[i] = p1(w[i - 16] ^ w[i - 9] ^ w[i - 3].rotate_left(15))
-                ^ w[i - 13].rotate_left(7)
-                ^ w[i - 6];
-        }
-        for i in 0..64 {
-            w1[i] = w[i] ^ w[i + 4];
-        }
+// 一轮迭代。为了不在每轮末尾整体移动A~H，轮换传入变量的顺序：
+// 本轮的D、B、H、F分别成为下一轮的A、C、E、G
+macro_rules! round {
+    ($a:ident, $b:ident, $c:ident, $d:ident, $e:ident, $f:ident, $g:ident, $h:ident,
+     $ff:expr, $gg:expr, $t:expr, $w:expr, $w1:expr) => {
+        let a12 = $a.rotate_left(12);
+        let ss1 = a12.wrapping_add($e).wrapping_add($t).rotate_left(7);
+        let ss2 = ss1 ^ a12;
+        $d = $ff($a, $b, $c)
+            .wrapping_add($d)
+            .wrapping_add(ss2)
+            .wrapping_add($w1);
+        $h = p0($gg($e, $f, $g)
+            .wrapping_add($h)
+            .wrapping_add(ss1)
+            .wrapping_add($w));
+        $b = $b.rotate_left(9);
+        $f = $f.rotate_left(19);
+    };
+}
+
+// 压缩函数，第j轮需要W_j和W'_j = W_j ^ W_(j+4)，因此边压缩边扩展消息，
+// 前16轮和后48轮使用不同的布尔函数。64轮全部展开，循环数组的下标都是常量，
+// W可以留在寄存器中而不必按运行时的下标读写内存
+fn cf(v: &mut [u32; 8], block: &[u8; 64]) {
+    let mut w = [0u32; 16];
+    for (word, bytes) in w.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_be_bytes(bytes.try_into().unwrap());
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *v;
+    let xor3 = |x: u32, y: u32, z: u32| x ^ y ^ z;
+
+    // 连续4轮，轮换4次后变量恰好回到原来的顺序
+    // $w为W_j ~ W_(j+3)，$x为W_(j+4) ~ W_(j+7)
+    #[rustfmt::skip]
+    macro_rules! four_rounds {
+        ($ff:expr, $gg:expr, $j:expr, $w:expr, $x:expr) => {
+            round!(a, b, c, d, e, f, g, h, $ff, $gg, TJ[$j], $w[0], $w[0] ^ $x[0]);
+            round!(d, a, b, c, h, e, f, g, $ff, $gg, TJ[$j + 1], $w[1], $w[1] ^ $x[1]);
+            round!(c, d, a, b, g, h, e, f, $ff, $gg, TJ[$j + 2], $w[2], $w[2] ^ $x[2]);
+            round!(b, c, d, a, f, g, h, e, $ff, $gg, TJ[$j + 3], $w[3], $w[3] ^ $x[3]);
+        };
     }
 
-    fn cf(&mut self, buffer: &[u8; 64]) {
-        // 消息拓展
-        let mut w: [u32; 68] = [0; 68];
-        let mut w1: [u32; 64] = [0; 64];
-        self.expand(&mut w, &mut w1, buffer);
-        // ABCDEFGH <- V
-        // 将V复制到r中，使用ABCDEFGH作为索引
-        let mut r = self.digest;
-        let (a, b, c, d, e, f, g, h) = (0, 1, 2, 3, 4, 5, 6, 7);
-        let mut ss1: u32;
-        let mut ss2: u32;
-        let mut tt1: u32;
-        let mut tt2: u32;
-
-        for i in 0..64 {
-            ss1 = (r[a]
-                .rotate_left(12)
-                .wrapping_add(r[e])
-                .wrapping_add(get_tt(i).rotate_left(i as u32)))
-            .rotate_left(7);
-            ss2 = ss1 ^ (r[a].rotate_left(12));
-            tt1 = ff(r[a], r[b], r[c], i)
-                .wrapping_add(r[d])
-                .wrapping_add(ss2)
-                .wrapping_add(w1[i as usize]);
-            tt2 = gg(r[e], r[f], r[g], i)
-                .wrapping_add(r[h])
-                .wrapping_add(ss1)
-                .wrapping_add(w[i as usize]);
-            r[d] = r[c];
-            r[c] = r[b].rotate_left(9);
-            r[b] = r[a];
-            r[a] = tt1;
-            r[h] = r[g];
-            r[g] = r[f].rotate_left(19);
-            r[f] = r[e];
-            r[e] = p0(tt2);
-        }
-        for i in 0..8 {
-            self.digest[i] ^= r[i];
-        }
+    // 从第j轮开始的4轮，W_(j+4) ~ W_(j+7)由消息扩展得到
+    macro_rules! expanded_rounds {
+        ($ff:expr, $gg:expr; $($j:expr),*) => {$(
+            let cur = [w[$j & 15], w[($j + 1) & 15], w[($j + 2) & 15], w[($j + 3) & 15]];
+            let next = [
+                expand_word(&mut w, $j + 4),
+                expand_word(&mut w, $j + 5),
+                expand_word(&mut w, $j + 6),
+                expand_word(&mut w, $j + 7),
+            ];
+            four_rounds!($ff, $gg, $j, cur, next);
+        )*};
     }
 
-    fn hash(&mut self) -> [u8; 32] {
-        let mut output: [u8; 32] = [0; 32];
-        let mut buffer: [u8; 64] = [0; 64];
-        // 填充
-        self.pad();
-
-        // 将填充后的消息m′按512比特进行分组：m′ = B(0)B(1)...B(n−1)
-        // 每次将512比特数据从message存入buffer后使用压缩函数迭代
-        for j in 0..(self.message.len() / 64) {
-            for i in (j * 64)..(j * 64 + 64) {
-                buffer[i - j * 64] = self.message[i];
-            }
-            self.cf(&buffer);
-        }
+    // 前12轮的W_(j+4)直接来自分组
+    for j in [0, 4, 8] {
+        let cur = [w[j], w[j + 1], w[j + 2], w[j + 3]];
+        let next = [w[j + 4], w[j + 5], w[j + 6], w[j + 7]];
+        four_rounds!(xor3, xor3, j, cur, next);
+    }
+    expanded_rounds!(xor3, xor3; 12);
+    expanded_rounds!(ff16_63, gg16_63; 16, 20, 24, 28, 32, 36, 40, 44, 48, 52, 56, 60);
 
-        // 以字节形式（大端）将散列值输出到output中
-        for (i, num) in self.digest.iter().enumerate() {
-            u32_to_u8(&mut output, i, *num);
-        }
-        output
+    for (x, y) in v.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *x ^= y;
     }
 }
 
@@ -249,8 +259,8 @@ mod tests {
         let string = String::from("abc");
         let string = string.as_bytes().to_vec();
 
-        let mut s = SM3::new(&string);
-        s.pad();
+        let (message, len) = pad(&string, string.len() as u64);
+        assert_eq!(len, 64);
 
         let expect: [u32; 16] = [
             0x61626380, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
@@ -262,7 +272,7 @@ mod tests {
             u32_to_u8(&mut expect_u8, i, *num);
         }
 
-        for (i, num) in s.message.iter().enumerate() {
+        for (i, num) in message[..len].iter().enumerate() {
             assert_eq!(*num, expect_u8[i]);
         }
     }
@@ -273,8 +283,10 @@ mod tests {
             String::from("abcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcdabcd");
         let string = string.as_bytes().to_vec();
 
-        let mut s = SM3::new(&string);
-        s.pad();
+        // 64字节的消息本身是一个完整分组，只有填充部分单独成组
+        let (message, len) = pad(&[], string.len() as u64);
+        assert_eq!(len, 64);
+        let message = [string.as_slice(), &message[..len]].concat();
 
         let expect: [u32; 32] = [
             0x61626364, 0x61626364, 0x61626364, 0x61626364, 0x61626364, 0x61626364, 0x61626364,
@@ -288,7 +300,8 @@ mod tests {
             u32_to_u8(&mut expect_u8, i, *num);
         }
 
-        for (i, num) in s.message.iter().enumerate() {
+        assert_eq!(message.len(), 128);
+        for (i, num) in message.iter().enumerate() {
             assert_eq!(*num, expect_u8[i], "panic at pos :{}", i);
         }
     }
@@ -297,16 +310,20 @@ mod tests {
     fn expand_1() {
         let string = String::from("abc");
         let s = string.as_bytes().to_vec();
-        let mut s = SM3::new(&s);
-        s.pad();
-
-        let mut w: [u32; 68] = [0; 68];
-        let mut w1: [u32; 64] = [0; 64];
-        let mut buffer: [u8; 64] = [0; 64];
-        for (i, byte) in s.message.iter().enumerate() {
-            buffer[i] = *byte;
+        let (message, _) = pad(&s, s.len() as u64);
+
+        // 循环数组从分组中的16个字读起，之后由expand_word逐个扩展
+        let mut ring = [0u32; 16];
+        let mut w = [0u32; 68];
+        for (i, bytes) in message[..64].chunks_exact(4).enumerate() {
+            ring[i] = u32::from_be_bytes(bytes.try_into().unwrap());
+            w[i] = ring[i];
+        }
+        for (i, word) in w.iter_mut().enumerate().skip(16) {
+            *word = expand_word(&mut ring, i);
         }
-        s.expand(&mut w, &mut w1, &buffer);
+        // 压缩函数中W'_j = W_j ^ W_(j+4)
+        let w1: [u32; 64] = core::array::from_fn(|j| w[j] ^ w[j + 4]);
 
         let expect_w: [u32; 68] = [
             0x61626380, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000, 0x00000000,
@@ -335,5 +352,36 @@ mod tests {
 
         assert_eq!(w, expect_w);
         assert_eq!(w1, expect_w1);
+        // 循环数组中留下的是最后16个字
+        for i in 52..68 {
+            assert_eq!(ring[i & 15], expect_w[i]);
+        }
+    }
+
+    #[test]
+    fn pad_boundary() {
+        // 剩余55字节时填充后仍是一个分组，56字节时需要两个分组
+        assert_eq!(pad(&[0; 55], 55).1, 64);
+        assert_eq!(pad(&[0; 56], 56).1, 128);
+        let (message, len) = pad(&[0; 56], 56);
+        assert_eq!(message[56], 0x80);
+        assert_eq!(&message[len - 8..len], &(56u64 * 8).to_be_bytes());
+    }
+
+    #[test]
+    fn streaming() {
+        let data: [u8; 200] = core::array::from_fn(|i| i as u8);
+        // 任意切分输入得到的结果都与一次性计算相同
+        for split in [0, 1, 63, 64, 65, 130, 200] {
+            let mut hasher = Sm3::new();
+            hasher.update(&data[..split]);
+            hasher.update(&data[split..]);
+            assert_eq!(hasher.finalize(), sm3(&data));
+        }
+        let mut hasher = Sm3::new();
+        for byte in b"abc" {
+            hasher.update(&[*byte]);
+        }
+        assert_eq!(hasher.finalize(), sm3(b"abc"));
     }
 }
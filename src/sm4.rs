@@ -0,0 +1,328 @@
+//! SM4分组密码(GB/T 32907)及CTR、GCM工作模式，用于加密存储的数据块
+// 加密后的数据块直接作为Merkle树的叶子，存储节点不需要密钥也能生成和验证proof；
+// 每个数据块用GCM加密，nonce由文件的nonce前缀和数据块下标组成，密文后附16字节的认证标签
+use core::convert::TryInto;
+
+use crate::ct::ct_eq;
+
+pub const BLOCK_LEN: usize = 16;
+pub const TAG_LEN: usize = 16;
+pub const NONCE_LEN: usize = 12;
+
+#[rustfmt::skip]
+const SBOX: [u8; 256] = [
+    0xd6, 0x90, 0xe9, 0xfe, 0xcc, 0xe1, 0x3d, 0xb7, 0x16, 0xb6, 0x14, 0xc2, 0x28, 0xfb, 0x2c, 0x05,
+    0x2b, 0x67, 0x9a, 0x76, 0x2a, 0xbe, 0x04, 0xc3, 0xaa, 0x44, 0x13, 0x26, 0x49, 0x86, 0x06, 0x99,
+    0x9c, 0x42, 0x50, 0xf4, 0x91, 0xef, 0x98, 0x7a, 0x33, 0x54, 0x0b, 0x43, 0xed, 0xcf, 0xac, 0x62,
+    0xe4, 0xb3, 0x1c, 0xa9, 0xc9, 0x08, 0xe8, 0x95, 0x80, 0xdf, 0x94, 0xfa, 0x75, 0x8f, 0x3f, 0xa6,
+    0x47, 0x07, 0xa7, 0xfc, 0xf3, 0x73, 0x17, 0xba, 0x83, 0x59, 0x3c, 0x19, 0xe6, 0x85, 0x4f, 0xa8,
+    0x68, 0x6b, 0x81, 0xb2, 0x71, 0x64, 0xda, 0x8b, 0xf8, 0xeb, 0x0f, 0x4b, 0x70, 0x56, 0x9d, 0x35,
+    0x1e, 0x24, 0x0e, 0x5e, 0x63, 0x58, 0xd1, 0xa2, 0x25, 0x22, 0x7c, 0x3b, 0x01, 0x21, 0x78, 0x87,
+    0xd4, 0x00, 0x46, 0x57, 0x9f, 0xd3, 0x27, 0x52, 0x4c, 0x36, 0x02, 0xe7, 0xa0, 0xc4, 0xc8, 0x9e,
+    0xea, 0xbf, 0x8a, 0xd2, 0x40, 0xc7, 0x38, 0xb5, 0xa3, 0xf7, 0xf2, 0xce, 0xf9, 0x61, 0x15, 0xa1,
+    0xe0, 0xae, 0x5d, 0xa4, 0x9b, 0x34, 0x1a, 0x55, 0xad, 0x93, 0x32, 0x30, 0xf5, 0x8c, 0xb1, 0xe3,
+    0x1d, 0xf6, 0xe2, 0x2e, 0x82, 0x66, 0xca, 0x60, 0xc0, 0x29, 0x23, 0xab, 0x0d, 0x53, 0x4e, 0x6f,
+    0xd5, 0xdb, 0x37, 0x45, 0xde, 0xfd, 0x8e, 0x2f, 0x03, 0xff, 0x6a, 0x72, 0x6d, 0x6c, 0x5b, 0x51,
+    0x8d, 0x1b, 0xaf, 0x92, 0xbb, 0xdd, 0xbc, 0x7f, 0x11, 0xd9, 0x5c, 0x41, 0x1f, 0x10, 0x5a, 0xd8,
+    0x0a, 0xc1, 0x31, 0x88, 0xa5, 0xcd, 0x7b, 0xbd, 0x2d, 0x74, 0xd0, 0x12, 0xb8, 0xe5, 0xb4, 0xb0,
+    0x89, 0x69, 0x97, 0x4a, 0x0c, 0x96, 0x77, 0x7e, 0x65, 0xb9, 0xf1, 0x09, 0xc5, 0x6e, 0xc6, 0x84,
+    0x18, 0xf0, 0x7d, 0xec, 0x3a, 0xdc, 0x4d, 0x20, 0x79, 0xee, 0x5f, 0x3e, 0xd7, 0xcb, 0x39, 0x48,
+];
+
+// 系统参数FK
+const FK: [u32; 4] = [0xa3b1bac6, 0x56aa3350, 0x677d9197, 0xb27022dc];
+
+// 固定参数CK，第i个字的第j个字节为(4i + j) * 7 mod 256
+const fn gen_ck() -> [u32; 32] {
+    let mut ck = [0u32; 32];
+    let mut i = 0;
+    while i < 32 {
+        let mut j = 0;
+        while j < 4 {
+            ck[i] = (ck[i] << 8) | (((4 * i + j) * 7) % 256) as u32;
+            j += 1;
+        }
+        i += 1;
+    }
+    ck
+}
+
+const CK: [u32; 32] = gen_ck();
+
+// 非线性变换τ，每个字节查S盒
+fn tau(a: u32) -> u32 {
+    let b = a.to_be_bytes();
+    u32::from_be_bytes([
+        SBOX[b[0] as usize],
+        SBOX[b[1] as usize],
+        SBOX[b[2] as usize],
+        SBOX[b[3] as usize],
+    ])
+}
+
+// 加密使用的合成置换T
+fn t(a: u32) -> u32 {
+    let b = tau(a);
+    b ^ b.rotate_left(2) ^ b.rotate_left(10) ^ b.rotate_left(18) ^ b.rotate_left(24)
+}
+
+// 密钥扩展使用的合成置换T'
+fn t_key(a: u32) -> u32 {
+    let b = tau(a);
+    b ^ b.rotate_left(13) ^ b.rotate_left(23)
+}
+
+fn words(block: &[u8; BLOCK_LEN]) -> [u32; 4] {
+    let mut x = [0u32; 4];
+    for (w, chunk) in x.iter_mut().zip(block.chunks_exact(4)) {
+        *w = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+    x
+}
+
+#[derive(Clone)]
+pub struct Sm4 {
+    rk: [u32; 32], // 轮密钥
+}
+
+impl Sm4 {
+    pub fn new(key: &[u8; 16]) -> Sm4 {
+        let mut k = words(key);
+        for (ki, fk) in k.iter_mut().zip(FK.iter()) {
+            *ki ^= fk;
+        }
+        let mut rk = [0u32; 32];
+        for i in 0..32 {
+            rk[i] = k[0] ^ t_key(k[1] ^ k[2] ^ k[3] ^ CK[i]);
+            k = [k[1], k[2], k[3], rk[i]];
+        }
+        Sm4 { rk }
+    }
+
+    // 32轮迭代后反序输出，加密和解密只有轮密钥的顺序不同
+    fn crypt<'a>(block: &mut [u8; BLOCK_LEN], rk: impl Iterator<Item = &'a u32>) {
+        let mut x = words(block);
+        for k in rk {
+            x = [x[1], x[2], x[3], x[0] ^ t(x[1] ^ x[2] ^ x[3] ^ k)];
+        }
+        for (chunk, w) in block.chunks_exact_mut(4).zip(x.iter().rev()) {
+            chunk.copy_from_slice(&w.to_be_bytes());
+        }
+    }
+
+    pub fn encrypt_block(&self, block: &mut [u8; BLOCK_LEN]) {
+        Sm4::crypt(block, self.rk.iter());
+    }
+
+    pub fn decrypt_block(&self, block: &mut [u8; BLOCK_LEN]) {
+        Sm4::crypt(block, self.rk.iter().rev());
+    }
+
+    // CTR模式，counter为初始计数器分组，按128比特大端整数递增；加密和解密相同
+    pub fn ctr(&self, counter: &[u8; BLOCK_LEN], data: &mut [u8]) {
+        let mut counter = u128::from_be_bytes(*counter);
+        for chunk in data.chunks_mut(BLOCK_LEN) {
+            let mut stream = counter.to_be_bytes();
+            self.encrypt_block(&mut stream);
+            for (b, s) in chunk.iter_mut().zip(stream.iter()) {
+                *b ^= s;
+            }
+            counter = counter.wrapping_add(1);
+        }
+    }
+}
+
+// GF(2^128)上的乘法，按GCM的比特顺序(最高位为x^0)
+fn gf_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xe1 << 120;
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        // 不依赖比特值分支
+        z ^= v & ((x >> (127 - i)) & 1).wrapping_neg();
+        v = (v >> 1) ^ (R & (v & 1).wrapping_neg());
+    }
+    z
+}
+
+// GCM模式(NIST SP 800-38D)，只支持96比特的nonce
+#[derive(Clone)]
+pub struct Gcm {
+    cipher: Sm4,
+    h: u128, // 哈希子密钥E(0)
+}
+
+impl Gcm {
+    pub fn new(key: &[u8; 16]) -> Gcm {
+        let cipher = Sm4::new(key);
+        let mut h = [0u8; BLOCK_LEN];
+        cipher.encrypt_block(&mut h);
+        Gcm {
+            cipher,
+            h: u128::from_be_bytes(h),
+        }
+    }
+
+    fn ghash(&self, aad: &[u8], ciphertext: &[u8]) -> u128 {
+        let mut y = 0u128;
+        for data in [aad, ciphertext] {
+            for chunk in data.chunks(BLOCK_LEN) {
+                let mut block = [0u8; BLOCK_LEN];
+                block[..chunk.len()].copy_from_slice(chunk);
+                y = gf_mul(y ^ u128::from_be_bytes(block), self.h);
+            }
+        }
+        let lengths = ((aad.len() as u128 * 8) << 64) | (ciphertext.len() as u128 * 8);
+        gf_mul(y ^ lengths, self.h)
+    }
+
+    // 计数器只递增低32比特
+    fn ctr32(&self, j0: &[u8; BLOCK_LEN], data: &mut [u8]) {
+        let mut counter = *j0;
+        for chunk in data.chunks_mut(BLOCK_LEN) {
+            let n = u32::from_be_bytes(counter[12..].try_into().unwrap()).wrapping_add(1);
+            counter[12..].copy_from_slice(&n.to_be_bytes());
+            let mut stream = counter;
+            self.cipher.encrypt_block(&mut stream);
+            for (b, s) in chunk.iter_mut().zip(stream.iter()) {
+                *b ^= s;
+            }
+        }
+    }
+
+    fn tag(&self, j0: &[u8; BLOCK_LEN], aad: &[u8], ciphertext: &[u8]) -> [u8; TAG_LEN] {
+        let mut mask = *j0;
+        self.cipher.encrypt_block(&mut mask);
+        (self.ghash(aad, ciphertext) ^ u128::from_be_bytes(mask)).to_be_bytes()
+    }
+
+    fn j0(nonce: &[u8; NONCE_LEN]) -> [u8; BLOCK_LEN] {
+        let mut j0 = [0u8; BLOCK_LEN];
+        j0[..NONCE_LEN].copy_from_slice(nonce);
+        j0[15] = 1;
+        j0
+    }
+
+    // 原地加密data，返回认证标签；同一个密钥下nonce不能重复使用
+    pub fn encrypt(&self, nonce: &[u8; NONCE_LEN], aad: &[u8], data: &mut [u8]) -> [u8; TAG_LEN] {
+        let j0 = Gcm::j0(nonce);
+        self.ctr32(&j0, data);
+        self.tag(&j0, aad, data)
+    }
+
+    // 先检查认证标签，通过后原地解密；标签不符时返回false，data保持不变
+    pub fn decrypt(
+        &self,
+        nonce: &[u8; NONCE_LEN],
+        aad: &[u8],
+        data: &mut [u8],
+        tag: &[u8; TAG_LEN],
+    ) -> bool {
+        let j0 = Gcm::j0(nonce);
+        if !ct_eq(&self.tag(&j0, aad, data), tag) {
+            return false;
+        }
+        self.ctr32(&j0, data);
+        true
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub use self::blocks::*;
+
+// 加密数据块并在密文上构建Merkle树
+#[cfg(feature = "alloc")]
+mod blocks {
+    use alloc::vec::Vec;
+    use core::convert::TryInto;
+
+    use super::{Gcm, NONCE_LEN, TAG_LEN};
+    use crate::{
+        error::{MerkleError, Reason, Result},
+        tree::MerkleTree,
+    };
+
+    pub struct EncryptedBlocks {
+        pub blocks: Vec<Vec<u8>>, // 每个数据块的密文，末尾附认证标签
+        pub tree: MerkleTree,     // 在密文上构建的树
+    }
+
+    // 第index个数据块的nonce：4字节的前缀 || 8字节大端的数据块下标
+    // 同一个密钥加密不同的数据时必须使用不同的前缀
+    pub fn block_nonce(prefix: &[u8; 4], index: usize) -> [u8; NONCE_LEN] {
+        let mut nonce = [0u8; NONCE_LEN];
+        nonce[..4].copy_from_slice(prefix);
+        nonce[4..].copy_from_slice(&(index as u64).to_be_bytes());
+        nonce
+    }
+
+    // 用GCM分别加密每个数据块(例如data_to_blocks的结果)，下标同时作为附加认证数据
+    pub fn encrypt_blocks<T: AsRef<[u8]>>(
+        key: &[u8; 16],
+        prefix: &[u8; 4],
+        blocks: &[T],
+        blocksize: usize,
+    ) -> EncryptedBlocks {
+        let gcm = Gcm::new(key);
+        let blocks: Vec<Vec<u8>> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, block)| {
+                let mut data = block.as_ref().to_vec();
+                let tag = gcm.encrypt(
+                    &block_nonce(prefix, i),
+                    &(i as u64).to_be_bytes(),
+                    &mut data,
+                );
+                data.extend_from_slice(&tag);
+                data
+            })
+            .collect();
+        EncryptedBlocks {
+            tree: MerkleTree::new(&blocks, blocksize),
+            blocks,
+        }
+    }
+
+    // 解密第index个数据块的密文，认证失败时返回错误
+    pub fn decrypt_block(
+        key: &[u8; 16],
+        prefix: &[u8; 4],
+        index: usize,
+        block: &[u8],
+    ) -> Result<Vec<u8>> {
+        let failed = || MerkleError::VerifyFailed(Reason::Tag(index));
+        if block.len() < TAG_LEN {
+            return Err(failed());
+        }
+        let (data, tag) = block.split_at(block.len() - TAG_LEN);
+        let mut data = data.to_vec();
+        let tag = tag.try_into().unwrap();
+        if Gcm::new(key).decrypt(
+            &block_nonce(prefix, index),
+            &(index as u64).to_be_bytes(),
+            &mut data,
+            tag,
+        ) {
+            Ok(data)
+        } else {
+            Err(failed())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sbox_is_permutation() {
+        let mut seen = [false; 256];
+        for b in SBOX.iter() {
+            assert!(!seen[*b as usize]);
+            seen[*b as usize] = true;
+        }
+        assert_eq!(CK[0], 0x00070e15);
+        assert_eq!(CK[31], 0x646b7279);
+    }
+}
@@ -0,0 +1,283 @@
+//! 按数据块去重的内容寻址存储
+// 每个不同的数据块只保存一份，文件名就是它的SM3哈希(即Merkle树的叶子)；
+// 每个文件在清单中记录大小、根哈希和全部叶子哈希，读取时逐块验证并重新计算根哈希。
+// 目录结构：
+//   blocks/<哈希前2位>/<哈希其余部分>
+//   manifest.bin    二进制格式的清单，路径为文件在存储中的名称
+// 先写数据块再写清单，中断时最多留下未被引用的数据块，由gc清理
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    config::data_to_blocks,
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    manifest::{Manifest, ManifestEntry},
+    tree::MerkleTree,
+};
+
+const BLOCKS_DIR: &str = "blocks";
+const MANIFEST_FILE: &str = "manifest.bin";
+
+// 保存一个文件的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PutResult {
+    pub root: Digest,
+    pub blocks: usize,     // 文件的数据块数量
+    pub new_blocks: usize, // 其中存储中原来没有或已损坏而重新写入的数据块数量
+    pub new_bytes: u64,    // 新写入的数据块的总大小
+}
+
+// 去重统计，只计算被文件引用的数据块
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StoreStats {
+    pub files: usize,
+    pub logical_bytes: u64,   // 所有文件大小之和
+    pub block_refs: usize,    // 所有文件的数据块数量之和
+    pub unique_blocks: usize, // 不同的数据块数量
+    pub stored_bytes: u64,    // 不同的数据块的总大小
+}
+
+impl StoreStats {
+    // 去重比例，即文件总大小与实际存储的数据块总大小之比
+    pub fn dedup_ratio(&self) -> f64 {
+        if self.stored_bytes == 0 {
+            1.0
+        } else {
+            self.logical_bytes as f64 / self.stored_bytes as f64
+        }
+    }
+}
+
+// 垃圾回收的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GcStats {
+    pub removed_blocks: usize,
+    pub freed_bytes: u64,
+}
+
+pub struct Store {
+    dir: PathBuf,
+    blocksize: usize, // 新保存的文件使用的数据块大小
+    manifest: Manifest,
+}
+
+impl Store {
+    // 打开目录中的存储，不存在时创建
+    pub fn open(dir: &Path, blocksize: usize) -> Result<Store> {
+        if blocksize == 0 {
+            return Err(MerkleError::InvalidArgument(Reason::ZeroBlocksize));
+        }
+        fs::create_dir_all(dir.join(BLOCKS_DIR))?;
+        let manifest = match fs::read(dir.join(MANIFEST_FILE)) {
+            Ok(data) => Manifest::from_bytes(&data)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(e) = manifest.entries.iter().find(|e| e.leaf_hashes.is_none()) {
+            return Err(MerkleError::InvalidData(Reason::MissingLeaves(
+                e.path.clone(),
+            )));
+        }
+        Ok(Store {
+            dir: dir.to_path_buf(),
+            blocksize,
+            manifest,
+        })
+    }
+
+    // 按名称排序的所有文件
+    pub fn files(&self) -> &[ManifestEntry] {
+        &self.manifest.entries
+    }
+
+    pub fn get_entry(&self, name: &str) -> Option<&ManifestEntry> {
+        self.position(name).ok().map(|i| &self.manifest.entries[i])
+    }
+
+    // 保存文件，名称已存在时替换原来的内容，原来的数据块在gc时清理
+    pub fn put_file(&mut self, name: &str, data: &[u8]) -> Result<PutResult> {
+        if name.is_empty() {
+            return Err(MerkleError::InvalidArgument(Reason::EmptyName));
+        }
+        let blocks = data_to_blocks(data, self.blocksize);
+        let tree = MerkleTree::new(&blocks, self.blocksize);
+        let mut result = PutResult {
+            root: tree.root_hash()?,
+            blocks: blocks.len(),
+            new_blocks: 0,
+            new_bytes: 0,
+        };
+        for (block, hash) in blocks.iter().zip(&tree.nodes[0]) {
+            let path = self.block_path(hash);
+            // 已有的数据块与哈希一致时直接复用，损坏或不完整时重新写入
+            match fs::read(&path) {
+                Ok(existing) if Digest::of(&existing) == *hash => continue,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+            fs::create_dir_all(path.parent().unwrap())?;
+            write_file(&path, &path.with_extension("tmp"), block)?;
+            result.new_blocks += 1;
+            result.new_bytes += block.len() as u64;
+        }
+
+        let entry = ManifestEntry {
+            path: name.to_string(),
+            size: data.len() as u64,
+            blocksize: tree.blocksize,
+            leaves: tree.leaves,
+            root: result.root,
+            leaf_hashes: tree.nodes.first().cloned(),
+        };
+        // 先保存修改后的副本，写盘失败时内存中的清单保持不变
+        let mut manifest = self.manifest.clone();
+        match self.position(name) {
+            Ok(i) => manifest.entries[i] = entry,
+            Err(i) => manifest.entries.insert(i, entry),
+        }
+        self.save(&manifest)?;
+        self.manifest = manifest;
+        Ok(result)
+    }
+
+    // 读取文件，每个数据块都与叶子哈希比较，叶子哈希再与根哈希比较
+    pub fn get_file(&self, name: &str) -> Result<Vec<u8>> {
+        let entry = self
+            .get_entry(name)
+            .ok_or_else(|| MerkleError::InvalidArgument(Reason::NoSuchFile(name.to_string())))?;
+        let leaves = entry.leaf_hashes.clone().unwrap_or_default();
+        let tree = MerkleTree::from_hashes(leaves, entry.blocksize);
+        if tree.leaves != entry.leaves || tree.root_hash().ok() != Some(entry.root) {
+            return Err(MerkleError::VerifyFailed(Reason::StoreLeaves(
+                name.to_string(),
+            )));
+        }
+        let bad_block = |index| {
+            MerkleError::VerifyFailed(Reason::StoreBlock {
+                name: name.to_string(),
+                index,
+            })
+        };
+        // 按实际读到的数据块增长，不按清单中的大小预先分配
+        let mut data = Vec::new();
+        for (i, hash) in tree.nodes[0].iter().enumerate() {
+            let block = match fs::read(self.block_path(hash)) {
+                Ok(block) => block,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(bad_block(i)),
+                Err(e) => return Err(e.into()),
+            };
+            if Digest::of(&block) != *hash {
+                return Err(bad_block(i));
+            }
+            data.extend_from_slice(&block);
+        }
+        if data.len() as u64 != entry.size {
+            return Err(MerkleError::VerifyFailed(Reason::FileSize {
+                name: name.to_string(),
+                size: data.len() as u64,
+                expected: entry.size,
+            }));
+        }
+        Ok(data)
+    }
+
+    // 从清单中删除文件，返回文件是否存在，数据块在gc时清理
+    pub fn remove_file(&mut self, name: &str) -> Result<bool> {
+        match self.position(name) {
+            Ok(i) => {
+                let mut manifest = self.manifest.clone();
+                manifest.entries.remove(i);
+                self.save(&manifest)?;
+                self.manifest = manifest;
+                Ok(true)
+            }
+            Err(_) => Ok(false),
+        }
+    }
+
+    // 删除没有被任何文件引用的数据块和中断时留下的临时文件
+    pub fn gc(&mut self) -> Result<GcStats> {
+        let referenced = self.referenced();
+        let mut stats = GcStats::default();
+        for dir in fs::read_dir(self.dir.join(BLOCKS_DIR))? {
+            let dir = dir?;
+            if !dir.file_type()?.is_dir() {
+                continue;
+            }
+            let prefix = dir.file_name().to_string_lossy().into_owned();
+            for file in fs::read_dir(dir.path())? {
+                let file = file?;
+                let name = format!("{}{}", prefix, file.file_name().to_string_lossy());
+                let keep = name
+                    .parse::<Digest>()
+                    .is_ok_and(|hash| referenced.contains(&hash));
+                if !keep {
+                    stats.freed_bytes += file.metadata()?.len();
+                    stats.removed_blocks += 1;
+                    fs::remove_file(file.path())?;
+                }
+            }
+            // 目录已空时一并删除，失败说明目录中还有数据块
+            let _ = fs::remove_dir(dir.path());
+        }
+        Ok(stats)
+    }
+
+    pub fn stats(&self) -> Result<StoreStats> {
+        let referenced = self.referenced();
+        let mut stats = StoreStats {
+            files: self.manifest.entries.len(),
+            unique_blocks: referenced.len(),
+            ..StoreStats::default()
+        };
+        for e in &self.manifest.entries {
+            stats.logical_bytes += e.size;
+            stats.block_refs += e.leaves;
+        }
+        for hash in &referenced {
+            stats.stored_bytes += fs::metadata(self.block_path(hash))?.len();
+        }
+        Ok(stats)
+    }
+
+    fn position(&self, name: &str) -> core::result::Result<usize, usize> {
+        self.manifest
+            .entries
+            .binary_search_by(|e| e.path.as_str().cmp(name))
+    }
+
+    fn referenced(&self) -> BTreeSet<Digest> {
+        self.manifest
+            .entries
+            .iter()
+            .flat_map(|e| e.leaf_hashes.iter().flatten().copied())
+            .collect()
+    }
+
+    fn block_path(&self, hash: &Digest) -> PathBuf {
+        let hex = hash.to_string();
+        self.dir.join(BLOCKS_DIR).join(&hex[..2]).join(&hex[2..])
+    }
+
+    fn save(&self, manifest: &Manifest) -> Result<()> {
+        write_file(
+            &self.dir.join(MANIFEST_FILE),
+            &self.dir.join(format!("{}.tmp", MANIFEST_FILE)),
+            &manifest.to_bytes(),
+        )
+    }
+}
+
+// 先写临时文件并同步到磁盘再改名，数据块和清单要么不存在要么完整，
+// 清单改名时它引用的数据块都已经在磁盘上
+fn write_file(path: &Path, tmp: &Path, bytes: &[u8]) -> Result<()> {
+    let mut file = fs::File::create(tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+//! 子树根和子树proof，用于分片存储
+// 每个分片保存连续的2^level个数据块(最后一个分片可以更少)，分片的子树根就是全局树第level层的节点，
+// 分片可以用子树proof证明自己的子树根属于全局根，也可以只用各分片的子树根构建全局树的上层
+use alloc::vec::Vec;
+
+use crate::{
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    node::NodeId,
+    proof::{check_path, check_root, fold_path},
+    tree::MerkleTree,
+};
+
+impl MerkleTree {
+    // 第level层第index个节点的哈希值，即该节点为根的子树的根哈希
+    pub fn subtree_root(&self, level: usize, index: usize) -> Result<Digest> {
+        self.node_hash(NodeId::new(level, index))
+            .ok_or_else(|| no_node(level, index))
+    }
+
+    // 从子树根到全局根的认证路径
+    pub fn subtree_proof(&self, level: usize, index: usize) -> Result<SubtreeProof> {
+        let node = NodeId::new(level, index);
+        let hash = self.node_hash(node).ok_or_else(|| no_node(level, index))?;
+        let (chain, pos_chain) = self.node_path(node);
+        Ok(SubtreeProof {
+            roothash: fold_path(hash, &chain, &pos_chain),
+            chain,
+            pos_chain,
+            node,
+            hash,
+            leaves: self.leaves,
+        })
+    }
+}
+
+fn no_node(level: usize, index: usize) -> MerkleError {
+    MerkleError::InvalidArgument(Reason::NoSuchNode { level, index })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubtreeProof {
+    pub chain: Vec<Digest>,   // 认证哈希串
+    pub pos_chain: Vec<bool>, // true表示这个哈希值位于左侧节点
+    pub node: NodeId,         // 子树根在全局树中的位置
+    pub hash: Digest,         // 子树根的哈希值
+    pub leaves: usize,        // 全局树的叶子数量
+    pub roothash: Digest,     // 利用proof链生成的根哈希
+}
+
+impl SubtreeProof {
+    // 子树覆盖的数据块下标范围
+    pub fn leaf_range(&self) -> core::ops::Range<usize> {
+        let start = self.node.index << self.node.level;
+        start..((self.node.index + 1) << self.node.level).min(self.leaves)
+    }
+
+    pub fn check_path(&self) -> Result<()> {
+        check_path(self.node, self.leaves, &self.chain, &self.pos_chain)
+    }
+
+    pub fn root_hash(&self) -> Digest {
+        self.roothash
+    }
+
+    // subtree_root为分片自己计算出的子树根，root为可信的全局根
+    pub fn verify(&self, subtree_root: &Digest, root: &Digest) -> Result<()> {
+        if *subtree_root != self.hash {
+            return Err(MerkleError::VerifyFailed(Reason::RootMismatch {
+                computed: *subtree_root,
+                expected: self.hash,
+            }));
+        }
+        self.check_path()?;
+        check_root(&fold_path(self.hash, &self.chain, &self.pos_chain), root)
+    }
+}
+
+// 只由各分片的子树根构建的全局树上层，tree的第k层对应全局树的第level+k层
+pub struct ShardTree {
+    pub tree: MerkleTree,
+    pub level: usize,  // 分片子树根所在的层，每个分片包含2^level个数据块
+    pub leaves: usize, // 全局树的叶子数量
+}
+
+impl ShardTree {
+    // roots按分片顺序排列，除最后一个分片外每个分片必须正好包含2^level个数据块
+    pub fn new(roots: &[Digest], level: usize, leaves: usize) -> Result<ShardTree> {
+        let shards = if level >= usize::BITS as usize {
+            usize::from(leaves > 0)
+        } else {
+            leaves.div_ceil(1 << level)
+        };
+        if roots.is_empty() || roots.len() != shards {
+            return Err(MerkleError::InvalidArgument(Reason::ShardCount {
+                leaves,
+                level,
+                expected: shards,
+                actual: roots.len(),
+            }));
+        }
+        Ok(ShardTree {
+            tree: MerkleTree::from_hashes(roots.to_vec(), 0),
+            level,
+            leaves,
+        })
+    }
+
+    pub fn root_hash(&self) -> Result<Digest> {
+        self.tree.root_hash()
+    }
+
+    // 第shard个分片的子树proof，与在完整的全局树上调用subtree_proof结果相同
+    pub fn proof(&self, shard: usize) -> Result<SubtreeProof> {
+        let (chain, pos_chain) = self.tree.gen_proof(shard)?;
+        let hash = self.tree.nodes[0][shard];
+        Ok(SubtreeProof {
+            roothash: fold_path(hash, &chain, &pos_chain),
+            chain,
+            pos_chain,
+            node: NodeId::new(self.level, shard),
+            hash,
+            leaves: self.leaves,
+        })
+    }
+}
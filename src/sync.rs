@@ -0,0 +1,358 @@
+//! 双方通过字节流同步文件的协议
+// 客户端持有旧文件，服务端持有新文件。客户端先请求根哈希，再仿照MerkleTree::compare
+// 自上而下逐层请求与本地不同节点的子节点，最后只请求不同的数据块，并用proof逐块验证
+//
+// 每条消息为 4字节大端长度 + 1字节类型 + 消息内容
+use std::io::{self, Read, Write};
+
+use crate::{
+    chunking::Blocking,
+    codec::{invalid, put_bytes, put_varint, Reader},
+    digest::Digest,
+    error::{MerkleError, Reason, Result},
+    proof::Proof,
+    tree::MerkleTree,
+};
+
+const MAX_FRAME: usize = 1 << 30;
+// 一次请求的节点或数据块数量上限
+const BATCH: usize = 1 << 12;
+
+const GET_ROOT: u8 = 0x01;
+const GET_NODES: u8 = 0x02;
+const GET_BLOCKS: u8 = 0x03;
+const DONE: u8 = 0x04;
+const ROOT: u8 = 0x81;
+const NODES: u8 = 0x82;
+const BLOCKS: u8 = 0x83;
+const ERROR: u8 = 0xee;
+
+fn write_frame<W: Write>(stream: &mut W, kind: u8, body: &[u8]) -> Result<()> {
+    if body.len() >= MAX_FRAME {
+        return Err(invalid(Reason::FrameLength));
+    }
+    stream.write_all(&((body.len() + 1) as u32).to_be_bytes())?;
+    stream.write_all(&[kind])?;
+    stream.write_all(body)?;
+    Ok(stream.flush()?)
+}
+
+// 读取一条消息，对方关闭连接时返回None
+fn read_frame<R: Read>(stream: &mut R) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut len = [0u8; 4];
+    match stream.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_be_bytes(len) as usize;
+    if len == 0 || len > MAX_FRAME {
+        return Err(invalid(Reason::FrameLength));
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    let kind = body.remove(0);
+    Ok(Some((kind, body)))
+}
+
+fn put_indices(out: &mut Vec<u8>, indices: &[usize]) {
+    put_varint(out, indices.len() as u64);
+    for i in indices {
+        put_varint(out, *i as u64);
+    }
+}
+
+fn read_indices(r: &mut Reader) -> Result<Vec<usize>> {
+    let count = r.usize()?;
+    let mut result = Vec::new();
+    for _ in 0..count {
+        result.push(r.usize()?);
+    }
+    Ok(result)
+}
+
+// 客户端收到的消息必须是期望的类型，服务端返回的错误信息转为MerkleError::Protocol
+fn expect_frame<R: Read>(stream: &mut R, kind: u8) -> Result<Vec<u8>> {
+    match read_frame(stream)? {
+        Some((k, body)) if k == kind => Ok(body),
+        Some((ERROR, body)) => Err(MerkleError::Protocol(Reason::ServerError(
+            String::from_utf8_lossy(&body).into_owned(),
+        ))),
+        Some(_) => Err(MerkleError::Protocol(Reason::UnexpectedMessage)),
+        None => Err(MerkleError::Protocol(Reason::Closed)),
+    }
+}
+
+// 服务端：响应客户端的请求，直到客户端发送结束消息或关闭连接
+pub fn serve<S: Read + Write>(stream: &mut S, data: &[u8], tree: &MerkleTree) -> Result<()> {
+    let spans = tree.spans(data.len());
+    while let Some((kind, body)) = read_frame(stream)? {
+        let mut r = Reader::new(&body);
+        let mut out = Vec::new();
+        let reply = match kind {
+            GET_ROOT => {
+                put_varint(&mut out, tree.leaves as u64);
+                put_varint(&mut out, tree.height as u64);
+                put_varint(&mut out, tree.blocksize as u64);
+                put_bytes(&mut out, tree.root_hash()?.as_ref());
+                ROOT
+            }
+            GET_NODES => {
+                let level = r.usize()?;
+                let indices = read_indices(&mut r)?;
+                match tree.nodes.get(level) {
+                    Some(nodes)
+                        if indices.len() <= BATCH && indices.iter().all(|i| *i < nodes.len()) =>
+                    {
+                        put_varint(&mut out, indices.len() as u64);
+                        for i in indices {
+                            put_bytes(&mut out, nodes[i].as_ref());
+                        }
+                        NODES
+                    }
+                    _ => {
+                        out.extend_from_slice("节点下标越界".as_bytes());
+                        ERROR
+                    }
+                }
+            }
+            GET_BLOCKS => {
+                let indices = read_indices(&mut r)?;
+                // 数据块位置来自树，树与数据不符时也不能越界
+                let blocks: Option<Vec<&[u8]>> = indices
+                    .iter()
+                    .map(|i| {
+                        spans
+                            .get(*i)
+                            .filter(|_| *i < tree.leaves)
+                            .and_then(|c| data.get(c.offset..c.offset + c.length))
+                    })
+                    .collect();
+                match blocks {
+                    Some(blocks) if indices.len() <= BATCH => {
+                        // 只返回放得进一条消息的前若干个数据块，其余的由客户端再次请求
+                        let mut items = Vec::new();
+                        let mut count = 0;
+                        for (i, block) in indices.into_iter().zip(blocks) {
+                            let proof = Proof::new(tree, block.to_vec(), i, tree.blocksize)?;
+                            let mut item = Vec::new();
+                            put_bytes(&mut item, &proof.data);
+                            put_bytes(&mut item, &proof.path_to_bytes());
+                            if items.len() + item.len() > MAX_FRAME - 16 {
+                                break;
+                            }
+                            items.extend_from_slice(&item);
+                            count += 1;
+                        }
+                        if count == 0 {
+                            out.extend_from_slice("数据块过大".as_bytes());
+                            ERROR
+                        } else {
+                            put_varint(&mut out, count as u64);
+                            out.extend_from_slice(&items);
+                            BLOCKS
+                        }
+                    }
+                    _ => {
+                        out.extend_from_slice("数据块下标越界".as_bytes());
+                        ERROR
+                    }
+                }
+            }
+            DONE => return Ok(()),
+            _ => {
+                out.extend_from_slice("未知的请求类型".as_bytes());
+                ERROR
+            }
+        };
+        write_frame(stream, reply, &out)?;
+    }
+    Ok(())
+}
+
+// 请求第level层中parents(已验证的上一层节点)的子节点，用父节点验证后
+// 把与本地不同的子节点加入next，返回获取的节点数量
+fn fetch_children<S: Read + Write>(
+    stream: &mut S,
+    level: usize,
+    parents: &[(usize, Digest)],
+    local_level: &[Digest],
+    next: &mut Vec<(usize, Digest)>,
+) -> Result<usize> {
+    let indices: Vec<usize> = parents
+        .iter()
+        .flat_map(|(i, _)| [i * 2, i * 2 + 1])
+        .filter(|i| *i < local_level.len())
+        .collect();
+    let mut out = Vec::new();
+    put_varint(&mut out, level as u64);
+    put_indices(&mut out, &indices);
+    write_frame(stream, GET_NODES, &out)?;
+    let body = expect_frame(stream, NODES)?;
+    let mut r = Reader::new(&body);
+    if r.usize()? != indices.len() {
+        return Err(invalid(Reason::ReplyCount));
+    }
+    let fetched = indices.len();
+
+    let mut received = Vec::with_capacity(indices.len());
+    for i in indices {
+        received.push((i, r.digest()?));
+    }
+
+    // 只有一个子节点时它就是被提升的父节点
+    let mut children = received.iter().peekable();
+    for (parent, hash) in parents {
+        let left = children.next().filter(|(i, _)| *i == parent * 2);
+        let right = children.next_if(|(i, _)| *i == parent * 2 + 1);
+        let combined = match (left, right) {
+            (Some((_, l)), Some((_, r))) => Digest::combine(l, r),
+            (Some((_, l)), None) => *l,
+            _ => return Err(invalid(Reason::ReplyCount)),
+        };
+        if combined != *hash {
+            return Err(MerkleError::VerifyFailed(Reason::Children {
+                level: level + 1,
+                parent: *parent,
+            }));
+        }
+        for (i, child) in left.into_iter().chain(right) {
+            if *child != local_level[*i] {
+                next.push((*i, *child));
+            }
+        }
+    }
+    Ok(fetched)
+}
+
+// 同步结果
+pub struct SyncResult {
+    pub data: Vec<u8>,        // 与服务端一致的新文件
+    pub root: Digest,         // 服务端的根哈希
+    pub changed: Vec<usize>,  // 从服务端获取的数据块下标
+    pub nodes_fetched: usize, // 比较过程中获取的节点数量
+}
+
+// 客户端：用本地的旧文件和它的树与服务端同步，得到服务端的文件
+// 两棵树结构不同时无法逐层比较，直接获取全部数据块
+// 节点和数据块都分批请求，服务端声明的叶子数量不可信，不按它预先分配内存
+// 服务端只有根哈希是可信的：每层获取的子节点都要能合并出已验证的父节点，
+// 组装完成后再对新文件重新建树，根哈希不符时返回MerkleError::VerifyFailed
+pub fn sync<S: Read + Write>(
+    stream: &mut S,
+    local: &[u8],
+    local_tree: &MerkleTree,
+) -> Result<SyncResult> {
+    write_frame(stream, GET_ROOT, &[])?;
+    let body = expect_frame(stream, ROOT)?;
+    let mut r = Reader::new(&body);
+    let leaves = r.usize()?;
+    let height = r.usize()?;
+    let blocksize = r.usize()?;
+    let root = r.digest()?;
+    if leaves == 0 {
+        return Err(MerkleError::EmptyTree);
+    }
+
+    let same_struct = local_tree.leaves == leaves
+        && local_tree.height == height
+        && local_tree.blocksize == blocksize
+        && blocksize > 0;
+    let mut nodes_fetched = 1;
+    // 需要获取的数据块下标，结构相同时由逐层比较得到
+    let mut todo: Box<dyn Iterator<Item = usize>> = if !same_struct {
+        Box::new(0..leaves)
+    } else if local_tree.root_hash()? == root {
+        Box::new(std::iter::empty())
+    } else {
+        // 逐层向下，只请求根哈希不同节点的子节点，check中为已验证的(下标, 哈希值)
+        let mut check = vec![(0, root)];
+        let mut level = height;
+        while level > 0 && !check.is_empty() {
+            level -= 1;
+            let local_level = &local_tree.nodes[level];
+            let mut next = Vec::new();
+            for parents in check.chunks(BATCH / 2) {
+                nodes_fetched += fetch_children(stream, level, parents, local_level, &mut next)?;
+            }
+            check = next;
+        }
+        Box::new(check.into_iter().map(|(i, _)| i))
+    };
+
+    // 分批获取不同的数据块并逐块验证proof，服务端没有返回的数据块放到下一批
+    let mut changed = Vec::new();
+    let mut fetched = Vec::new();
+    let mut batch: Vec<usize> = Vec::new();
+    loop {
+        batch.extend(todo.by_ref().take(BATCH - batch.len()));
+        if batch.is_empty() {
+            break;
+        }
+        let mut out = Vec::new();
+        put_indices(&mut out, &batch);
+        write_frame(stream, GET_BLOCKS, &out)?;
+        let body = expect_frame(stream, BLOCKS)?;
+        let mut r = Reader::new(&body);
+        let count = r.usize()?;
+        if count == 0 || count > batch.len() {
+            return Err(invalid(Reason::ReplyCount));
+        }
+        for i in batch.drain(..count) {
+            let block = r.bytes()?.to_vec();
+            let proof = Proof::from_path_bytes(r.bytes()?, block)?;
+            if proof.index != i
+                || proof.leaves != leaves
+                || proof.blocksize != blocksize
+                || proof.verify(&root).is_err()
+            {
+                return Err(MerkleError::VerifyFailed(Reason::BlockProof(i)));
+            }
+            changed.push(i);
+            fetched.push(proof.data);
+        }
+    }
+    write_frame(stream, DONE, &[])?;
+
+    // 用本地相同的数据块和获取到的数据块组装新文件
+    let mut blocks: Vec<Vec<u8>> = Vec::new();
+    let mut fetched = fetched.into_iter();
+    if same_struct {
+        let mut next_changed = changed.iter().peekable();
+        for (i, span) in local_tree.spans(local.len()).iter().enumerate() {
+            if next_changed.peek() == Some(&&i) {
+                next_changed.next();
+                blocks.extend(fetched.next());
+            } else {
+                let block = local
+                    .get(span.offset..span.offset + span.length)
+                    .ok_or_else(|| invalid(Reason::BlockRange))?;
+                blocks.push(block.to_vec());
+            }
+        }
+    } else {
+        blocks.extend(fetched);
+    }
+    let data = blocks.concat();
+
+    // 固定分块时按数据重新分块，否则按获取到的数据块建树
+    let tree = if blocksize > 0 {
+        MerkleTree::from_bytes(&data, &Blocking::Fixed(blocksize))?
+    } else {
+        MerkleTree::new(&blocks, 0)
+    };
+    let computed = tree.root_hash()?;
+    if tree.leaves != leaves || computed != root {
+        return Err(MerkleError::VerifyFailed(Reason::RootMismatch {
+            computed,
+            expected: root,
+        }));
+    }
+
+    Ok(SyncResult {
+        data,
+        root,
+        changed,
+        nodes_fetched,
+    })
+}
@@ -1,53 +1,58 @@
-use std::{usize, vec};
+use alloc::{collections::BTreeSet, vec, vec::Vec};
 
 use crate::{
-    hash::{hash_to_str, HashSM3},
-    proof::Proof,
+    chunking::{Blocking, Chunk},
+    digest::Digest,
+    error::{MerkleError, Result},
+    hash::HashSM3,
+    lookup::LeafIndex,
+    node::NodeId,
+    proof::{fold_path, Proof},
 };
 
 pub struct MerkleTree {
-    pub nodes: Vec<Vec<Vec<u8>>>, // 分层存储节点
-    pub leaves: usize,            // 叶子节点数量
-    pub height: usize,            // 树的高度
-    pub blocksize: usize,         // 数据块的大小
+    pub nodes: Vec<Vec<Digest>>,       // 分层存储节点
+    pub leaves: usize,                 // 叶子节点数量
+    pub height: usize,                 // 树的高度
+    pub blocksize: usize,              // 数据块的大小
+    pub chunks: Vec<Chunk>,            // 数据块在原始数据中的位置(仅from_bytes构建的树记录)
+    pub leaf_index: Option<LeafIndex>, // 叶子哈希到下标的索引(仅调用build_index后建立)
 }
 
 // 求两个节点合并后的哈希值，如果只剩最后一个节点则返回它自己
 // 在递归生成树时使用
-fn combined_hash(v: &Vec<Vec<u8>>, index: usize) -> Vec<u8> {
-    let mut left = v.get(index).unwrap().clone();
-    let right = v.get(index + 1);
-    match right {
-        Some(v) => {
-            left.append(&mut v.clone());
-            left.sm3()
-        }
-        None => left,
+fn combined_hash(v: &[Digest], index: usize) -> Digest {
+    match v.get(index + 1) {
+        Some(right) => Digest::combine(&v[index], right),
+        None => v[index],
     }
 }
 
 impl MerkleTree {
-    pub fn new<T: HashSM3>(data: &Vec<T>, blocksize: usize) -> MerkleTree {
+    pub fn new<T: HashSM3>(data: &[T], blocksize: usize) -> MerkleTree {
+        // 先从所有数据中生成哈希值作为最底层的叶子节点
+        MerkleTree::from_hashes(data.iter().map(|v| v.sm3()).collect(), blocksize)
+    }
+
+    // 由已经计算好的叶子哈希构建树
+    pub fn from_hashes(hashes: Vec<Digest>, blocksize: usize) -> MerkleTree {
         // 如果数据为空
-        if data.is_empty() {
+        if hashes.is_empty() {
             return MerkleTree {
                 nodes: vec![],
                 leaves: 0,
                 height: 0,
                 blocksize: 0,
+                chunks: vec![],
+                leaf_index: None,
             };
         }
 
         // 数据非空
-        let leaves = data.len();
+        let leaves = hashes.len();
         let mut height = 0;
-        let mut cur = Vec::with_capacity(leaves);
+        let mut cur = hashes;
         let mut tree = vec![];
-        // 先从所有数据中生成哈希值作为最底层的叶子节点
-        for v in data {
-            let leaf = v.sm3();
-            cur.push(leaf);
-        }
 
         // 递归地从下至上两两结合哈希值
         loop {
@@ -75,17 +80,72 @@ impl MerkleTree {
             leaves,
             height,
             blocksize,
+            chunks: vec![],
+            leaf_index: None,
         }
     }
 
-    // 返回根节点的哈希值
-    pub fn root_hash(&self) -> Vec<u8> {
-        self.nodes.get(self.height).unwrap().get(0).unwrap().clone()
+    // 在末尾追加一个叶子，只重新计算最右侧的一条路径，结果与from_hashes构建的树相同
+    // 追加叶子的树不再记录数据块位置
+    pub fn push(&mut self, leaf: Digest) {
+        self.chunks.clear();
+        if let Some(index) = &mut self.leaf_index {
+            index.insert(leaf, self.leaves);
+        }
+        if self.leaves == 0 {
+            let index = self.leaf_index.take();
+            *self = MerkleTree::from_hashes(vec![leaf], self.blocksize);
+            self.leaf_index = index;
+            return;
+        }
+        self.nodes[0].push(leaf);
+        self.leaves += 1;
+        // 每一层只有最后一个节点的父节点会改变，直到某一层只剩一个节点
+        let mut level = 0;
+        while self.nodes[level].len() > 1 {
+            let parent = (self.nodes[level].len() - 1) / 2;
+            let hash = combined_hash(&self.nodes[level], parent * 2);
+            if level + 1 == self.nodes.len() {
+                self.nodes.push(Vec::new());
+            }
+            let up = &mut self.nodes[level + 1];
+            if parent < up.len() {
+                up[parent] = hash;
+            } else {
+                up.push(hash);
+            }
+            level += 1;
+        }
+        self.height = level;
     }
 
-    // 两棵树是否相同(结构，根哈希)
-    pub fn eq(&self, other: &MerkleTree) -> bool {
-        self.struct_eq(other) && self.root_hash().eq(&other.root_hash())
+    // 按指定的分块方式切分原始数据并构建Merkle树，同时记录每个数据块的位置
+    pub fn from_bytes(data: &[u8], blocking: &Blocking) -> Result<MerkleTree> {
+        let chunks = blocking.split(data)?;
+        let blocks: Vec<&[u8]> = chunks.iter().map(|c| c.slice(data)).collect();
+        let mut tree = MerkleTree::new(&blocks, blocking.blocksize());
+        tree.chunks = chunks;
+        Ok(tree)
+    }
+
+    // 树中每个叶子节点对应的数据块位置，len为原始数据长度
+    // 没有记录位置的树按blocksize固定分块计算
+    #[cfg(feature = "std")]
+    pub(crate) fn spans(&self, len: usize) -> Vec<Chunk> {
+        if !self.chunks.is_empty() || self.blocksize == 0 {
+            self.chunks.clone()
+        } else {
+            crate::chunking::fixed_chunks(len, self.blocksize)
+        }
+    }
+
+    // 返回根节点的哈希值，空树没有根节点
+    pub fn root_hash(&self) -> Result<Digest> {
+        self.nodes
+            .get(self.height)
+            .and_then(|level| level.first())
+            .copied()
+            .ok_or(MerkleError::EmptyTree)
     }
 
     // 两棵树在结构上是否相同
@@ -96,12 +156,14 @@ impl MerkleTree {
     }
 
     // 比较两颗结构相同树，得到不同的数据块位置
-    pub fn compare(&self, other: &MerkleTree) -> Vec<usize> {
+    pub fn compare(&self, other: &MerkleTree) -> Result<Vec<usize>> {
         let mut result = Vec::new();
 
         if !self.struct_eq(other) {
-            eprintln!("两棵树结构不同无法比较");
-            return result;
+            return Err(MerkleError::StructMismatch);
+        }
+        if self.leaves == 0 {
+            return Ok(result);
         }
 
         // check中放置的为要检查的下标，从上至下对比两棵树
@@ -115,8 +177,7 @@ impl MerkleTree {
             let level = self.nodes.get(index).unwrap();
             let level_other = other.nodes.get(index).unwrap();
             // check中还有要检查的下标
-            while check.len() > 0 {
-                let i = check.pop().unwrap();
+            while let Some(i) = check.pop() {
                 let o1 = level.get(i);
                 let o2 = level_other.get(i);
                 let flag = match o1 {
@@ -144,27 +205,53 @@ impl MerkleTree {
             }
         }
         result.sort();
-        result
+        Ok(result)
+    }
+
+    // 按内容比较两棵树的叶子节点，得到other中在本树里找不到相同哈希的数据块位置
+    // 不要求两棵树结构相同，适用于内容定义分块构建的树
+    pub fn compare_chunks(&self, other: &MerkleTree) -> Vec<usize> {
+        let known: BTreeSet<&Digest> = match self.nodes.first() {
+            Some(level) => level.iter().collect(),
+            None => BTreeSet::new(),
+        };
+        match other.nodes.first() {
+            Some(level) => level
+                .iter()
+                .enumerate()
+                .filter(|(_, leaf)| !known.contains(leaf))
+                .map(|(i, _)| i)
+                .collect(),
+            None => vec![],
+        }
     }
 
     // 用给定的下标从树中生成proof证明链
-    pub fn gen_proof(&self, index: usize) -> (Vec<Vec<u8>>, Vec<bool>) {
+    pub fn gen_proof(&self, index: usize) -> Result<(Vec<Digest>, Vec<bool>)> {
+        if index >= self.leaves {
+            return Err(MerkleError::IndexOutOfRange {
+                index,
+                leaves: self.leaves,
+            });
+        }
+        Ok(self.node_path(NodeId::leaf(index)))
+    }
+
+    // 从给定节点到根节点的认证路径，节点必须存在
+    pub(crate) fn node_path(&self, id: NodeId) -> (Vec<Digest>, Vec<bool>) {
         let mut result = Vec::new();
         let mut pos = Vec::new();
-        let mut i = index;
-        for level in &self.nodes {
+        let mut i = id.index;
+        for level in &self.nodes[id.level..] {
             // 如果i整除2说明下标位于左子树中，把右节点哈希值加入proof中
-            if i % 2 == 0 {
-                i = i + 1;
+            if i.is_multiple_of(2) {
+                i += 1;
             } else {
-                i = i - 1;
+                i -= 1;
             }
-            match level.get(i) {
-                Some(v) => {
-                    result.push(v.clone());
-                    pos.push(i % 2 == 0); // 记录当前proof链中数据是从左节点还是右节点得到
-                }
-                None => {}
+            if let Some(v) = level.get(i) {
+                result.push(*v);
+                pos.push(i.is_multiple_of(2)); // 记录当前proof链中数据是从左节点还是右节点得到
             }
             i >>= 1;
         }
@@ -172,8 +259,54 @@ impl MerkleTree {
         (result, pos)
     }
 
-    // 验证proof
+    // 验证proof，根哈希由proof中的数据块和proof链重新计算，Digest之间的==是常数时间比较
     pub fn validate<T: HashSM3>(&self, proof: &Proof<T>) -> bool {
-        (self.blocksize == proof.blocksize) && self.root_hash().eq(&proof.root_hash())
+        match self.root_hash() {
+            Ok(root) => {
+                self.blocksize == proof.blocksize
+                    && self.leaves == proof.leaves
+                    && proof.check_path().is_ok()
+                    && root == fold_path(proof.data.sm3(), &proof.chain, &proof.pos_chain)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+// 由数据块下标和叶子数量推出proof链中各节点的位置(true表示位于左侧)，与gen_proof一致
+// 某一层节点数为奇数时最后一个节点直接提升到上一层，这一层不产生proof节点
+// 下标越界时返回None
+pub fn path_positions(index: usize, leaves: usize) -> Option<Vec<bool>> {
+    node_path_positions(NodeId::leaf(index), leaves)
+}
+
+// 同path_positions，从任意一层的节点开始
+pub fn node_path_positions(id: NodeId, leaves: usize) -> Option<Vec<bool>> {
+    // 第level层的节点数为叶子数量除以2^level向上取整
+    let mut n = leaves;
+    for _ in 0..id.level {
+        n = n.div_ceil(2);
+    }
+    if id.index >= n {
+        return None;
+    }
+    let mut result = Vec::new();
+    let mut i = id.index;
+    while n > 1 {
+        if !i.is_multiple_of(2) {
+            result.push(true);
+        } else if i + 1 < n {
+            result.push(false);
+        }
+        i >>= 1;
+        n = n.div_ceil(2);
+    }
+    Some(result)
+}
+
+// 两棵树是否相同(结构，根哈希)
+impl PartialEq for MerkleTree {
+    fn eq(&self, other: &MerkleTree) -> bool {
+        self.struct_eq(other) && self.root_hash().ok() == other.root_hash().ok()
     }
 }
@@ -0,0 +1,37 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::time::Duration;
+
+use merkle::bench::{run, size_name, BenchConfig};
+use merkle::config::parse_size;
+
+#[test]
+fn bench_quick_run() {
+    let config = BenchConfig {
+        max_size: 32 * 1024,
+        blocksizes: vec![1024],
+        min_time: Duration::from_millis(0),
+    };
+    let mut seen = 0;
+    let results = run(&config, |_| seen += 1);
+    assert_eq!(seen, results.len());
+    assert!(results.iter().any(|r| r.name == "build/32KB/bs=1024"));
+    assert!(!results.iter().any(|r| r.name.starts_with("build/1MB")));
+    for r in &results {
+        assert!(r.ops >= 1);
+        assert_eq!(r.mb_per_sec().is_some(), r.bytes > 0);
+    }
+}
+
+#[test]
+fn sizes() {
+    assert_eq!(parse_size("1K"), Some(1024));
+    assert_eq!(parse_size("32mb"), Some(32 << 20));
+    assert_eq!(parse_size("1G"), Some(1 << 30));
+    assert_eq!(parse_size("100"), Some(100));
+    assert_eq!(parse_size("x"), None);
+    assert_eq!(size_name(1 << 30), "1GB");
+    assert_eq!(size_name(64), "64B");
+}
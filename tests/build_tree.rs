@@ -4,16 +4,18 @@ extern crate merkle;
 use std::fs;
 
 use merkle::config::data_to_blocks;
+use merkle::error::MerkleError;
 use merkle::hash::hash_to_str;
-use merkle::tree::MerkleTree;
+use merkle::proof::{LeafProof, Proof};
+use merkle::tree::{path_positions, MerkleTree};
 #[test]
 fn build_tree_1() {
     let source_data = fs::read("./files/f1.txt").unwrap();
     let block1 = data_to_blocks(&source_data, 1024);
     let tree = MerkleTree::new(&block1, 1024);
     assert_eq!(
-        hash_to_str(&tree.root_hash()),
-        "5a6315a4f58f18181e6db7ce764c6b8c9fa32298b1f818639c82d19be5ea6b29"
+        hash_to_str(&tree.root_hash().unwrap()),
+        "0d4f4b31aef88ac86dec17d3da6bc256891b822322aaf28d87e4853823695339"
     );
 }
 
@@ -22,6 +24,112 @@ fn build_tree_2() {
     let source_data = fs::read("./files/f1.txt").unwrap();
     for i in 1..2049 {
         let block = data_to_blocks(&source_data, i);
-        let tree = MerkleTree::new(&block, i);
+        let _tree = MerkleTree::new(&block, i);
     }
 }
+
+#[test]
+fn tree_errors() {
+    let empty: Vec<Vec<u8>> = vec![];
+    let tree = MerkleTree::new(&empty, 1024);
+    assert!(matches!(tree.root_hash(), Err(MerkleError::EmptyTree)));
+
+    let source_data = fs::read("./files/f1.txt").unwrap();
+    let t1 = MerkleTree::new(&data_to_blocks(&source_data, 64), 64);
+    let t2 = MerkleTree::new(&data_to_blocks(&source_data, 128), 128);
+    assert!(matches!(t1.compare(&t2), Err(MerkleError::StructMismatch)));
+    assert!(matches!(
+        t1.gen_proof(t1.leaves),
+        Err(MerkleError::IndexOutOfRange { .. })
+    ));
+    assert!(Proof::new(&t1, vec![], t1.leaves, 64).is_err());
+}
+
+#[test]
+fn proof_file() {
+    let blocks: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 16]).collect();
+    let tree = MerkleTree::new(&blocks, 16);
+    let root = tree.root_hash().unwrap();
+    let bytes = Proof::new(&tree, blocks[3].clone(), 3, 16)
+        .unwrap()
+        .to_bytes();
+
+    // 只用proof文件、数据块和根哈希验证
+    let proof = Proof::from_bytes(&bytes, blocks[3].clone()).unwrap();
+    assert_eq!(proof.index, 3);
+    assert!(proof.verify(&root).is_ok());
+    let proof = Proof::from_bytes(&bytes, blocks[2].clone()).unwrap();
+    assert!(matches!(
+        proof.verify(&root),
+        Err(MerkleError::VerifyFailed(_))
+    ));
+    assert!(Proof::from_bytes(&bytes[1..], blocks[3].clone()).is_err());
+
+    // 创建proof后修改数据块，缓存的根哈希不能让它通过验证
+    let mut proof = Proof::new(&tree, blocks[3].clone(), 3, 16).unwrap();
+    proof.data = blocks[2].clone();
+    assert_eq!(proof.root_hash(), root);
+    assert!(proof.verify(&root).is_err());
+    assert!(!tree.validate(&proof));
+}
+
+#[test]
+fn proof_index_consistency() {
+    // 由下标和叶子数量推出的位置与树生成的proof一致，包括提升的奇数节点
+    for leaves in 1..40u8 {
+        let blocks: Vec<Vec<u8>> = (0..leaves).map(|i| vec![i]).collect();
+        let tree = MerkleTree::new(&blocks, 1);
+        for i in 0..leaves as usize {
+            let (_, pos) = tree.gen_proof(i).unwrap();
+            assert_eq!(path_positions(i, leaves as usize), Some(pos));
+        }
+        assert_eq!(path_positions(leaves as usize, leaves as usize), None);
+    }
+
+    // 数据块2和3相同，只改下标时根哈希不变，但位置与下标不符
+    let blocks: Vec<Vec<u8>> = vec![vec![0], vec![1], vec![2], vec![2], vec![4]];
+    let tree = MerkleTree::new(&blocks, 1);
+    let root = tree.root_hash().unwrap();
+    let mut proof = Proof::new(&tree, blocks[2].clone(), 2, 1).unwrap();
+    assert!(proof.verify(&root).is_ok());
+    proof.index = 3;
+    assert_eq!(proof.root_hash(), root);
+    assert!(proof.verify(&root).is_err());
+    assert!(!tree.validate(&proof));
+    proof.index = 2;
+    proof.leaves = 4;
+    assert!(proof.verify(&root).is_err());
+
+    // proof文件中叶子数量被改动后链长度不符
+    proof.leaves = 5;
+    let mut bytes = proof.to_bytes();
+    bytes[7] = 9;
+    assert!(Proof::from_bytes(&bytes, blocks[2].clone()).is_err());
+}
+
+#[test]
+fn leaf_proof() {
+    let blocks: Vec<Vec<u8>> = (0..7u8).map(|i| vec![i; 64]).collect();
+    let tree = MerkleTree::new(&blocks, 64);
+    let root = tree.root_hash().unwrap();
+    let proof = LeafProof::new(&tree, 6, 64).unwrap();
+    assert_eq!(proof.leaf, tree.nodes[0][6]);
+    assert_eq!(
+        proof,
+        Proof::new(&tree, blocks[6].clone(), 6, 64)
+            .unwrap()
+            .to_leaf_proof()
+    );
+
+    // 数据块不随proof传输，验证时另外提供
+    let proof = LeafProof::from_bytes(&proof.to_bytes()).unwrap();
+    assert!(proof.verify(&blocks[6], &root).is_ok());
+    assert!(proof.verify(&blocks[5], &root).is_err());
+    assert!(proof.verify(&blocks[6], &tree.nodes[0][0]).is_err());
+    assert!(LeafProof::from_bytes(&proof.to_bytes()[1..]).is_err());
+
+    // 把叶子哈希换成其他数据块的哈希，缓存的根哈希不能让它通过验证
+    let mut forged = proof.clone();
+    forged.leaf = tree.nodes[0][5];
+    assert!(forged.verify(&blocks[5], &root).is_err());
+}
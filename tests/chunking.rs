@@ -0,0 +1,68 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+mod common;
+
+use merkle::chunking::{Blocking, FastCdc};
+use merkle::config::data_to_blocks;
+use merkle::tree::MerkleTree;
+
+use common::random_data;
+
+#[test]
+fn cdc_chunk_bounds() {
+    let data = random_data(200_000, 1);
+    let cdc = FastCdc::new(512, 2048, 8192).unwrap();
+    let chunks = cdc.chunks(&data);
+
+    let mut offset = 0;
+    for (i, c) in chunks.iter().enumerate() {
+        assert_eq!(c.offset, offset);
+        assert!(c.length <= 8192);
+        if i + 1 < chunks.len() {
+            assert!(c.length >= 512);
+        }
+        offset += c.length;
+    }
+    assert_eq!(offset, data.len());
+}
+
+#[test]
+fn fixed_blocking_same_root() {
+    let data = random_data(10_000, 2);
+    let tree = MerkleTree::from_bytes(&data, &Blocking::Fixed(1024)).unwrap();
+    let expect = MerkleTree::new(&data_to_blocks(&data, 1024), 1024);
+    assert!(tree == expect);
+    assert_eq!(tree.chunks.len(), tree.leaves);
+}
+
+#[test]
+fn cdc_insert_stays_local() {
+    let old = random_data(100_000, 3);
+    let mut new = old.clone();
+    new.insert(50_000, 0xff);
+
+    let blocking = Blocking::FastCdc(FastCdc::with_avg(1024).unwrap());
+    let t1 = MerkleTree::from_bytes(&old, &blocking).unwrap();
+    let t2 = MerkleTree::from_bytes(&new, &blocking).unwrap();
+    let diff = t1.compare_chunks(&t2);
+    assert!(!diff.is_empty() && diff.len() <= 3, "{:?}", diff);
+    for i in diff {
+        let c = t2.chunks[i];
+        assert!(c.offset <= 50_000 + 8192 && c.offset + c.length + 8192 >= 50_000);
+    }
+}
+
+#[test]
+fn invalid_chunk_sizes() {
+    assert!(FastCdc::new(0, 2048, 8192).is_err());
+    assert!(FastCdc::new(4096, 2048, 8192).is_err());
+    assert!(FastCdc::new(512, 2048, 1024).is_err());
+    assert!(FastCdc::with_avg(0).is_err());
+    assert!(FastCdc::with_avg(usize::MAX).is_err());
+    assert!(MerkleTree::from_bytes(b"data", &Blocking::Fixed(0)).is_err());
+
+    let cdc = FastCdc::with_avg(1).unwrap();
+    assert_eq!((cdc.min_size(), cdc.avg_size(), cdc.max_size()), (1, 1, 8));
+}
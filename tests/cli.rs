@@ -0,0 +1,197 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::config::{command_usage, lang_from_args, usage, Command, Config, Format};
+use merkle::digest::Digest;
+use merkle::error::{MerkleError, Result};
+use merkle::output::{error_text, tr, Lang, Msg, ProofReport, Report};
+use merkle::proof::Proof;
+use merkle::tree::MerkleTree;
+
+fn parse(args: &str) -> Result<Config> {
+    let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+    Config::new(&args)
+}
+
+#[test]
+fn cli_subcommands() {
+    let config = parse("merkle compare a b --block-size 64 --format json").unwrap();
+    assert_eq!(
+        config.command,
+        Command::Compare {
+            file1: "a".into(),
+            file2: "b".into()
+        }
+    );
+    assert_eq!(config.blocksize, 64);
+    assert_eq!(config.format, Format::Json);
+
+    let config = parse("merkle prove a --index=3").unwrap();
+    assert_eq!(
+        config.command,
+        Command::Prove {
+            file: "a".into(),
+            index: 3,
+            out: None
+        }
+    );
+    let root = "ab".repeat(32);
+    let config = parse(&format!(
+        "merkle verify --root {} --proof p --block b",
+        root
+    ))
+    .unwrap();
+    assert_eq!(
+        config.command,
+        Command::VerifyProof {
+            root: Digest([0xab; 32]),
+            proof: "p".into(),
+            block: "b".into()
+        }
+    );
+    assert_eq!(
+        parse("merkle show a b --depth 2 --dot").unwrap().command,
+        Command::Show {
+            file: "a".into(),
+            other: Some("b".into()),
+            depth: Some(2),
+            dot: true
+        }
+    );
+    assert_eq!(
+        parse("merkle manifest d m --no-leaves").unwrap().command,
+        Command::Manifest {
+            dir: "d".into(),
+            out: "m".into(),
+            leaves: false
+        }
+    );
+    assert_eq!(
+        parse("merkle root --help a").unwrap().command,
+        Command::Help(Some("root".into()))
+    );
+}
+
+#[test]
+fn cli_errors() {
+    assert!(parse("merkle root a --block-size 1k").is_err());
+    assert!(parse("merkle root a --block-size 0").is_err());
+    assert!(parse("merkle prove a").is_err());
+    assert!(parse("merkle verify --root xyz --proof p --block b").is_err());
+    assert!(parse("merkle verify --root abcd --proof p --block b").is_err());
+    assert!(parse(&format!(
+        "merkle verify --root {} --proof p",
+        "ab".repeat(32)
+    ))
+    .is_err());
+    assert!(parse("merkle compare a").is_err());
+    let hash = "ab".repeat(32);
+    assert!(parse(&format!("merkle build a --hash {}", hash)).is_ok());
+    assert!(parse(&format!("merkle compare a b --hash {}", hash)).is_err());
+    assert!(parse(&format!("merkle check d m --hash={}", hash)).is_err());
+    assert!(parse("merkle root a b").is_err());
+    assert!(parse("merkle root a --bogus").is_err());
+    assert!(parse("merkle frobnicate").is_err());
+
+    // 错误信息和帮助使用--lang指定的语言，参数解析失败时也一样
+    let e = parse("merkle frobnicate --lang en").err().unwrap();
+    assert_eq!(error_text(Lang::En, &e), "unknown command: frobnicate");
+    let e = parse("merkle compare a --lang=en").err().unwrap();
+    assert_eq!(
+        error_text(Lang::En, &e),
+        "compare: missing argument <file2>"
+    );
+    let args: Vec<String> = vec![
+        "merkle".into(),
+        "--bogus".into(),
+        "--lang".into(),
+        "en".into(),
+    ];
+    assert_eq!(lang_from_args(&args), Lang::En);
+    assert_eq!(
+        error_text(Lang::En, &MerkleError::EmptyTree),
+        "the tree is empty"
+    );
+    // 库返回的错误原因也按语言输出
+    let e = Proof::from_bytes(b"junk", vec![0u8]).err().unwrap();
+    assert_eq!(
+        error_text(Lang::En, &e),
+        "invalid data: not a valid proof file"
+    );
+    assert_eq!(
+        error_text(Lang::Zh, &e),
+        "数据格式有误: 不是有效的proof文件"
+    );
+    assert_eq!(e.to_string(), error_text(Lang::Zh, &e));
+    assert!(usage(Lang::En).starts_with("Usage: merkle <command>"));
+    assert!(command_usage("root", Lang::En)
+        .unwrap()
+        .starts_with("Usage: merkle root <file>"));
+    assert!(command_usage("frobnicate", Lang::En).is_none());
+
+    // 参数中的占位符不会被再次替换
+    assert_eq!(
+        tr(Lang::En, Msg::InvalidValue, &[&"{1}", &"x"]),
+        "invalid value for --{1}: x"
+    );
+}
+
+#[test]
+fn report_output() {
+    let report = Report::Compare {
+        blocksize: 4,
+        root1: Digest([0xab; 32]),
+        root2: Digest([0xcd; 32]),
+        leaves1: 3,
+        leaves2: 3,
+        different: Some(vec![0, 2]),
+    };
+    assert!(!report.success());
+    let json = report.to_json().to_string();
+    assert!(json.starts_with("{\"command\":\"compare\""));
+    assert!(json.contains("\"different\":[0,2]"));
+
+    assert!(report.to_text(Lang::En).contains("[0, 2]"));
+    assert_eq!(Lang::parse("en"), Some(Lang::En));
+    assert_eq!(Lang::parse("fr"), None);
+    assert_eq!(parse("merkle root a --lang en").unwrap().lang, Lang::En);
+    assert!(parse("merkle root a --lang fr").is_err());
+
+    // 验证失败时输出原因
+    let blocks: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 4]).collect();
+    let tree = MerkleTree::new(&blocks, 4);
+    let proof = Proof::new(&tree, blocks[1].clone(), 1, 4).unwrap();
+    let root = Digest([0xab; 32]);
+    let report = Report::Proof(ProofReport::new(&proof).verified(
+        root,
+        proof.verify(&root).err().map(|e| error_text(Lang::En, &e)),
+    ));
+    assert!(!report.success());
+    let reason = match &report {
+        Report::Proof(p) => p.reason.clone().unwrap(),
+        _ => unreachable!(),
+    };
+    assert!(reason.starts_with("verification failed: computed root"));
+    assert!(report.to_text(Lang::En).contains(&reason));
+    assert!(report
+        .to_json()
+        .to_string()
+        .contains("\"valid\":false,\"reason\":"));
+    let root = tree.root_hash().unwrap();
+    let report =
+        ProofReport::new(&proof).verified(root, proof.verify(&root).err().map(|e| e.to_string()));
+    assert_eq!((report.valid, report.reason), (Some(true), None));
+
+    // 无法解码的proof文件按验证失败输出
+    let report = Report::BadProof {
+        target_root: root,
+        reason: "invalid data: data is truncated".into(),
+    };
+    assert!(!report.success());
+    assert!(report
+        .to_json()
+        .to_string()
+        .ends_with("\"valid\":false,\"reason\":\"invalid data: data is truncated\"}"));
+    assert!(report.to_text(Lang::En).contains("reason: invalid data"));
+}
@@ -0,0 +1,14 @@
+// 集成测试共用的辅助函数
+
+// 生成伪随机测试数据
+pub fn random_data(len: usize, seed: u64) -> Vec<u8> {
+    let mut x = seed;
+    (0..len)
+        .map(|_| {
+            x = x
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (x >> 33) as u8
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::digest::Digest;
+use merkle::tree::MerkleTree;
+
+fn leaves(n: usize) -> Vec<Digest> {
+    (0..n as u32)
+        .map(|i| Digest::of(&i.to_be_bytes()))
+        .collect()
+}
+
+#[test]
+fn push_matches_from_hashes() {
+    let mut tree = MerkleTree::from_hashes(vec![], 0);
+    for n in 1..=40 {
+        tree.push(leaves(n)[n - 1]);
+        let whole = MerkleTree::from_hashes(leaves(n), 0);
+        assert_eq!(tree.nodes, whole.nodes, "{}", n);
+        assert_eq!(tree.height, whole.height);
+        assert_eq!(tree.leaves, n);
+    }
+}
+
+#[test]
+fn prefix_and_consistency_proofs() {
+    let tree = MerkleTree::from_hashes(leaves(21), 0);
+    for size in 1..=21 {
+        let old = MerkleTree::from_hashes(leaves(size), 0);
+        let old_root = old.root_hash().unwrap();
+        assert_eq!(tree.prefix_root(size).unwrap(), old_root);
+        for index in 0..size {
+            let proof = tree.prefix_proof(index, size).unwrap();
+            assert_eq!(proof.chain, old.gen_proof(index).unwrap().0);
+            proof.check_path().unwrap();
+            assert_eq!(proof.root_hash(), old_root);
+        }
+        for new_size in size..=21 {
+            let root = tree.prefix_root(new_size).unwrap();
+            let proof = tree.consistency_proof(size, new_size).unwrap();
+            proof.verify(&old_root, &root).unwrap();
+            assert!(proof.verify(&root, &old_root).is_err() || size == new_size);
+
+            // 篡改proof中的任意一个哈希都会导致验证失败
+            for i in 0..proof.chain.len() {
+                let mut bad = proof.clone();
+                bad.chain[i] = Digest::of(b"x");
+                assert!(bad.verify(&old_root, &root).is_err());
+            }
+            let mut short = proof.clone();
+            if short.chain.pop().is_some() {
+                assert!(short.verify(&old_root, &root).is_err());
+            }
+        }
+    }
+    assert!(tree.consistency_proof(0, 5).is_err());
+    assert!(tree.consistency_proof(6, 5).is_err());
+    assert!(tree.prefix_root(22).is_err());
+}
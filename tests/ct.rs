@@ -0,0 +1,50 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::cell::Cell;
+
+use merkle::ct::{ct_eq, ct_eq_iter};
+use merkle::digest::Digest;
+use merkle::proof::Proof;
+use merkle::tree::MerkleTree;
+
+#[test]
+fn ct_eq_reads_everything() {
+    let a = [0x55u8; 32];
+    for pos in [0, 1, 16, 31] {
+        let mut b = a;
+        b[pos] ^= 0x80;
+        // 统计两边各被读取了多少字节，第一个字节就不同时也必须读完
+        let read_a = Cell::new(0);
+        let read_b = Cell::new(0);
+        let equal = ct_eq_iter(
+            a.iter().copied().inspect(|_| read_a.set(read_a.get() + 1)),
+            b.iter().copied().inspect(|_| read_b.set(read_b.get() + 1)),
+        );
+        assert!(!equal);
+        assert_eq!((read_a.get(), read_b.get()), (32, 32));
+    }
+    assert!(ct_eq_iter(a.iter().copied(), a.iter().copied()));
+    assert!(!ct_eq_iter(a.iter().copied(), a[..31].iter().copied()));
+    assert!(ct_eq(&a, &a));
+    assert!(!ct_eq(&a, &a[1..]));
+    assert!(ct_eq(&[], &[]));
+}
+
+#[test]
+fn ct_verification_paths() {
+    let blocks: Vec<Vec<u8>> = (0..6u8).map(|i| vec![i; 32]).collect();
+    let tree = MerkleTree::new(&blocks, 32);
+    let root = tree.root_hash().unwrap();
+    let proof = Proof::new(&tree, blocks[4].clone(), 4, 32).unwrap();
+    assert!(tree.validate(&proof));
+    assert!(proof.verify(&root).is_ok());
+
+    // 只有最后一个字节不同的根哈希也要被拒绝
+    let mut bytes: [u8; 32] = root.into();
+    bytes[31] ^= 1;
+    assert!(proof.verify(&Digest(bytes)).is_err());
+    let bad = Proof::new(&tree, blocks[3].clone(), 4, 32).unwrap();
+    assert!(!tree.validate(&bad));
+}
@@ -0,0 +1,88 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+mod common;
+
+use merkle::chunking::{Blocking, FastCdc};
+use merkle::config::data_to_blocks;
+use merkle::delta::{apply_patch, make_patch, Patch, PatchOp};
+use merkle::error::MerkleError;
+use merkle::tree::MerkleTree;
+
+use common::random_data;
+
+fn round_trip(old: &[u8], new: &[u8], blocking: &Blocking) -> Patch {
+    let t1 = MerkleTree::from_bytes(old, blocking).unwrap();
+    let t2 = MerkleTree::from_bytes(new, blocking).unwrap();
+    let patch = make_patch(old, &t1, new, &t2).unwrap();
+    let decoded = Patch::from_bytes(&patch.to_bytes()).unwrap();
+    assert_eq!(decoded, patch);
+    assert_eq!(apply_patch(old, &decoded).unwrap(), new);
+    patch
+}
+
+#[test]
+fn patch_fixed_modify() {
+    let old = random_data(50_000, 1);
+    let mut new = old.clone();
+    new[20_000] ^= 0xff;
+    let patch = round_trip(&old, &new, &Blocking::Fixed(1024));
+    assert_eq!(patch.literal_len(), 1024);
+    assert_eq!(patch.ops.len(), 3);
+}
+
+#[test]
+fn patch_cdc_insert() {
+    let old = random_data(100_000, 2);
+    let mut new = old.clone();
+    new.splice(30_000..30_000, b"inserted".iter().cloned());
+    let patch = round_trip(
+        &old,
+        &new,
+        &Blocking::FastCdc(FastCdc::with_avg(2048).unwrap()),
+    );
+    assert!(patch.literal_len() < 3 * 16_384);
+}
+
+#[test]
+fn patch_rejects_tampering() {
+    let old = random_data(10_000, 3);
+    let new = random_data(12_000, 4);
+    let blocking = Blocking::Fixed(512);
+    let t1 = MerkleTree::from_bytes(&old, &blocking).unwrap();
+    let t2 = MerkleTree::from_bytes(&new, &blocking).unwrap();
+    let mut patch = make_patch(&old, &t1, &new, &t2).unwrap();
+    if let Some(PatchOp::Literal(data)) = patch.ops.first_mut() {
+        data[0] ^= 1;
+    }
+    assert!(apply_patch(&old, &patch).is_err());
+    assert!(Patch::from_bytes(b"MPAT").is_err());
+
+    // 补丁头中的长度过大或数据块长度之和溢出时返回错误而不是panic
+    let mut huge = patch.clone();
+    huge.new_len = usize::MAX >> 1;
+    assert!(matches!(
+        apply_patch(&old, &huge),
+        Err(MerkleError::InvalidData(_))
+    ));
+    let cdc = Patch {
+        blocksize: 0,
+        chunk_lengths: vec![usize::MAX, 2],
+        new_len: 1,
+        new_root: patch.new_root,
+        ops: vec![PatchOp::Literal(vec![0])],
+    };
+    assert!(matches!(
+        apply_patch(&old, &cdc),
+        Err(MerkleError::InvalidData(_))
+    ));
+
+    // 传入的数据比树记录的短
+    let t1 = MerkleTree::new(&data_to_blocks(&old, 512), 512);
+    let mut changed = old.clone();
+    changed[0] ^= 1;
+    let t2 = MerkleTree::new(&data_to_blocks(&changed, 512), 512);
+    assert!(make_patch(&old[..1000], &t1, &changed, &t2).is_err());
+    assert!(make_patch(&old, &t1, &changed[..1000], &t2).is_err());
+}
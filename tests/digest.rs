@@ -0,0 +1,48 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::collections::{BTreeSet, HashSet};
+
+use merkle::digest::Digest;
+use merkle::hash::HashSM3;
+use merkle::tree::MerkleTree;
+
+#[test]
+fn digest_conversions() {
+    let d = Digest::of(b"abc");
+    let hex = "66c7f0f462eeedd9d1f2d46bdc10e4e24167c4875cf2f7a2297da02b8f4ba8e0";
+    assert_eq!(d.to_string(), hex);
+    assert_eq!(hex.parse::<Digest>(), Ok(d));
+    assert_eq!(hex.to_uppercase().parse::<Digest>(), Ok(d));
+    assert!(hex[1..].parse::<Digest>().is_err());
+    assert!(hex.replace('6', "g").parse::<Digest>().is_err());
+
+    let bytes: [u8; 32] = d.into();
+    assert_eq!(Digest::from(bytes), d);
+    assert_eq!(Digest::from_slice(&d.to_vec()), Some(d));
+    assert_eq!(Digest::from_slice(&bytes[1..]), None);
+    assert_eq!(b"abc".to_vec().sm3(), d);
+
+    let mut other = bytes;
+    other[31] ^= 1;
+    let other = Digest(other);
+    assert_ne!(d, other);
+    assert!(d < other);
+    assert_eq!([d, other, d].iter().collect::<HashSet<_>>().len(), 2);
+    assert_eq!([d, other, d].iter().collect::<BTreeSet<_>>().len(), 2);
+}
+
+#[test]
+fn digest_tree_nodes() {
+    let blocks: Vec<Vec<u8>> = (0..3u8).map(|i| vec![i; 8]).collect();
+    let tree = MerkleTree::new(&blocks, 8);
+    let leaves: Vec<Digest> = blocks.iter().map(|b| b.sm3()).collect();
+    assert_eq!(tree.nodes[0], leaves);
+    // 奇数个节点时最后一个节点直接提升到上一层
+    assert_eq!(tree.nodes[1][1], leaves[2]);
+    assert_eq!(
+        tree.root_hash().unwrap(),
+        Digest::combine(&Digest::combine(&leaves[0], &leaves[1]), &leaves[2])
+    );
+}
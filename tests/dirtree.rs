@@ -0,0 +1,71 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::fs;
+use std::path::PathBuf;
+
+use merkle::config::data_to_blocks;
+use merkle::dirtree::{dir_hash, DirTree, Node};
+use merkle::error::MerkleError;
+
+// 在临时目录中创建测试用的目录结构
+fn make_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("merkle-dirtree-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub/empty")).unwrap();
+    fs::write(dir.join("a.txt"), b"hello world").unwrap();
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+    fs::write(dir.join("sub/b.bin"), data).unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink("a.txt", dir.join("link")).unwrap();
+    dir
+}
+
+#[test]
+fn dirtree_root_and_rename() {
+    let dir = make_dir("rename");
+    let t1 = DirTree::build(&dir, 1024).unwrap();
+    assert_eq!(
+        t1.root_hash(),
+        DirTree::build(&dir, 1024).unwrap().root_hash()
+    );
+
+    match &t1.get("sub/empty").unwrap().node {
+        Node::Dir(empty) => assert_eq!(empty.root, dir_hash(&[])),
+        _ => panic!("sub/empty应为目录"),
+    }
+    #[cfg(unix)]
+    assert!(matches!(t1.get("link").unwrap().node, Node::Symlink(_)));
+    assert_eq!(t1.files().len(), 2);
+
+    fs::rename(dir.join("a.txt"), dir.join("c.txt")).unwrap();
+    let t2 = DirTree::build(&dir, 1024).unwrap();
+    assert_ne!(t1.root_hash(), t2.root_hash());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn dirtree_block_proof() {
+    let dir = make_dir("proof");
+    let tree = DirTree::build(&dir, 1024).unwrap();
+    let blocks = data_to_blocks(&fs::read(dir.join("sub/b.bin")).unwrap(), 1024);
+
+    let proof = tree.prove("sub/b.bin", 3, blocks[3].clone()).unwrap();
+    assert_eq!(proof.path(), "sub/b.bin");
+    assert!(proof.verify(&tree.root_hash()).is_ok());
+
+    let bad = tree.prove("sub/b.bin", 3, blocks[2].clone()).unwrap();
+    assert!(matches!(
+        bad.verify(&tree.root_hash()),
+        Err(MerkleError::VerifyFailed(_))
+    ));
+    let mut bad = tree.prove("sub/b.bin", 3, blocks[3].clone()).unwrap();
+    bad.file_proof.data = blocks[2].clone();
+    assert!(matches!(
+        bad.verify(&tree.root_hash()),
+        Err(MerkleError::VerifyFailed(_))
+    ));
+    assert!(tree.prove("sub/missing", 0, vec![]).is_none());
+    fs::remove_dir_all(&dir).unwrap();
+}
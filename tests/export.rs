@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::error::MerkleError;
+use merkle::export::{to_ascii, to_dot, to_json, ViewNode};
+use merkle::tree::MerkleTree;
+
+fn tree(blocks: &[u8]) -> MerkleTree {
+    let blocks: Vec<Vec<u8>> = blocks.iter().map(|b| vec![*b; 8]).collect();
+    MerkleTree::new(&blocks, 8)
+}
+
+#[test]
+fn export_view() {
+    // 5个叶子，第4个叶子逐层提升
+    let t = tree(&[0, 1, 2, 3, 4]);
+    let root = ViewNode::new(&t, None, None).unwrap();
+    assert_eq!(root.hash, t.root_hash().unwrap());
+    assert_eq!(root.leaves, (0, 5));
+    assert_eq!(root.children[1].leaves, (4, 5));
+    assert_eq!(root.children[1].children.len(), 1);
+
+    let ascii = to_ascii(&root);
+    assert_eq!(ascii.lines().count(), t.nodes.iter().map(|l| l.len()).sum());
+    assert!(ascii.starts_with(&format!("L3#0 {} [0..5)", &root.hash.to_string()[..8])));
+
+    // 限制深度后只展开根节点下一层
+    let shallow = ViewNode::new(&t, None, Some(1)).unwrap();
+    assert_eq!(to_ascii(&shallow).lines().count(), 3);
+    assert!(to_ascii(&shallow).contains("L2#1"));
+    assert!(to_ascii(&shallow).ends_with(" ...\n"));
+    let json = to_json(&shallow).to_string();
+    assert!(json.starts_with("{\"level\":3,\"index\":0,"));
+    assert!(json.contains("\"leaves\":[4,5],\"mismatch\":false,\"children\":[]"));
+}
+
+#[test]
+fn export_mismatch() {
+    let t1 = tree(&[0, 1, 2, 3]);
+    let t2 = tree(&[0, 1, 9, 3]);
+    let root = ViewNode::new(&t1, Some(&t2), None).unwrap();
+    let dot = to_dot(&root);
+    assert!(dot.starts_with("digraph merkle {"));
+    // 根节点、右子树和第2个叶子不同
+    assert_eq!(dot.matches("color=red").count(), 3);
+    assert!(dot.contains("n0_2 [label=\"L0#2\\n"));
+    assert!(dot.contains("n2_0 -> n1_1;"));
+    assert!(!root.children[0].mismatch);
+    assert!(root.children[1].children[0].mismatch);
+
+    // 叶子数量不同的树按位置比较没有意义
+    let t3 = tree(&[0, 1, 2, 3, 4]);
+    assert!(matches!(
+        ViewNode::new(&t1, Some(&t3), None),
+        Err(MerkleError::StructMismatch)
+    ));
+}
@@ -0,0 +1,242 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use merkle::consistency::ConsistencyProof;
+use merkle::digest::Digest;
+use merkle::error::MerkleError;
+use merkle::hash::{hash_to_str, str_to_hash};
+use merkle::json::Value;
+use merkle::log::{Log, LogServer};
+use merkle::sm2::{PublicKey, Signature, SignedRoot};
+use merkle::tree::path_positions;
+
+fn log_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("merkle-log-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+// 发送一个HTTP请求，返回状态码和JSON响应
+fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+        method,
+        path,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head[9..12].parse().unwrap();
+    (status, Value::parse(body).unwrap())
+}
+
+fn digest(v: &Value) -> Digest {
+    v.as_str().unwrap().parse().unwrap()
+}
+
+fn digests(v: &Value) -> Vec<Digest> {
+    v.as_array().unwrap().iter().map(digest).collect()
+}
+
+fn get_sth(addr: SocketAddr) -> (SignedRoot, PublicKey) {
+    let (status, sth) = request(addr, "GET", "/ct/v1/get-sth", "");
+    assert_eq!(status, 200);
+    let bytes = |name: &str| str_to_hash(sth.get(name).unwrap().as_str().unwrap()).unwrap();
+    let key = PublicKey::from_bytes(&bytes("public_key")).unwrap();
+    let signed = SignedRoot {
+        root: digest(sth.get("root_hash").unwrap()),
+        leaves: sth.get("tree_size").unwrap().as_u64().unwrap() as usize,
+        blocksize: 0,
+        timestamp: sth.get("timestamp").unwrap().as_u64().unwrap(),
+        signature: Signature::from_bytes(&bytes("tree_head_signature")).unwrap(),
+    };
+    signed.verify(&key).unwrap();
+    (signed, key)
+}
+
+#[test]
+fn log_http_api() {
+    let dir = log_dir("http");
+    let server = LogServer::start(Log::open(&dir).unwrap(), 0, Duration::from_millis(50)).unwrap();
+    let addr = server.addr();
+    assert!(addr.ip().is_loopback());
+
+    let (empty, key) = get_sth(addr);
+    assert_eq!(empty.leaves, 0);
+    assert_eq!(empty.root, Digest::of(&[]));
+
+    let mut old = None;
+    let entries: Vec<Vec<u8>> = (0..7u8).map(|i| vec![i; 3 + i as usize]).collect();
+    for (i, entry) in entries.iter().enumerate() {
+        let body = Value::obj(vec![("entry", Value::Str(hash_to_str(entry)))]).to_string();
+        let (status, added) = request(addr, "POST", "/ct/v1/add-entry", &body);
+        assert_eq!(status, 200);
+        assert_eq!(added.get("leaf_index").unwrap().as_u64(), Some(i as u64));
+        assert_eq!(digest(added.get("leaf_hash").unwrap()), Digest::of(entry));
+        if i == 2 {
+            old = Some(server.log().sign_head(1).unwrap().clone());
+        }
+    }
+    // 重复添加返回原来的下标
+    let body = format!("{{\"entry\": \"{}\"}}", hash_to_str(&entries[1]));
+    let (_, again) = request(addr, "POST", "/ct/v1/add-entry", &body);
+    assert_eq!(again.get("leaf_index").unwrap().as_u64(), Some(1));
+    let old = old.unwrap();
+    assert_eq!(old.leaves, 3);
+
+    // 等待后台线程签名新的树头
+    let sth = loop {
+        let (sth, k) = get_sth(addr);
+        assert_eq!(k, key);
+        if sth.leaves == entries.len() {
+            break sth;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    for (i, entry) in entries.iter().enumerate() {
+        let path = format!(
+            "/ct/v1/get-proof-by-hash?hash={}&tree_size={}",
+            Digest::of(entry),
+            sth.leaves
+        );
+        let (status, proof) = request(addr, "GET", &path, "");
+        assert_eq!(status, 200);
+        assert_eq!(proof.get("leaf_index").unwrap().as_u64(), Some(i as u64));
+        let chain = digests(proof.get("audit_path").unwrap());
+        let pos = path_positions(i, sth.leaves).unwrap();
+        let mut hash = Digest::of(entry);
+        for (h, left) in chain.iter().zip(pos) {
+            hash = if left {
+                Digest::combine(h, &hash)
+            } else {
+                Digest::combine(&hash, h)
+            };
+        }
+        assert_eq!(hash, sth.root);
+    }
+
+    let path = format!(
+        "/ct/v1/get-consistency?first={}&second={}",
+        old.leaves, sth.leaves
+    );
+    let (status, consistency) = request(addr, "GET", &path, "");
+    assert_eq!(status, 200);
+    let proof = ConsistencyProof {
+        old_size: old.leaves,
+        size: sth.leaves,
+        chain: digests(consistency.get("consistency").unwrap()),
+    };
+    proof.verify(&old.root, &sth.root).unwrap();
+
+    // 不发送请求的连接不会阻塞其他连接
+    let idle = TcpStream::connect(addr).unwrap();
+    let start = std::time::Instant::now();
+    assert_eq!(get_sth(addr).0.leaves, sth.leaves);
+    assert!(start.elapsed() < Duration::from_secs(2));
+    drop(idle);
+
+    // 错误的请求
+    let missing = format!(
+        "/ct/v1/get-proof-by-hash?hash={}&tree_size=7",
+        Digest::of(b"no")
+    );
+    assert_eq!(request(addr, "GET", &missing, "").0, 400);
+    assert_eq!(
+        request(addr, "GET", "/ct/v1/get-consistency?first=3&second=9", "").0,
+        400
+    );
+    assert_eq!(
+        request(addr, "POST", "/ct/v1/add-entry", "{\"entry\": 1}").0,
+        400
+    );
+    assert_eq!(request(addr, "POST", "/ct/v1/get-sth", "").0, 405);
+    assert_eq!(request(addr, "GET", "/ct/v1/get-entries", "").0, 404);
+    server.shutdown();
+    assert!(TcpStream::connect(addr).is_err());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn log_survives_reopen() {
+    let dir = log_dir("reopen");
+    let (root, key) = {
+        let mut log = Log::open(&dir).unwrap();
+        for i in 0..5u8 {
+            log.append(&[i; 10]).unwrap();
+        }
+        let sth = log.sign_head(100).unwrap().clone();
+        log.append(b"unsigned").unwrap();
+        (sth.root, *log.public_key())
+    };
+
+    // 模拟追加到一半时中断
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(dir.join("entries"))
+        .unwrap();
+    file.write_all(&[0, 0, 0, 9, 1, 2]).unwrap();
+    drop(file);
+
+    let mut log = Log::open(&dir).unwrap();
+    assert_eq!(log.size(), 6);
+    assert_eq!(*log.public_key(), key);
+    assert_eq!(log.sth().leaves, 5);
+    assert_eq!(log.sth().root, root);
+    assert_eq!(log.append(b"next").unwrap().0, 6);
+    let proof = log.inclusion_proof(&Digest::of(&[3u8; 10]), 5).unwrap();
+    proof.verify(&[3u8; 10], &root).unwrap();
+
+    // 树头被篡改时拒绝打开
+    drop(log);
+    let mut forged = SignedRoot::from_bytes(&fs::read(dir.join("sth")).unwrap()).unwrap();
+    forged.root = Digest::of(b"forged");
+    fs::write(dir.join("sth"), forged.to_bytes()).unwrap();
+    assert!(Log::open(&dir).is_err());
+
+    // 私钥文件全为0时返回错误而不是panic
+    fs::write(dir.join("log.key"), [0u8; 32]).unwrap();
+    assert!(matches!(Log::open(&dir), Err(MerkleError::InvalidData(_))));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn log_keeps_corrupt_entries() {
+    let dir = log_dir("corrupt");
+    {
+        let mut log = Log::open(&dir).unwrap();
+        for i in 0..3u8 {
+            log.append(&[i; 10]).unwrap();
+        }
+        log.sign_head(100).unwrap();
+    }
+    let path = dir.join("entries");
+    let good = fs::read(&path).unwrap();
+    assert_eq!(good.len(), 3 * 14);
+
+    // 第2个条目的长度超过上限，或者长度有误使已签名的条目看起来不完整，
+    // 都拒绝打开并且不改动文件
+    for len in [u32::MAX, 200] {
+        let mut data = good.clone();
+        data[14..18].copy_from_slice(&len.to_be_bytes());
+        fs::write(&path, &data).unwrap();
+        assert!(matches!(Log::open(&dir), Err(MerkleError::InvalidData(_))));
+        assert_eq!(fs::read(&path).unwrap(), data);
+    }
+
+    fs::write(&path, &good).unwrap();
+    assert_eq!(Log::open(&dir).unwrap().size(), 3);
+    let _ = fs::remove_dir_all(&dir);
+}
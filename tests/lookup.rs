@@ -0,0 +1,49 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::digest::Digest;
+use merkle::tree::MerkleTree;
+
+#[test]
+fn find_duplicate_blocks() {
+    let blocks: Vec<Vec<u8>> = [1u8, 2, 3, 2, 4, 2, 1]
+        .iter()
+        .map(|b| vec![*b; 8])
+        .collect();
+    let plain = MerkleTree::new(&blocks, 8);
+    let indexed = MerkleTree::new(&blocks, 8).with_index();
+    assert!(plain.leaf_index.is_none());
+    assert_eq!(indexed.leaf_index.as_ref().unwrap().len(), 4);
+    for tree in [&plain, &indexed] {
+        assert_eq!(tree.find(&Digest::of(&[2u8; 8])), vec![1, 3, 5]);
+        assert_eq!(tree.find(&Digest::of(&[1u8; 8])), vec![0, 6]);
+        assert_eq!(tree.find(&Digest::of(&[4u8; 8])), vec![4]);
+        assert!(tree.find(&Digest::of(b"missing")).is_empty());
+    }
+
+    // push追加的叶子同样加入索引
+    let mut tree = MerkleTree::from_hashes(vec![], 0).with_index();
+    for b in &blocks {
+        tree.push(Digest::of(b));
+    }
+    assert_eq!(tree.leaf_index, indexed.leaf_index);
+    assert_eq!(tree.find(&Digest::of(&[2u8; 8])), vec![1, 3, 5]);
+}
+
+#[test]
+fn prove_by_hash() {
+    let blocks: Vec<Vec<u8>> = (0..13u8).map(|i| vec![i % 5; 16]).collect();
+    let tree = MerkleTree::new(&blocks, 16).with_index();
+    let root = tree.root_hash().unwrap();
+    let proofs = tree.prove_by_hash(&Digest::of(&[3u8; 16])).unwrap();
+    assert_eq!(
+        proofs.iter().map(|p| p.index).collect::<Vec<_>>(),
+        vec![3, 8]
+    );
+    for proof in &proofs {
+        proof.verify(&[3u8; 16], &root).unwrap();
+        assert_eq!(proof.blocksize, 16);
+    }
+    assert!(tree.prove_by_hash(&Digest::of(&[9u8; 16])).is_err());
+}
@@ -0,0 +1,90 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::fs;
+use std::path::PathBuf;
+
+use merkle::manifest::{Manifest, Modified};
+
+fn make_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("merkle-manifest-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("a.txt"), b"hello world").unwrap();
+    let data: Vec<u8> = (0..5000u32).map(|i| (i * 7 % 251) as u8).collect();
+    fs::write(dir.join("sub/b.bin"), data).unwrap();
+    dir
+}
+
+#[test]
+fn manifest_formats_round_trip() {
+    let dir = make_dir("formats");
+    for with_leaves in [false, true] {
+        let manifest = Manifest::generate(&dir, 1024, with_leaves).unwrap();
+        assert_eq!(manifest.entries.len(), 2);
+        assert_eq!(manifest.entries[1].path, "sub/b.bin");
+        assert_eq!(manifest.entries[1].size, 5000);
+        assert_eq!(Manifest::parse(&manifest.to_bytes()).unwrap(), manifest);
+        assert_eq!(
+            Manifest::parse(manifest.to_text().as_bytes()).unwrap(),
+            manifest
+        );
+    }
+
+    // 文件大小超过4GB，哈希必须为32字节
+    let root = "ab".repeat(32);
+    let text = format!(
+        "merkle-manifest 1\nfile 5000000000 1024 4882813 {} big\n",
+        root
+    );
+    assert_eq!(
+        Manifest::from_text(&text).unwrap().entries[0].size,
+        5_000_000_000
+    );
+    let text = format!("merkle-manifest 1\nfile 1 1024 1 {} a\n", &root[..62]);
+    assert!(Manifest::from_text(&text).is_err());
+    // 数据块大小为0的记录单独报告，不影响其他文件的检查
+    let mut manifest = Manifest::generate(&dir, 1024, false).unwrap();
+    manifest.entries[0].blocksize = 0;
+    let report = manifest.check(&dir).unwrap();
+    assert_eq!(report.invalid, vec!["a.txt".to_string()]);
+    assert!(report.modified.is_empty() && report.missing.is_empty());
+    assert!(!report.is_ok());
+
+    // 路径中的换行、回车和反斜杠在文本格式中转义
+    manifest.entries[0].path = "line\nbreak\r\\n x".to_string();
+    assert_eq!(Manifest::from_text(&manifest.to_text()).unwrap(), manifest);
+    let text = format!("merkle-manifest 1\nfile 1 1024 1 {} a\\x\n", root);
+    assert!(Manifest::from_text(&text).is_err());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn manifest_check_reports_changes() {
+    let dir = make_dir("check");
+    let manifest = Manifest::generate(&dir, 1024, true).unwrap();
+    assert!(manifest.check(&dir).unwrap().is_ok());
+
+    let mut data = fs::read(dir.join("sub/b.bin")).unwrap();
+    data[2100] ^= 1;
+    fs::write(dir.join("sub/b.bin"), data).unwrap();
+    fs::remove_file(dir.join("a.txt")).unwrap();
+    fs::write(dir.join("new.txt"), b"new").unwrap();
+
+    let report = manifest.check(&dir).unwrap();
+    assert_eq!(
+        Manifest::default().check(&dir).unwrap().extra,
+        vec!["new.txt".to_string(), "sub/b.bin".to_string()]
+    );
+    assert_eq!(report.missing, vec!["a.txt".to_string()]);
+    assert_eq!(report.extra, vec!["new.txt".to_string()]);
+    assert_eq!(
+        report.modified,
+        vec![Modified {
+            path: "sub/b.bin".to_string(),
+            blocks: Some(vec![2]),
+        }]
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,91 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::chunking::{Blocking, Chunk};
+use merkle::merge::forest_root;
+use merkle::tree::MerkleTree;
+
+fn blocks(range: std::ops::Range<u8>) -> Vec<Vec<u8>> {
+    range.map(|i| vec![i; 4]).collect()
+}
+
+#[test]
+fn concat_aligned() {
+    for sizes in [
+        &[1usize][..],
+        &[4, 4, 3],
+        &[8, 2, 1],
+        &[2, 2, 2, 2, 5],
+        &[16, 7],
+        &[1, 1],
+    ] {
+        let mut start = 0u8;
+        let mut segments = vec![];
+        for n in sizes {
+            segments.push(MerkleTree::new(&blocks(start..start + *n as u8), 4));
+            start += *n as u8;
+        }
+        let whole = MerkleTree::new(&blocks(0..start), 4);
+        let merged = MerkleTree::concat(&segments).unwrap();
+        assert_eq!(merged.nodes, whole.nodes, "{:?}", sizes);
+        assert_eq!(merged.height, whole.height);
+        assert!(merged == whole);
+    }
+
+    // 数据块位置按拼接后的数据偏移，每段末尾都有一个不满的数据块
+    let data: Vec<u8> = (0..100).collect();
+    let blocking = Blocking::Fixed(10);
+    let a = MerkleTree::from_bytes(&data[..70], &blocking).unwrap();
+    let b = MerkleTree::from_bytes(&data[70..], &blocking).unwrap();
+    assert_eq!((a.leaves, b.leaves), (8, 4));
+    let merged = MerkleTree::concat(&[a, b]).unwrap();
+    assert_eq!(merged.chunks.len(), 12);
+    assert_eq!(
+        merged.chunks[8],
+        Chunk {
+            offset: 70,
+            length: 10
+        }
+    );
+    assert_eq!(
+        merged.chunks[11],
+        Chunk {
+            offset: 100,
+            length: 0
+        }
+    );
+}
+
+#[test]
+fn concat_unaligned() {
+    let a = MerkleTree::new(&blocks(0..3), 4);
+    let b = MerkleTree::new(&blocks(3..8), 4);
+    assert!(MerkleTree::concat(&[a, b]).is_err());
+
+    // 起始下标不是该段大小的整数倍
+    let segments = vec![
+        MerkleTree::new(&blocks(0..2), 4),
+        MerkleTree::new(&blocks(2..6), 4),
+    ];
+    assert!(MerkleTree::concat(&segments).is_err());
+    assert!(MerkleTree::concat(&[
+        MerkleTree::new(&blocks(0..2), 8),
+        MerkleTree::new(&blocks(2..4), 4)
+    ])
+    .is_err());
+
+    // 森林根以各段的根为叶子
+    let roots: Vec<_> = segments.iter().map(|t| t.root_hash().unwrap()).collect();
+    assert_eq!(
+        forest_root(&segments).unwrap(),
+        MerkleTree::from_hashes(roots, 0).root_hash().unwrap()
+    );
+    let equal: Vec<MerkleTree> = (0..3)
+        .map(|i| MerkleTree::new(&blocks(i * 4..i * 4 + 4), 4))
+        .collect();
+    assert_eq!(
+        forest_root(&equal).unwrap(),
+        MerkleTree::concat(&equal).unwrap().root_hash().unwrap()
+    );
+}
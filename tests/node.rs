@@ -0,0 +1,86 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::digest::Digest;
+use merkle::node::NodeId;
+use merkle::tree::MerkleTree;
+
+fn tree(leaves: u8) -> MerkleTree {
+    let blocks: Vec<Vec<u8>> = (0..leaves).map(|i| vec![i]).collect();
+    MerkleTree::new(&blocks, 1)
+}
+
+#[test]
+fn navigation() {
+    // 5个叶子: 第4个叶子在第0、1层都被提升
+    let t = tree(5);
+    let root = t.root_id().unwrap();
+    assert_eq!(root, NodeId::new(3, 0));
+    assert_eq!(t.node_hash(root), t.root_hash().ok());
+    assert_eq!(t.parent(root), None);
+    assert_eq!(t.leaf_range(root), Some(0..5));
+
+    let promoted = NodeId::new(1, 2);
+    assert_eq!(
+        t.children(promoted).collect::<Vec<_>>(),
+        vec![NodeId::leaf(4)]
+    );
+    assert_eq!(t.node_hash(promoted), t.node_hash(NodeId::leaf(4)));
+    assert_eq!(t.sibling(NodeId::leaf(4)), None);
+    assert_eq!(t.sibling(promoted), None);
+    assert_eq!(t.sibling(NodeId::new(2, 1)), Some(NodeId::new(2, 0)));
+    assert_eq!(t.leaf_range(NodeId::new(2, 1)), Some(4..5));
+    assert_eq!(t.parent(NodeId::leaf(3)), Some(NodeId::new(1, 1)));
+
+    // 父节点的哈希由子节点组合得到
+    let id = NodeId::new(1, 1);
+    let c: Vec<Digest> = t.children(id).map(|c| t.node_hash(c).unwrap()).collect();
+    assert_eq!(t.node_hash(id), Some(Digest::combine(&c[0], &c[1])));
+
+    // 不存在的节点
+    assert_eq!(t.node_hash(NodeId::new(1, 3)), None);
+    assert_eq!(t.parent(NodeId::new(1, 3)), None);
+    assert_eq!(t.children(NodeId::leaf(9)).count(), 0);
+    assert_eq!(t.leaf_range(NodeId::new(4, 0)), None);
+}
+
+#[test]
+fn traversal() {
+    let t = tree(5);
+    let bfs: Vec<NodeId> = t.bfs().map(|(id, _)| id).collect();
+    assert_eq!(bfs.len(), 1 + 2 + 3 + 5);
+    assert_eq!(
+        &bfs[..3],
+        &[NodeId::new(3, 0), NodeId::new(2, 0), NodeId::new(2, 1)]
+    );
+
+    let dfs: Vec<(usize, usize)> = t.dfs().map(|(id, _)| (id.level, id.index)).collect();
+    assert_eq!(
+        dfs,
+        vec![
+            (3, 0),
+            (2, 0),
+            (1, 0),
+            (0, 0),
+            (0, 1),
+            (1, 1),
+            (0, 2),
+            (0, 3),
+            (2, 1),
+            (1, 2),
+            (0, 4)
+        ]
+    );
+    for (id, hash) in t.dfs() {
+        assert_eq!(t.node_hash(id), Some(hash));
+    }
+
+    let leaves: Vec<Digest> = t.level_nodes(0).map(|(_, h)| h).collect();
+    assert_eq!(leaves, t.nodes[0]);
+    assert_eq!(t.level_nodes(9).count(), 0);
+
+    let empty = tree(0);
+    assert_eq!(empty.root_id(), None);
+    assert_eq!(empty.dfs().count() + empty.bfs().count(), 0);
+}
@@ -0,0 +1,150 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::digest::Digest;
+use merkle::sm2::{Curve, PrivateKey, PublicKey, Rng, Signature, SignedRoot, Sm2Error};
+use merkle::tree::MerkleTree;
+
+fn hex(s: &str) -> [u8; 32] {
+    s.replace(' ', "").parse::<Digest>().unwrap().0
+}
+
+// 依次返回给定的字节，用于重现标准中的随机数k
+struct Fixed(Vec<u8>);
+
+impl Rng for Fixed {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Sm2Error> {
+        if self.0.len() < dest.len() {
+            return Err(Sm2Error::Rng);
+        }
+        let rest = self.0.split_off(dest.len());
+        dest.copy_from_slice(&self.0);
+        self.0 = rest;
+        Ok(())
+    }
+}
+
+// 测试用的xorshift，只用于生成测试数据
+struct XorShift(u64);
+
+impl Rng for XorShift {
+    fn fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Sm2Error> {
+        for b in dest {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            *b = self.0 as u8;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn standard_example() {
+    // GB/T 32918.2 附录A.2 示例(Fp-256)
+    let curve = Curve::new(
+        &hex("8542D69E 4C044F18 E8B92435 BF6FF7DE 45728391 5C45517D 722EDB8B 08F1DFC3"),
+        &hex("787968B4 FA32C3FD 2417842E 73BBFEFF 2F3C848B 6831D7E0 EC65228B 3937E498"),
+        &hex("63E4C6D3 B23B0C84 9CF84241 484BFE48 F61D59A5 B16BA06E 6E12D1DA 27C5249A"),
+        &hex("421DEBD6 1B62EAB6 746434EB C3CC315E 32220B3B ADD50BDC 4C4E6C14 7FEDD43D"),
+        &hex("0680512B CBB42C07 D47349D2 153B70C4 E5D7FDFC BFA36EA1 A85841B9 E46E09A2"),
+        &hex("8542D69E 4C044F18 E8B92435 BF6FF7DD 29772063 0485628D 5AE74EE7 C32E79B7"),
+    );
+    let id = b"ALICE123@YAHOO.COM";
+    let key = PrivateKey(hex(
+        "128B2FA8 BD433C6C 068C8D80 3DFF7979 2A519A55 171B1B65 0C23661D 15897263",
+    ));
+    let public = curve.public_key(&key).unwrap();
+    assert_eq!(
+        public,
+        PublicKey {
+            x: hex("0AE4C779 8AA0F119 471BEE11 825BE462 02BB79E2 A5844495 E97C04FF 4DF2548A"),
+            y: hex("7C0240F8 8F1CD4E1 6352A73C 17B7F16F 07353E53 A176D684 A9FE0C6B B798E857"),
+        }
+    );
+    assert_eq!(
+        curve.z_a(id, &public).unwrap(),
+        hex("F4A38489 E32B45B6 F876E3AC 2168CA39 2362DC8F 23459C1D 1146FC3D BFB7BC9A")
+    );
+
+    let k = hex("6CB28D99 385C175C 94F94E93 4817663F C176D925 DD72B727 260DBAAE 1FB2F96F");
+    let sig = curve
+        .sign(&key, id, b"message digest", &mut Fixed(k.to_vec()))
+        .unwrap();
+    assert_eq!(
+        sig,
+        Signature {
+            r: hex("40F1EC59 F793D9F4 9E09DCEF 49130D41 94F79FB1 EED2CAA5 5BACDB49 C4E755D1"),
+            s: hex("6FC6DAC3 2C5D5CF1 0C77DFB2 0F7C2EB6 67A45787 2FB09EC5 6327A67E C7DEEBE7"),
+        }
+    );
+    assert!(curve.verify(&public, id, b"message digest", &sig));
+    assert!(!curve.verify(&public, id, b"message digesT", &sig));
+    assert!(!curve.verify(&public, b"BOB", b"message digest", &sig));
+
+    // 私钥不在[1, n-2]中、用户标识过长时返回错误而不是panic
+    assert_eq!(
+        curve.public_key(&PrivateKey([0; 32])),
+        Err(Sm2Error::InvalidKey)
+    );
+    let n_minus_1 = hex("8542D69E 4C044F18 E8B92435 BF6FF7DD 29772063 0485628D 5AE74EE7 C32E79B6");
+    assert_eq!(
+        curve.public_key(&PrivateKey(n_minus_1)),
+        Err(Sm2Error::InvalidKey)
+    );
+    assert_eq!(curve.z_a(&[0; 8192], &public), Err(Sm2Error::IdTooLong));
+    // 随机数来源用尽时签名失败
+    assert_eq!(
+        curve.sign(&key, id, b"message digest", &mut Fixed(vec![])),
+        Err(Sm2Error::Rng)
+    );
+    assert!(!curve.verify(&public, &[0; 8192], b"message digest", &sig));
+}
+
+#[test]
+fn signed_root() {
+    // 推荐曲线上的密钥对(GM/T 0003示例)
+    let curve = Curve::sm2();
+    let key = PrivateKey(hex(
+        "3945208F 7B2144B1 3F36E38A C6D39F95 88939369 2860B51A 42FB81EF 4DF7C5B8",
+    ));
+    assert_eq!(
+        curve.public_key(&key).unwrap(),
+        PublicKey {
+            x: hex("09F9DF31 1E5421A1 50DD7D16 1E4BC5C6 72179FAD 1833FC07 6BB08FF3 56F35020"),
+            y: hex("CCEA490C E26775A5 2DC6EA71 8CC1AA60 0AED05FB F35E084A 6632F607 2DA9AD13"),
+        }
+    );
+
+    let mut rng = XorShift(0x2545_f491_4f6c_dd1d);
+    let key = curve.generate_key(&mut rng).unwrap();
+    let public = curve.public_key(&key).unwrap();
+    assert_eq!(PublicKey::from_bytes(&public.to_bytes()), Some(public));
+
+    let blocks: Vec<Vec<u8>> = (0..5u8).map(|i| vec![i; 16]).collect();
+    let tree = MerkleTree::new(&blocks, 16);
+    let signed = SignedRoot::for_tree(&tree, 1_700_000_000, &key, &mut rng).unwrap();
+    assert_eq!(signed.root, tree.root_hash().unwrap());
+    assert!(signed.verify(&public).is_ok());
+
+    let decoded = SignedRoot::from_bytes(&signed.to_bytes()).unwrap();
+    assert_eq!(decoded, signed);
+    assert!(decoded.verify(&public).is_ok());
+
+    // 改动任何一个字段或使用其他公钥都验证失败
+    let mut forged = signed.clone();
+    forged.leaves = 6;
+    assert!(forged.verify(&public).is_err());
+    let mut forged = signed.clone();
+    forged.timestamp += 1;
+    assert!(forged.verify(&public).is_err());
+    let other = curve
+        .public_key(&curve.generate_key(&mut rng).unwrap())
+        .unwrap();
+    assert!(signed.verify(&other).is_err());
+    let mut bad = public;
+    bad.y[31] ^= 1;
+    assert!(signed.verify(&bad).is_err());
+    assert!(SignedRoot::from_bytes(&signed.to_bytes()[1..]).is_err());
+}
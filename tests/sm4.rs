@@ -0,0 +1,87 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::config::data_to_blocks;
+use merkle::hash::str_to_hash;
+use merkle::proof::Proof;
+use merkle::sm4::{decrypt_block, encrypt_blocks, Gcm, Sm4};
+
+fn hex(s: &str) -> Vec<u8> {
+    str_to_hash(s).unwrap()
+}
+
+fn key(s: &str) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    key.copy_from_slice(&hex(s));
+    key
+}
+
+#[test]
+fn sm4_vectors() {
+    // GB/T 32907 附录A示例1
+    let k = key("0123456789abcdeffedcba9876543210");
+    let cipher = Sm4::new(&k);
+    let mut block = k;
+    cipher.encrypt_block(&mut block);
+    assert_eq!(block.to_vec(), hex("681edf34d206965e86b3e94f536e4246"));
+    cipher.decrypt_block(&mut block);
+    assert_eq!(block, k);
+
+    // CTR模式加密和解密相同，长度不必是分组的整数倍
+    let mut data = b"counter mode works on any length".to_vec();
+    let counter = [7u8; 16];
+    cipher.ctr(&counter, &mut data);
+    assert_ne!(&data[..], b"counter mode works on any length");
+    cipher.ctr(&counter, &mut data);
+    assert_eq!(&data[..], b"counter mode works on any length");
+
+    // RFC 8998 附录A.1 SM4-GCM
+    let gcm = Gcm::new(&k);
+    let mut nonce = [0u8; 12];
+    nonce.copy_from_slice(&hex("00001234567800000000abcd"));
+    let aad = hex("feedfacedeadbeeffeedfacedeadbeefabaddad2");
+    let plain = hex(concat!(
+        "aaaaaaaaaaaaaaaabbbbbbbbbbbbbbbbccccccccccccccccdddddddddddddddd",
+        "eeeeeeeeeeeeeeeeffffffffffffffffeeeeeeeeeeeeeeeeaaaaaaaaaaaaaaaa"
+    ));
+    let mut data = plain.clone();
+    let tag = gcm.encrypt(&nonce, &aad, &mut data);
+    assert_eq!(
+        data,
+        hex(concat!(
+            "17f399f08c67d5ee19d0dc9969c4bb7d5fd46fd3756489069157b282bb200735",
+            "d82710ca5c22f0ccfa7cbf93d496ac15a56834cbcf98c397b4024a2691233b8d"
+        ))
+    );
+    assert_eq!(tag.to_vec(), hex("83de3541e4c2b58177e065a9bf7b62ec"));
+    assert!(!gcm.decrypt(&nonce, &aad[1..], &mut data, &tag));
+    assert!(gcm.decrypt(&nonce, &aad, &mut data, &tag));
+    assert_eq!(data, plain);
+}
+
+#[test]
+fn encrypted_tree() {
+    let k = key("0123456789abcdeffedcba9876543210");
+    let prefix = *b"arc1";
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 31) as u8).collect();
+    let blocks = data_to_blocks(&data, 256);
+    let enc = encrypt_blocks(&k, &prefix, &blocks, 256);
+    assert_eq!(enc.tree.leaves, blocks.len());
+
+    // 存储节点只有密文，也能生成和验证proof
+    let root = enc.tree.root_hash().unwrap();
+    let proof = Proof::new(&enc.tree, enc.blocks[2].clone(), 2, 256).unwrap();
+    assert!(proof.verify(&root).is_ok());
+
+    for (i, block) in enc.blocks.iter().enumerate() {
+        assert_eq!(decrypt_block(&k, &prefix, i, block).unwrap(), blocks[i]);
+    }
+    // 换位置、篡改密文或使用其他前缀都无法解密
+    assert!(decrypt_block(&k, &prefix, 1, &enc.blocks[0]).is_err());
+    let mut bad = enc.blocks[0].clone();
+    bad[0] ^= 1;
+    assert!(decrypt_block(&k, &prefix, 0, &bad).is_err());
+    assert!(decrypt_block(&k, b"arc2", 0, &enc.blocks[0]).is_err());
+    assert!(decrypt_block(&k, &prefix, 0, &[0u8; 3]).is_err());
+}
@@ -0,0 +1,91 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use std::fs;
+use std::path::PathBuf;
+
+use merkle::store::Store;
+
+fn store_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("merkle-store-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn data(seed: u32, len: usize) -> Vec<u8> {
+    (0..len as u32)
+        .map(|i| ((i / 64 + seed) * 31 % 251) as u8)
+        .collect()
+}
+
+#[test]
+fn put_get_dedup() {
+    let dir = store_dir("dedup");
+    let mut store = Store::open(&dir, 64).unwrap();
+
+    // 每个数据块内的字节相同，两个文件共享大部分数据块
+    let a = data(0, 640);
+    let mut b = a.clone();
+    b.extend_from_slice(&data(5, 100));
+    let first = store.put_file("a", &a).unwrap();
+    assert_eq!(first.blocks, 11);
+    let second = store.put_file("dir/b", &b).unwrap();
+    assert_eq!(second.blocks, 12);
+    assert!(second.new_blocks < 3);
+    assert_eq!(store.put_file("a", &a).unwrap().new_blocks, 0);
+
+    let stats = store.stats().unwrap();
+    assert_eq!(stats.files, 2);
+    assert_eq!(stats.logical_bytes, 640 + 740);
+    assert_eq!(stats.block_refs, 23);
+    assert_eq!(stats.stored_bytes, first.new_bytes + second.new_bytes);
+    assert!(stats.dedup_ratio() > 1.5);
+
+    // 重新打开后内容不变
+    drop(store);
+    let store = Store::open(&dir, 64).unwrap();
+    assert_eq!(store.get_file("a").unwrap(), a);
+    assert_eq!(store.get_file("dir/b").unwrap(), b);
+    assert_eq!(store.get_entry("dir/b").unwrap().root, second.root);
+    assert!(store.get_file("c").is_err());
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn gc_and_corruption() {
+    let dir = store_dir("gc");
+    let mut store = Store::open(&dir, 32).unwrap();
+    let a = data(1, 200);
+    let b = data(100, 200);
+    store.put_file("a", &a).unwrap();
+    store.put_file("b", &b).unwrap();
+    let before = store.stats().unwrap();
+
+    // 没有删除文件时不回收任何数据块
+    assert_eq!(store.gc().unwrap().removed_blocks, 0);
+    assert!(store.remove_file("b").unwrap());
+    assert!(!store.remove_file("b").unwrap());
+    let gc = store.gc().unwrap();
+    let after = store.stats().unwrap();
+    assert_eq!(
+        gc.removed_blocks,
+        before.unique_blocks - after.unique_blocks
+    );
+    assert_eq!(gc.freed_bytes, before.stored_bytes - after.stored_bytes);
+    assert_eq!(store.get_file("a").unwrap(), a);
+
+    // 被篡改或丢失的数据块在读取时被发现
+    let leaf = store.get_entry("a").unwrap().leaf_hashes.as_ref().unwrap()[2];
+    let hex = leaf.to_string();
+    let path = dir.join("blocks").join(&hex[..2]).join(&hex[2..]);
+    fs::write(&path, b"tampered").unwrap();
+    assert!(store.get_file("a").is_err());
+    // 再次保存时重新写入损坏的数据块
+    let put = store.put_file("a", &a).unwrap();
+    assert_eq!((put.new_blocks, put.new_bytes), (1, 32));
+    assert_eq!(store.get_file("a").unwrap(), a);
+    fs::remove_file(&path).unwrap();
+    assert!(store.get_file("a").is_err());
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,68 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+use merkle::digest::Digest;
+use merkle::node::NodeId;
+use merkle::subtree::ShardTree;
+use merkle::tree::MerkleTree;
+
+fn blocks(n: u8) -> Vec<Vec<u8>> {
+    (0..n).map(|i| vec![i; 4]).collect()
+}
+
+#[test]
+fn subtree_proof() {
+    let data = blocks(11);
+    let tree = MerkleTree::new(&data, 4);
+    let root = tree.root_hash().unwrap();
+
+    // 第2层的节点覆盖4个数据块，最后一个只有3个
+    for index in 0..3 {
+        let proof = tree.subtree_proof(2, index).unwrap();
+        let range = proof.leaf_range();
+        let shard = MerkleTree::new(&data[range], 4);
+        let shard_root = shard.root_hash().unwrap();
+        assert_eq!(tree.subtree_root(2, index).unwrap(), shard_root);
+        assert!(proof.verify(&shard_root, &root).is_ok());
+        assert!(proof.verify(&Digest::of(b"x"), &root).is_err());
+    }
+    assert!(tree.subtree_root(2, 3).is_err());
+    assert!(tree.subtree_proof(5, 0).is_err());
+
+    // 叶子的子树proof与普通proof相同，改写节点位置后验证失败
+    let mut proof = tree.subtree_proof(0, 9).unwrap();
+    assert_eq!(
+        (proof.chain.clone(), proof.pos_chain.clone()),
+        tree.gen_proof(9).unwrap()
+    );
+    proof.node = NodeId::new(1, 4);
+    assert!(proof
+        .verify(&tree.subtree_root(0, 9).unwrap(), &root)
+        .is_err());
+
+    // 把子树根换成其他节点，缓存的根哈希不能让它通过验证
+    let mut proof = tree.subtree_proof(2, 1).unwrap();
+    let other = tree.subtree_root(2, 0).unwrap();
+    proof.hash = other;
+    assert!(proof.verify(&other, &root).is_err());
+}
+
+#[test]
+fn shard_tree() {
+    let data = blocks(11);
+    let tree = MerkleTree::new(&data, 4);
+    let roots: Vec<Digest> = data
+        .chunks(4)
+        .map(|c| MerkleTree::new(c, 4).root_hash().unwrap())
+        .collect();
+    let shards = ShardTree::new(&roots, 2, 11).unwrap();
+    assert_eq!(shards.root_hash().unwrap(), tree.root_hash().unwrap());
+    for (i, shard_root) in roots.iter().enumerate() {
+        let proof = shards.proof(i).unwrap();
+        assert_eq!(proof, tree.subtree_proof(2, i).unwrap());
+        assert!(proof.verify(shard_root, &tree.root_hash().unwrap()).is_ok());
+    }
+    assert!(ShardTree::new(&roots, 2, 13).is_err());
+    assert!(ShardTree::new(&roots[..2], 2, 11).is_err());
+}
@@ -0,0 +1,181 @@
+#![cfg(test)]
+
+extern crate merkle;
+
+mod common;
+
+use std::io::{self, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+use merkle::chunking::Blocking;
+use merkle::error::MerkleError;
+use merkle::sync::{serve, sync, SyncResult};
+use merkle::tree::MerkleTree;
+
+use common::random_data;
+
+// 在本地TCP端口上启动服务端，用客户端同步
+fn sync_over_tcp(old: &[u8], new: Vec<u8>, blocksize: usize) -> SyncResult {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let tree = MerkleTree::from_bytes(&new, &Blocking::Fixed(blocksize)).unwrap();
+        serve(&mut stream, &new, &tree).unwrap();
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let tree = MerkleTree::from_bytes(old, &Blocking::Fixed(blocksize)).unwrap();
+    let result = sync(&mut stream, old, &tree).unwrap();
+    server.join().unwrap();
+    result
+}
+
+#[test]
+fn sync_changed_blocks() {
+    let old = random_data(64 * 1024, 1);
+    let mut new = old.clone();
+    new[100] ^= 1;
+    new[40_000] ^= 1;
+    let result = sync_over_tcp(&old, new.clone(), 1024);
+    assert_eq!(result.data, new);
+    assert_eq!(result.changed, vec![0, 39]);
+    assert!(result.nodes_fetched < 30);
+}
+
+#[test]
+fn sync_identical() {
+    let old = random_data(10_000, 2);
+    let result = sync_over_tcp(&old, old.clone(), 256);
+    assert_eq!(result.data, old);
+    assert!(result.changed.is_empty());
+}
+
+#[test]
+fn sync_different_length() {
+    let old = random_data(10_000, 3);
+    let new = random_data(15_000, 4);
+    let result = sync_over_tcp(&old, new.clone(), 512);
+    assert_eq!(result.data, new);
+
+    // 数据块数量超过一次请求的上限时分批获取
+    let new = random_data(66_000, 6);
+    let result = sync_over_tcp(&old, new.clone(), 16);
+    assert_eq!(result.data, new);
+    assert!(result.changed.len() > 4096);
+}
+
+// 内存中的服务端：把客户端写入的请求交给serve处理，再按tamper修改每条回复的内容
+struct LyingServer<F: FnMut(u8, &mut Vec<u8>)> {
+    data: Vec<u8>,
+    tree: MerkleTree,
+    requests: Vec<u8>,
+    replies: Cursor<Vec<u8>>,
+    tamper: F,
+}
+
+// serve使用的请求和回复
+struct Pipe<'a> {
+    input: &'a [u8],
+    output: Vec<u8>,
+}
+
+impl Read for Pipe<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Pipe<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(u8, &mut Vec<u8>)> Read for LyingServer<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.replies.position() as usize == self.replies.get_ref().len() {
+            let mut pipe = Pipe {
+                input: &self.requests,
+                output: Vec::new(),
+            };
+            serve(&mut pipe, &self.data, &self.tree).unwrap();
+            let mut replies = Vec::new();
+            let mut frames = &pipe.output[..];
+            while !frames.is_empty() {
+                let len = u32::from_be_bytes([frames[0], frames[1], frames[2], frames[3]]);
+                let kind = frames[4];
+                let mut body = frames[5..4 + len as usize].to_vec();
+                frames = &frames[4 + len as usize..];
+                (self.tamper)(kind, &mut body);
+                replies.extend_from_slice(&((body.len() + 1) as u32).to_be_bytes());
+                replies.push(kind);
+                replies.extend_from_slice(&body);
+            }
+            self.requests.clear();
+            self.replies = Cursor::new(replies);
+        }
+        self.replies.read(buf)
+    }
+}
+
+impl<F: FnMut(u8, &mut Vec<u8>)> Write for LyingServer<F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.requests.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn sync_with_liar<F: FnMut(u8, &mut Vec<u8>)>(tamper: F) -> Result<SyncResult, MerkleError> {
+    let old = random_data(16 * 1024, 5);
+    let mut new = old.clone();
+    new[5000] ^= 1;
+    let mut server = LyingServer {
+        tree: MerkleTree::from_bytes(&new, &Blocking::Fixed(1024)).unwrap(),
+        data: new,
+        requests: Vec::new(),
+        replies: Cursor::new(Vec::new()),
+        tamper,
+    };
+    let tree = MerkleTree::from_bytes(&old, &Blocking::Fixed(1024)).unwrap();
+    sync(&mut server, &old, &tree)
+}
+
+#[test]
+fn sync_rejects_lying_server() {
+    // 不修改时与TCP同步的结果相同
+    let result = sync_with_liar(|_, _| {}).unwrap();
+    assert_eq!(result.changed, vec![4]);
+
+    // 修改返回的节点哈希(0x82为NODES消息)
+    let result = sync_with_liar(|kind, body| {
+        if kind == 0x82 {
+            *body.last_mut().unwrap() ^= 1;
+        }
+    });
+    assert!(matches!(result, Err(MerkleError::VerifyFailed(_))));
+
+    // 修改返回的数据块内容(0x83为BLOCKS消息)，跳过数量和长度前缀
+    let result = sync_with_liar(|kind, body| {
+        if kind == 0x83 {
+            body[3] ^= 1;
+        }
+    });
+    assert!(matches!(result, Err(MerkleError::VerifyFailed(_))));
+
+    // 声明极大的叶子数量(0x81为ROOT消息，第一个字段为叶子数量)时不按它分配内存
+    let result = sync_with_liar(|kind, body| {
+        if kind == 0x81 {
+            body.splice(..1, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        }
+    });
+    assert!(result.is_err());
+}